@@ -0,0 +1,64 @@
+//! Lex, parse and assemble programs of 1k, 10k and 100k instructions, run with `cargo bench`
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Runs of each measurement, the mean is reported
+const RUNS: u32 = 10;
+
+fn main() {
+    for instructions in [1_000, 10_000, 100_000] {
+        let source = program(instructions);
+
+        // Warm up and check the output once
+        let bytecode = easm::assemble(&source).unwrap();
+        assert_eq!(bytecode.len(), instructions / 2 * 8);
+
+        let lex = measure(|| easm::lex(&source).unwrap());
+        report("lex", instructions, source.len(), lex);
+
+        // Parsing starts from the tokens of the lexer, so each run gets its own copy
+        let lexer = easm::lex(&source).unwrap();
+        let parse = measure(|| {
+            let mut tokens = lexer.result.clone();
+            easm::Resolver::new().resolve(&mut tokens).unwrap();
+            easm::Parser::new(&tokens).parse().unwrap()
+        });
+        report("parse", instructions, source.len(), parse);
+
+        let assemble = measure(|| easm::assemble(&source).unwrap());
+        report("assemble", instructions, source.len(), assemble);
+    }
+}
+
+/// A program of PUSHes and POPs, half of them each
+fn program(instructions: usize) -> String {
+    (0..instructions / 2)
+        .map(|i| format!("push2 0x{:04x} pop\n", i % 0x10000))
+        .collect()
+}
+
+/// Mean time of a run of `f`
+fn measure<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(f());
+    }
+
+    start.elapsed() / RUNS
+}
+
+fn report(stage: &str, instructions: usize, bytes: usize, time: Duration) {
+    let seconds = time.as_secs_f64();
+
+    println!(
+        "{:<8} {:>7} instructions: {:>10.3?} per run, {:>6.1} MB/s, {:>5.1}M instructions/s",
+        stage,
+        instructions,
+        time,
+        bytes as f64 / seconds / 1e6,
+        instructions as f64 / seconds / 1e6
+    );
+}
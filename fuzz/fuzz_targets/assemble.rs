@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Any input must give bytecode or an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        if let Ok(hex) = easm::assemble(source) {
+            // Bytecode the assembler emits disassembles and assembles again
+            let bytes: Vec<u8> = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect();
+            let _ = easm::assemble(&easm::disassemble(&bytes));
+        }
+    }
+
+    let _ = easm::disassemble(data);
+});
@@ -0,0 +1,392 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{Instruction, Op};
+
+/// Maximum size of deployed contract code, see EIP-170
+pub const MAX_CODE_SIZE: usize = 24576;
+
+/// Maximum size of the init code of a contract creation, see EIP-3860
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Maximum number of items on the EVM stack
+pub const MAX_STACK_DEPTH: usize = 1024;
+
+/// Static gas cost of a program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasEstimate {
+    /// Summed gas of every instruction with a fixed cost
+    pub total: u64,
+
+    /// Instructions with a dynamic cost, which are not part of `total`
+    pub dynamic: usize,
+}
+
+/// Sum the static gas cost of the resolved tokens
+pub fn estimate_gas(tokens: &[(Op, &str, usize)]) -> GasEstimate {
+    let mut estimate = GasEstimate::default();
+
+    for (op, _, _) in tokens {
+        match op {
+            // Operands and literals are not executed
+            Op::VALUE(_) | Op::DATA(_) => {}
+
+            op if op.has_dynamic_gas() => estimate.dynamic += 1,
+
+            op => estimate.total += op.base_gas(),
+        }
+    }
+
+    estimate
+}
+
+/// Result of simulating the stack height over the program as straight-line code,
+/// jumps are not followed so this is a best-effort check
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackReport {
+    /// First instruction that pops more items than the stack holds
+    pub underflow: Option<StackUnderflow>,
+
+    /// First DUP or SWAP that reaches deeper than the stack, which always faults at runtime
+    pub too_deep: Option<StackUnderflow>,
+
+    /// Stack height after the last instruction
+    pub height: usize,
+
+    /// Highest stack height reached, which the EVM limits to `MAX_STACK_DEPTH`
+    pub max_height: usize,
+
+    /// First STOP, or the end of the code, reached with items still on the stack. Only the
+    /// code that runs from the start without a jump is looked at, after a jump or a
+    /// JUMPDEST the height depends on where the code came from and after an underflow it
+    /// counts items that are not there
+    pub leftover: Option<Leftover>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackUnderflow {
+    pub line: usize,
+    pub op: Op,
+
+    /// Items the instruction pops
+    pub needed: u8,
+
+    /// Items on the stack before the instruction
+    pub available: usize,
+}
+
+/// Track the stack height across the resolved tokens as if they ran in order
+pub fn check_stack(tokens: &[(Op, &str, usize)]) -> StackReport {
+    let mut report = StackReport::default();
+    let mut straight = true;
+
+    for (op, _, line) in tokens {
+        // A label is the JUMPDEST it assembles to
+        straight &= !matches!(op, Op::JUMPDEST | Op::LABEL(_));
+
+        let (pops, pushes) = op.stack_io();
+
+        if pops as usize > report.height {
+            let underflow = StackUnderflow {
+                line: *line,
+                op: op.clone(),
+                needed: pops,
+                available: report.height,
+            };
+
+            if report.too_deep.is_none() && (op.is_dup().is_some() || op.is_swap().is_some()) {
+                report.too_deep = Some(underflow.clone());
+            }
+
+            if report.underflow.is_none() {
+                report.underflow = Some(underflow);
+            }
+
+            // Keep going as if the missing items were there, but they are made up so the
+            // items left at the end are not known
+            report.height = pops as usize;
+            straight = false;
+        }
+
+        report.height = report.height - pops as usize + pushes as usize;
+        report.max_height = report.max_height.max(report.height);
+
+        if straight && *op == Op::STOP && report.height > 0 {
+            report.leftover = Some(Leftover {
+                line: *line,
+                items: report.height,
+                stop: true,
+            });
+        }
+
+        straight &= *op != Op::JUMP && !op.is_terminating();
+    }
+
+    // Code that still runs straight at the end of the program runs off it
+    if let Some((_, _, line)) = tokens.last().filter(|_| straight && report.height > 0) {
+        report.leftover = Some(Leftover {
+            line: *line,
+            items: report.height,
+            stop: false,
+        });
+    }
+
+    report
+}
+
+/// Items left on the stack where the code stops, which is often a value that was meant
+/// to be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leftover {
+    pub line: usize,
+
+    /// Items on the stack
+    pub items: usize,
+
+    /// Is it a STOP, rather than the code running off its end
+    pub stop: bool,
+}
+
+/// Stack height before and after every instruction as straight-line code, starting
+/// from an empty stack. Heights go negative where the code pops more than it pushed
+pub fn stack_heights(instructions: &[Instruction]) -> Vec<(isize, isize)> {
+    let mut height = 0;
+
+    instructions
+        .iter()
+        .map(|instruction| {
+            let (pops, pushes) = instruction.op.stack_io();
+            let before = height;
+            height += pushes as isize - pops as isize;
+
+            (before, height)
+        })
+        .collect()
+}
+
+/// Size and opcode usage of a program
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Length of the bytecode in bytes
+    pub size: usize,
+
+    /// Executable instructions, bare values and data are not counted
+    pub instructions: usize,
+
+    /// Uses of every opcode, most used first
+    pub histogram: Vec<(String, usize)>,
+}
+
+/// Count the bytes and the opcodes of the parsed instructions
+pub fn stats(instructions: &[Instruction]) -> Stats {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut stats = Stats::default();
+
+    for instruction in instructions {
+        stats.size += instruction.size();
+
+        let mnemonic = match &instruction.op {
+            Op::VALUE(_) | Op::DATA(_) => continue,
+
+            // A label is emitted as a JUMPDEST
+            Op::LABEL(_) => "JUMPDEST".to_string(),
+
+            op => op.to_string(),
+        };
+
+        stats.instructions += 1;
+        *counts.entry(mnemonic).or_default() += 1;
+    }
+
+    stats.histogram = counts.into_iter().collect();
+    stats
+        .histogram
+        .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    stats
+}
+
+/// Opcodes of the EVM a program uses and those it does not, in the order of their byte
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    pub used: Vec<Op>,
+    pub unused: Vec<Op>,
+}
+
+/// Split every opcode by whether the parsed instructions use it, a label counts as a JUMPDEST
+pub fn coverage(instructions: &[Instruction]) -> Coverage {
+    let used: BTreeSet<u8> = instructions
+        .iter()
+        .filter_map(|instruction| instruction.op.opcode())
+        .collect();
+
+    let (used, unused) = (0..=u8::MAX)
+        .filter_map(Op::from_byte)
+        .partition(|op| op.opcode().is_some_and(|byte| used.contains(&byte)));
+
+    Coverage { used, unused }
+}
+
+/// An instruction that no execution can reach
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unreachable {
+    pub line: usize,
+
+    /// Column starting at 1, 0 when it is not known
+    pub column: usize,
+
+    pub op: Op,
+
+    /// Terminating instruction that comes before it
+    pub after: Op,
+}
+
+/// Find the first instruction after a terminating one with no JUMPDEST in between,
+/// jumps are not followed so only a JUMPDEST makes code reachable again
+pub fn find_unreachable(instructions: &[Instruction]) -> Option<Unreachable> {
+    let mut terminated: Option<&Op> = None;
+
+    for instruction in instructions {
+        match (&instruction.op, terminated) {
+            // Data after the code is never executed, so it is not dead code
+            (Op::DATA(_), _) => {}
+
+            (Op::JUMPDEST | Op::LABEL(_), _) => terminated = None,
+
+            (op, Some(after)) => {
+                return Some(Unreachable {
+                    line: instruction.line,
+                    column: instruction.column,
+                    op: op.clone(),
+                    after: after.clone(),
+                })
+            }
+
+            (op, None) if op.is_terminating() => terminated = Some(op),
+
+            (_, None) => {}
+        }
+    }
+
+    None
+}
+
+/// Does the code end with a terminating opcode rather than running off its end,
+/// data after the last opcode is not executed
+pub fn ends_with_terminator(instructions: &[Instruction]) -> bool {
+    instructions
+        .iter()
+        .rfind(|instruction| !matches!(instruction.op, Op::DATA(_)))
+        .is_some_and(|instruction| instruction.op.is_terminating())
+}
+
+/// A constant jump whose target is not a JUMPDEST
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadJump {
+    pub line: usize,
+
+    /// Column starting at 1, 0 when it is not known
+    pub column: usize,
+
+    pub op: Op,
+
+    /// Byte offset the pushed constant points at
+    pub target: usize,
+}
+
+/// Check that every PUSH immediately followed by a JUMP or JUMPI pushes the offset of
+/// a JUMPDEST, jumps to computed targets can not be checked
+pub fn check_jumps(instructions: &[Instruction]) -> Vec<BadJump> {
+    let jumpdests: BTreeSet<usize> = offsets(instructions)
+        .zip(instructions)
+        .filter(|(_, instruction)| matches!(instruction.op, Op::JUMPDEST | Op::LABEL(_)))
+        .map(|(offset, _)| offset)
+        .collect();
+
+    jumps(instructions)
+        .filter_map(|(_, jump, target)| {
+            let target = target?;
+
+            (!jumpdests.contains(&target)).then(|| BadJump {
+                line: jump.line,
+                column: jump.column,
+                op: jump.op.clone(),
+                target,
+            })
+        })
+        .collect()
+}
+
+/// Where every jump of a program goes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlFlow {
+    /// Every JUMPDEST and constant target with the offsets of the jumps to it
+    pub targets: BTreeMap<usize, Vec<usize>>,
+
+    /// Offsets of the jumps whose target is computed at runtime
+    pub dynamic: Vec<usize>,
+}
+
+/// Collect which jumps go to each JUMPDEST, a jump right after a PUSH goes to the pushed
+/// constant and any other jump is dynamic
+pub fn control_flow(instructions: &[Instruction]) -> ControlFlow {
+    let mut flow = ControlFlow::default();
+
+    for (offset, instruction) in offsets(instructions).zip(instructions) {
+        if matches!(instruction.op, Op::JUMPDEST | Op::LABEL(_)) {
+            flow.targets.insert(offset, vec![]);
+        }
+    }
+
+    for (offset, _, target) in jumps(instructions) {
+        match target {
+            Some(target) => flow.targets.entry(target).or_default().push(offset),
+            None => flow.dynamic.push(offset),
+        }
+    }
+
+    flow
+}
+
+/// Byte offset of every instruction
+fn offsets(instructions: &[Instruction]) -> impl Iterator<Item = usize> + '_ {
+    instructions.iter().scan(0, |offset, instruction| {
+        let start = *offset;
+        *offset += instruction.size();
+
+        Some(start)
+    })
+}
+
+/// Every JUMP and JUMPI with its offset and its target when a PUSH right before it sets it
+fn jumps(
+    instructions: &[Instruction],
+) -> impl Iterator<Item = (usize, &Instruction, Option<usize>)> {
+    offsets(instructions)
+        .zip(instructions)
+        .enumerate()
+        .filter(|(_, (_, instruction))| matches!(instruction.op, Op::JUMP | Op::JUMPI))
+        .map(|(index, (offset, jump))| {
+            let push = index
+                .checked_sub(1)
+                .map(|index| &instructions[index])
+                .filter(|push| push.op.is_push().is_some());
+
+            // A target too large for usize can not be an offset of the program
+            let target = push.map(|push| {
+                push.operand
+                    .iter()
+                    .flatten()
+                    .try_fold(0usize, |target, &byte| {
+                        target.checked_mul(256)?.checked_add(byte as usize)
+                    })
+                    .unwrap_or(usize::MAX)
+            });
+
+            (offset, jump, target)
+        })
+}
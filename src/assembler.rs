@@ -0,0 +1,198 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use crate::{
+    assemble_sections, bytecode, check_fork, lint, optimize, size_warnings, stack_diagnostics,
+    to_hex, warnings, AssembleError, Diagnostic, Fork, Instruction, LexError, Lexer, ParseError,
+    Parser, Resolver,
+};
+
+/// Assembles any number of programs with the same settings, like a server that assembles
+/// the source it is sent
+///
+/// ```
+/// use easm::{Assembler, Fork};
+///
+/// let assembler = Assembler::builder().fork(Fork::Cancun).strict(true).build().unwrap();
+/// assert_eq!(assembler.assemble("push1 0x01 push1 0x02 add pop").unwrap(), "600160020150");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    /// Holds the constants, opcodes and lexing settings every program starts from
+    lexer: Lexer,
+
+    fork: Fork,
+    strict: bool,
+    optimize: bool,
+}
+
+/// Settings of an `Assembler`, from `Assembler::builder`
+#[derive(Debug, Clone, Default)]
+pub struct AssemblerBuilder {
+    lexer: Lexer,
+    fork: Fork,
+    strict: bool,
+    optimize: bool,
+
+    /// Mnemonic, byte and whether it may replace a built-in opcode
+    opcodes: Vec<(String, u8, bool)>,
+}
+
+impl AssemblerBuilder {
+    /// Reject opcodes newer than the fork, the newest one by default
+    pub fn fork(mut self, fork: Fork) -> Self {
+        self.fork = fork;
+
+        self
+    }
+
+    /// Fail on every warning and lint as if it were an error
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+
+        self
+    }
+
+    /// Run the peephole optimizer before labels are resolved
+    pub fn optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+
+        self
+    }
+
+    /// Define a constant as if every program started with `#define NAME value`
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.lexer
+            .constants
+            .insert(name.to_string(), value.to_string());
+
+        self
+    }
+
+    /// Encode decimal, binary and octal operands least significant byte first
+    pub fn little_endian(mut self, little_endian: bool) -> Self {
+        self.lexer.little_endian = little_endian;
+
+        self
+    }
+
+    /// Fail on a program of more tokens than `max_ops`
+    pub fn max_ops(mut self, max_ops: usize) -> Self {
+        self.lexer.max_ops = Some(max_ops);
+
+        self
+    }
+
+    /// Look for an `#include` in the directory after the ones added before it
+    #[cfg(feature = "std")]
+    pub fn include_path(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.lexer.include_paths.push(directory.into());
+
+        self
+    }
+
+    /// Add an opcode, `build` fails when it is already a built-in opcode
+    pub fn opcode(mut self, name: &str, byte: u8) -> Self {
+        self.opcodes.push((name.to_string(), byte, false));
+
+        self
+    }
+
+    /// Add an opcode that may replace the built-in opcode of the same name
+    pub fn replace_opcode(mut self, name: &str, byte: u8) -> Self {
+        self.opcodes.push((name.to_string(), byte, true));
+
+        self
+    }
+
+    /// The assembler, or the error of the first opcode that can not be added
+    pub fn build(mut self) -> Result<Assembler, LexError> {
+        for (name, byte, replace) in &self.opcodes {
+            self.lexer.add_opcode(name, *byte, *replace)?;
+        }
+
+        Ok(Assembler {
+            lexer: self.lexer,
+            fork: self.fork,
+            strict: self.strict,
+            optimize: self.optimize,
+        })
+    }
+}
+
+impl Assembler {
+    pub fn builder() -> AssemblerBuilder {
+        AssemblerBuilder::default()
+    }
+
+    /// Assemble the source into hex encoded bytecode, sections as `easm::assemble` does
+    pub fn assemble(&self, source: &str) -> Result<String, AssembleError> {
+        let mut lexer = self.lexer.clone();
+        lexer.lex(source)?;
+
+        let sections = lexer.runtime.is_some();
+        let mut runtime = 0;
+        let code = assemble_sections(lexer, |lexer| -> Result<_, AssembleError> {
+            let code = bytecode(&self.instructions(lexer)?);
+            runtime = code.len();
+
+            Ok(code)
+        })?;
+
+        if self.strict {
+            Self::fail_on(size_warnings(runtime, sections.then_some(code.len())))?;
+        }
+
+        Ok(to_hex(&code))
+    }
+
+    /// Resolve and parse the lexed code with the settings of the assembler
+    fn instructions(&self, mut lexer: Lexer) -> Result<Vec<Instruction>, AssembleError> {
+        check_fork(&lexer.result, self.fork)?;
+
+        if self.optimize {
+            optimize(&mut lexer, self.fork);
+        }
+
+        // Literals are told apart from labels before they are resolved
+        let lints = match self.strict {
+            true => lint(&lexer),
+            false => Vec::new(),
+        };
+
+        Resolver::new().fork(self.fork).resolve(&mut lexer.result)?;
+        let instructions = Parser::new(&lexer.result).spans(&lexer.spans).parse()?;
+
+        if self.strict {
+            let stack = stack_diagnostics(&lexer.result);
+            Self::fail_on([lints, stack, warnings(&instructions)].concat())?;
+        }
+
+        Ok(instructions)
+    }
+
+    /// Strict mode turns every warning into an error
+    fn fail_on(warnings: Vec<Diagnostic>) -> Result<(), AssembleError> {
+        if warnings.is_empty() {
+            return Ok(());
+        }
+
+        Err(AssembleError::many(
+            warnings
+                .into_iter()
+                .map(|warning| {
+                    let error = ParseError::new(warning.message);
+
+                    match warning.line {
+                        Some(line) => error.line(line),
+                        None => error,
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
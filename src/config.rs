@@ -0,0 +1,675 @@
+use easm::Fork;
+
+pub const USAGE: &str =
+    "Usage: easm asm [FILE... | --code SOURCE] [-o OUTPUT] [--format FORMAT] [--fork FORK]
+                [-x] [--columns N] [--output-case CASE] [-v] [--verify] [--sourcemap MAP] [--deploy]
+                [--optimize] [--auto-stop] [--max-size] [--require-nonempty]
+                [--metadata --metadata-hash HASH] [--emit-obj] [--count-only]
+                [--define NAME] [--include-path DIR] [--entry HEX] [--operand-endian ENDIAN]
+                [--max-ops N] [--opcodes TABLE [--override]] [--lint] [--strict] [--watch]
+                [--manifest MANIFEST]
+       easm analyze [--stats | --gas | --stack | --listing | --listing-gas | --trace | --explain
+                    | --emit-ir | --tokens-json | --symbols | --cfg | --coverage | --check | --diagnostics-json
+                    | --diff]
+                    [--fork FORK] [--define NAME] [--include-path DIR] [--operand-endian ENDIAN]
+                    [--max-ops N] [--opcodes TABLE [--override]] [--lint] [--strict] [-v]
+                    [FILE... | --code SOURCE]
+       easm link OBJECT... [-o OUTPUT] [--format FORMAT] [-x] [--columns N] [--output-case CASE]
+       easm disasm [--annotate | --base OFFSET] HEX
+
+Assembles the FILEs as one program, or the source piped to stdin when no FILE or --code
+is given.
+easm without a subcommand assembles like asm, that form is deprecated and takes the
+flags of every subcommand, with --disasm HEX in place of disasm and --decode-annotate HEX
+in place of disasm --annotate.
+
+Options of asm:
+  -o OUTPUT         Write the bytecode to OUTPUT, as raw bytes when it ends in .bin
+  --format FORMAT   Output the bytecode as hex or bin (raw bytes)
+  -x, --hex-prefix  Prefix the hex output with 0x
+  --columns N       Split the hex output into rows of N bytes, each after its offset
+  --output-case CASE
+                    Write the hex output in lower (the default) or upper case
+  --sourcemap MAP   Write the source line and column of every instruction to MAP as JSON
+  --deploy          Wrap the bytecode in init code that returns it as the contract code
+  --optimize        Remove wasteful instruction sequences and report the bytes saved
+  --auto-stop       Append a STOP unless the code already ends with STOP, RETURN, REVERT,
+                    INVALID or SELFDESTRUCT
+  --verify          Check that the disassembled bytecode assembles to the same bytes
+  --metadata        Append a solc style CBOR metadata trailer of the --metadata-hash HASH,
+                    a 34 byte IPFS multihash or a 32 byte swarm hash in hex
+  --max-size        Fail instead of warning when the code is over the EIP-170 or EIP-3860 limit
+  --require-nonempty
+                    Fail instead of warning when the program assembles to no bytecode
+  --emit-obj        Output an object for link, labels that are not defined are left to it
+  --count-only      Print the number of bytes of the code instead of the code
+  --entry HEX       Fail unless the code starts with the bytes HEX
+  --manifest MANIFEST
+                    Write the files, fork, labels and constants of the build to MANIFEST as JSON,
+                    with the keccak-256 and length of the bytecode and the keccak-256 of each file
+  --watch           Assemble the FILEs again whenever they or a file they include change,
+                    needs easm built with the watch feature
+
+link places the OBJECTs one after the other and fills in the labels they refer to,
+its -o, --format, -x and --columns are those of asm.
+
+Options of disasm:
+  --annotate        Print the hex of every byte with the mnemonic of each opcode after it
+  --base OFFSET     Print every instruction on a line of its own after its offset, counting
+                    from the hex or decimal OFFSET where the code starts in a larger contract
+
+Options of analyze:
+  --stats           Print the size of the program and how often each opcode is used (the default)
+  --gas             Print the static gas cost instead of the bytecode
+  --stack           Check the program for stack underflows as straight-line code
+  --listing         Print every instruction with its byte offset
+  --listing-gas     Print the listing with the static gas of every instruction and the running
+                    total, a * marks a dynamic cost that is left out of the total
+  --trace           Print the listing with the stack height before and after every instruction
+  --explain         Print the listing with what every instruction does, or what the opcode
+                    does when the only FILE is an opcode name like add
+  --emit-ir         Print the instructions with the macros, constants and labels expanded,
+                    as source that assembles to the same bytecode
+  --tokens-json     Print the resolved tokens as JSON
+  --symbols         Print the offset of every label and the value of every constant
+  --cfg             Print every JUMPDEST with the offsets of the constant jumps to it
+  --coverage        Print the opcodes the program uses, marking deprecated ones, and those it
+                    does not
+  --check           Report every error and warning without output, failing on any error
+  --diagnostics-json
+                    Print what --check reports as JSON with the line, column and kind of each
+  --diff            Assemble the two FILEs on their own and print every run of bytes that
+                    differs with the instructions it is in, failing when there is any
+
+Options of asm and analyze:
+  --code SOURCE     Assemble SOURCE itself instead of files or stdin
+  --fork FORK       Reject opcodes newer than FORK, from frontier to cancun (the default)
+  --define NAME     Define the constant NAME as 1, which makes #ifdef NAME blocks part of the program.
+                    A #define NAME in the source is skipped, the command line wins
+  --include-path DIR
+                    Look for an #include in DIR when it is not next to the file including it,
+                    the directories are tried in the order they are given
+  --operand-endian ENDIAN
+                    Encode decimal, binary and octal operands big (the default) or little
+                    endian, hex operands are kept as written
+  --max-ops N       Fail once the program has more than N tokens, for untrusted source
+  --opcodes TABLE   Add the opcodes of the file TABLE, with a line like FOO = 0xf9 for each
+  --override        Let the opcodes of --opcodes replace built-in opcodes of the same name
+  --lint            Also warn about a PUSHn of a literal that fits a smaller PUSH and about
+                    what the stack check of analyze --check finds
+  --strict          Treat every warning as an error, --lint included
+  -v, --verbose     Trace the tokens, labels, constants and bytecode size to stderr";
+
+/// Subcommand given as the first argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Asm,
+    Analyze,
+    Disasm,
+    Link,
+
+    /// No subcommand, the deprecated form that takes every flag
+    Legacy,
+}
+
+impl Command {
+    /// Is the flag an option of easm that this subcommand does not take
+    fn rejects(self, flag: &str) -> bool {
+        let assemble = [
+            "-o",
+            "--format",
+            "-x",
+            "--hex-prefix",
+            "--columns",
+            "--sourcemap",
+            "--deploy",
+            "--optimize",
+            "--auto-stop",
+            "--verify",
+            "--max-size",
+            "--require-nonempty",
+            "--metadata",
+            "--metadata-hash",
+            "--emit-obj",
+            "--count-only",
+            "--entry",
+            "--watch",
+            "--manifest",
+        ];
+        let analyze = [
+            "--stats",
+            "--gas",
+            "--stack",
+            "--listing",
+            "--listing-gas",
+            "--trace",
+            "--explain",
+            "--emit-ir",
+            "--tokens-json",
+            "--symbols",
+            "--cfg",
+            "--coverage",
+            "--check",
+            "--diagnostics-json",
+            "--diff",
+        ];
+        let output = [
+            "-o",
+            "--format",
+            "-x",
+            "--hex-prefix",
+            "--columns",
+            "--output-case",
+        ];
+        let shared = [
+            "--code",
+            "--fork",
+            "--define",
+            "--include-path",
+            "--operand-endian",
+            "--max-ops",
+            "--opcodes",
+            "--override",
+            "--lint",
+            "--strict",
+            "-v",
+            "--verbose",
+        ];
+
+        // --disasm and --decode-annotate are the deprecated form of disasm
+        let disasm = ["--disasm", "--decode-annotate", "--annotate", "--base"];
+
+        match self {
+            Self::Asm => analyze.contains(&flag) || disasm.contains(&flag),
+            Self::Analyze => assemble.contains(&flag) || disasm.contains(&flag),
+            Self::Disasm => [&assemble[..], &analyze, &shared, &disasm[..2]]
+                .concat()
+                .contains(&flag),
+            Self::Link => {
+                !output.contains(&flag)
+                    && [&assemble[..], &analyze, &shared, &disasm]
+                        .concat()
+                        .contains(&flag)
+            }
+            Self::Legacy => false,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Asm => "asm",
+            Self::Analyze => "analyze",
+            Self::Disasm => "disasm",
+            Self::Link => "link",
+            Self::Legacy => "easm",
+        }
+    }
+}
+
+/// What the binary does with its input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Assemble,
+    Gas,
+    Stack,
+    Listing,
+    ListingGas,
+    Trace,
+    Explain,
+    Ir,
+    Stats,
+    TokensJson,
+    Symbols,
+    Cfg,
+    Coverage,
+    Check,
+    DiagnosticsJson,
+
+    /// Compare the bytecode of the two inputs
+    Diff,
+    Disassemble(String),
+
+    /// Print the hex with the mnemonic of every opcode
+    Annotate(String),
+
+    /// Link the inputs, which are objects
+    Link,
+}
+
+/// Encoding of the assembled bytecode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Hex,
+    Bin,
+}
+
+/// Command line options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Source files in the order they are assembled, stdin is read when there are none
+    pub inputs: Vec<String>,
+
+    /// Source given on the command line in place of the inputs
+    pub code: Option<String>,
+
+    /// File to write the bytecode to instead of stdout
+    pub output: Option<String>,
+
+    /// Output encoding, inferred from the output extension when not given
+    pub format: Option<Format>,
+
+    /// Newest fork whose opcodes can be used
+    pub fork: Fork,
+
+    /// Constants defined on the command line
+    pub defines: Vec<String>,
+
+    /// Directories searched for an `#include`, in order
+    pub include_paths: Vec<String>,
+
+    /// Encode number operands least significant byte first
+    pub little_endian: bool,
+
+    /// Most tokens the program may have
+    pub max_ops: Option<usize>,
+
+    /// File of opcodes to add to the built-in ones
+    pub opcodes: Option<String>,
+
+    /// Let the added opcodes replace built-in ones
+    pub override_opcodes: bool,
+
+    /// Prefix the hex output with 0x
+    pub hex_prefix: bool,
+
+    /// Write the hex output in upper case
+    pub uppercase: bool,
+
+    /// Bytes per row of the hex output, one row when not given
+    pub columns: Option<usize>,
+
+    /// Trace every stage to stderr
+    pub verbose: bool,
+
+    /// Give the warnings of `lint`
+    pub lint: bool,
+
+    /// Fail on warnings as if they were errors, lints included
+    pub strict: bool,
+
+    /// File to write the source map of the bytecode to
+    pub sourcemap: Option<String>,
+
+    /// Output creation bytecode that deploys the assembled program
+    pub deploy: bool,
+
+    /// Run the peephole optimizer before labels are resolved
+    pub optimize: bool,
+
+    /// End the code with a STOP when it would run off its end
+    pub auto_stop: bool,
+
+    /// Round trip the bytecode through the disassembler before writing it
+    pub verify: bool,
+
+    /// Fail when the bytecode is over the contract size limits
+    pub max_size: bool,
+
+    /// Fail when the program assembles to no bytecode
+    pub require_nonempty: bool,
+
+    /// Hash of the metadata trailer to append to the runtime
+    pub metadata: Option<String>,
+
+    /// Output an object to link instead of bytecode
+    pub emit_obj: bool,
+
+    /// Output the size of the bytecode instead of the bytecode
+    pub count_only: bool,
+
+    /// Assemble again whenever a file of the program changes
+    pub watch: bool,
+
+    /// Write a JSON record of the sources and the bytecode of the build here
+    pub manifest: Option<String>,
+
+    /// Hex bytes the code must start with
+    pub entry: Option<String>,
+
+    /// Offset the disassembled code starts at, which is printed with every instruction
+    pub base: Option<usize>,
+
+    pub mode: Mode,
+}
+
+impl Config {
+    /// Parse the command line arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self {
+            inputs: vec![],
+            code: None,
+            output: None,
+            format: None,
+            fork: Fork::default(),
+            defines: vec![],
+            include_paths: vec![],
+            little_endian: false,
+            max_ops: None,
+            opcodes: None,
+            override_opcodes: false,
+            hex_prefix: false,
+            uppercase: false,
+            columns: None,
+            verbose: false,
+            lint: false,
+            strict: false,
+            sourcemap: None,
+            deploy: false,
+            optimize: false,
+            auto_stop: false,
+            verify: false,
+            max_size: false,
+            require_nonempty: false,
+            metadata: None,
+            emit_obj: false,
+            count_only: false,
+            watch: false,
+            manifest: None,
+            entry: None,
+            base: None,
+            mode: Mode::Assemble,
+        };
+
+        let mut metadata = false;
+        let mut annotate = false;
+
+        let mut args = args.into_iter().peekable();
+        let command = match args.peek().map(String::as_str) {
+            Some("asm") => Command::Asm,
+            Some("analyze") => Command::Analyze,
+            Some("disasm") => Command::Disasm,
+            Some("link") => Command::Link,
+            _ => Command::Legacy,
+        };
+        if command != Command::Legacy {
+            args.next();
+        }
+
+        while let Some(arg) = args.next() {
+            if command.rejects(&arg) {
+                return Err(format!("{} is not an option of {}", arg, command.name()));
+            }
+
+            match arg.as_str() {
+                "-o" => config.output = Some(args.next().ok_or("-o expects an output path")?),
+
+                "--format" => {
+                    config.format = match args.next().as_deref() {
+                        Some("hex") => Some(Format::Hex),
+                        Some("bin") => Some(Format::Bin),
+                        _ => return Err("--format expects hex or bin".into()),
+                    }
+                }
+
+                "--fork" => {
+                    config.fork = args
+                        .next()
+                        .as_deref()
+                        .and_then(Fork::from_name)
+                        .ok_or("--fork expects a fork name like london or cancun")?
+                }
+
+                "--code" => config.code = Some(args.next().ok_or("--code expects source code")?),
+
+                "--define" => config
+                    .defines
+                    .push(args.next().ok_or("--define expects a constant name")?),
+
+                "--include-path" => config
+                    .include_paths
+                    .push(args.next().ok_or("--include-path expects a directory")?),
+
+                "--operand-endian" => {
+                    config.little_endian = match args.next().as_deref() {
+                        Some("big") => false,
+                        Some("little") => true,
+                        _ => return Err("--operand-endian expects big or little".into()),
+                    }
+                }
+
+                "--max-ops" => {
+                    config.max_ops = Some(
+                        args.next()
+                            .and_then(|max| max.parse().ok())
+                            .ok_or("--max-ops expects a number of tokens")?,
+                    )
+                }
+
+                "--opcodes" => {
+                    config.opcodes = Some(args.next().ok_or("--opcodes expects a file")?)
+                }
+
+                "--override" => config.override_opcodes = true,
+
+                "-x" | "--hex-prefix" => config.hex_prefix = true,
+
+                "--output-case" => {
+                    config.uppercase = match args.next().as_deref() {
+                        Some("lower") => false,
+                        Some("upper") => true,
+                        _ => return Err("--output-case expects lower or upper".into()),
+                    }
+                }
+
+                "--columns" => {
+                    config.columns = Some(
+                        args.next()
+                            .and_then(|columns| columns.parse().ok())
+                            .filter(|&columns| columns > 0)
+                            .ok_or("--columns expects a number of bytes per row")?,
+                    )
+                }
+
+                "-v" | "--verbose" => config.verbose = true,
+
+                "--sourcemap" => {
+                    config.sourcemap = Some(args.next().ok_or("--sourcemap expects a path")?)
+                }
+
+                "--deploy" => config.deploy = true,
+
+                "--optimize" => config.optimize = true,
+
+                "--auto-stop" => config.auto_stop = true,
+
+                "--verify" => config.verify = true,
+
+                "--max-size" => config.max_size = true,
+
+                "--require-nonempty" => config.require_nonempty = true,
+
+                "--lint" => config.lint = true,
+
+                "--strict" => config.strict = true,
+
+                "--emit-obj" => config.emit_obj = true,
+
+                "--count-only" => config.count_only = true,
+
+                "--watch" => config.watch = true,
+
+                "--manifest" => {
+                    config.manifest = Some(args.next().ok_or("--manifest expects a path")?)
+                }
+
+                "--metadata" => metadata = true,
+
+                "--entry" => {
+                    config.entry = Some(
+                        args.next()
+                            .ok_or("--entry expects the hex bytes the code starts with")?,
+                    )
+                }
+
+                "--metadata-hash" => {
+                    config.metadata = Some(args.next().ok_or("--metadata-hash expects a hex hash")?)
+                }
+
+                "--gas" => config.set_mode(Mode::Gas)?,
+
+                "--stack" => config.set_mode(Mode::Stack)?,
+
+                "--listing" => config.set_mode(Mode::Listing)?,
+
+                "--listing-gas" => config.set_mode(Mode::ListingGas)?,
+
+                "--explain" => config.set_mode(Mode::Explain)?,
+
+                "--emit-ir" => config.set_mode(Mode::Ir)?,
+
+                "--trace" => config.set_mode(Mode::Trace)?,
+
+                "--stats" => config.set_mode(Mode::Stats)?,
+
+                "--tokens-json" => config.set_mode(Mode::TokensJson)?,
+
+                "--symbols" => config.set_mode(Mode::Symbols)?,
+
+                "--cfg" => config.set_mode(Mode::Cfg)?,
+
+                "--coverage" => config.set_mode(Mode::Coverage)?,
+
+                "--check" => config.set_mode(Mode::Check)?,
+
+                "--diagnostics-json" => config.set_mode(Mode::DiagnosticsJson)?,
+
+                "--diff" => config.set_mode(Mode::Diff)?,
+
+                "--disasm" => {
+                    let hex = args.next().ok_or("--disasm expects hex bytecode")?;
+                    config.set_mode(Mode::Disassemble(hex))?
+                }
+
+                "--decode-annotate" => {
+                    let hex = args
+                        .next()
+                        .ok_or("--decode-annotate expects hex bytecode")?;
+                    config.set_mode(Mode::Annotate(hex))?
+                }
+
+                "--annotate" => annotate = true,
+
+                "--base" => {
+                    config.base = Some(
+                        args.next()
+                            .and_then(|base| match base.strip_prefix("0x") {
+                                Some(hex) => usize::from_str_radix(hex, 16).ok(),
+                                None => base.parse().ok(),
+                            })
+                            .ok_or("--base expects a hex or decimal offset")?,
+                    )
+                }
+
+                flag if flag.starts_with('-') => return Err(format!("unknown flag {}", flag)),
+
+                _ if command == Command::Disasm => config.set_mode(Mode::Disassemble(arg))?,
+
+                _ => config.inputs.push(arg),
+            }
+        }
+
+        if annotate {
+            let Mode::Disassemble(hex) = config.mode else {
+                return Err("--annotate expects hex bytecode".into());
+            };
+
+            config.mode = Mode::Annotate(hex);
+        }
+
+        if config.base.is_some() && !matches!(config.mode, Mode::Disassemble(_)) {
+            return Err("--base is only used to disassemble without --annotate".into());
+        }
+
+        if metadata != config.metadata.is_some() {
+            return Err("--metadata and --metadata-hash must be used together".into());
+        }
+
+        // The init code and the trailer are not relocated
+        if config.emit_obj && (config.deploy || config.metadata.is_some() || config.entry.is_some())
+        {
+            return Err("--emit-obj can not be used with --deploy, --metadata or --entry".into());
+        }
+
+        // There is no bytecode to wrap, check or write with --count-only
+        if config.count_only
+            && (config.emit_obj
+                || config.deploy
+                || config.metadata.is_some()
+                || config.entry.is_some()
+                || config.verify
+                || config.sourcemap.is_some()
+                || config.output.is_some())
+        {
+            return Err(
+                "--count-only can not be used with --emit-obj, --deploy, --metadata, \
+                        --entry, --verify, --sourcemap or -o"
+                    .into(),
+            );
+        }
+
+        // An object or a size is not an artifact to deploy
+        if config.manifest.is_some() && (config.emit_obj || config.count_only) {
+            return Err("--manifest can not be used with --emit-obj or --count-only".into());
+        }
+
+        if config.watch && !cfg!(feature = "watch") {
+            return Err("--watch needs easm built with the watch feature".into());
+        }
+
+        // Source from --code or stdin does not change
+        if config.watch && (config.inputs.is_empty() || config.mode != Mode::Assemble) {
+            return Err("--watch expects the FILEs to assemble".into());
+        }
+
+        if config.override_opcodes && config.opcodes.is_none() {
+            return Err("--override only applies with --opcodes".into());
+        }
+
+        if config.mode == Mode::Diff && config.inputs.len() != 2 {
+            return Err("--diff expects the two files to compare".into());
+        }
+
+        if config.code.is_some() && !config.inputs.is_empty() {
+            return Err("--code and FILE can not be used together".into());
+        }
+
+        match command {
+            Command::Analyze if config.mode == Mode::Assemble => config.mode = Mode::Stats,
+
+            Command::Disasm if config.mode == Mode::Assemble => {
+                return Err("disasm expects hex bytecode".into())
+            }
+
+            Command::Link if config.inputs.is_empty() => {
+                return Err("link expects the objects to link".into())
+            }
+
+            Command::Link => config.mode = Mode::Link,
+
+            _ => {}
+        }
+
+        Ok(config)
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), String> {
+        if self.mode != Mode::Assemble {
+            // The usage printed after the error lists the mode flags
+            return Err("only one mode flag can be used".into());
+        }
+
+        self.mode = mode;
+
+        Ok(())
+    }
+}
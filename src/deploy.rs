@@ -0,0 +1,97 @@
+use crate::{bytecode, Instruction, Op};
+use alloc::{vec, vec::Vec};
+
+/// Wrap the runtime bytecode in init code that copies it to memory and returns it,
+/// the result is the creation bytecode of a contract
+pub fn deploy(runtime: &[u8]) -> Vec<u8> {
+    deploy_after(&[], runtime)
+}
+
+/// Creation bytecode that runs `init` first, then copies the runtime to memory and
+/// returns it. The init code must go on to its end rather than stop or jump past it
+pub fn deploy_after(init: &[u8], runtime: &[u8]) -> Vec<u8> {
+    let length = be_bytes(runtime.len());
+
+    // The runtime starts right after the code that returns it, which is 9 bytes besides
+    // the operands of the length and offset PUSHes. A wider offset moves the runtime
+    // further, so the width grows until the offset fits it
+    let start = init.len() + 9 + length.len();
+    let mut width = 1;
+    while be_bytes(start + width).len() > width {
+        width += 1;
+    }
+    let offset = be_bytes(start + width);
+
+    let preamble = [
+        push(length),
+        op(Op::DUP1),
+        push(offset),
+        push(vec![0]),
+        op(Op::CODECOPY),
+        push(vec![0]),
+        op(Op::RETURN),
+    ];
+
+    let mut code = init.to_vec();
+    code.extend(bytecode(&preamble));
+    code.extend(runtime);
+
+    code
+}
+
+/// Shortest big-endian bytes of the value, at least one byte
+fn be_bytes(value: usize) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(bytes.len() - 1);
+
+    bytes[start..].to_vec()
+}
+
+fn op(op: Op) -> Instruction {
+    Instruction {
+        opcode: op.opcode(),
+        op,
+        operand: None,
+        line: 0,
+        column: 0,
+        len: 0,
+    }
+}
+
+fn push(operand: Vec<u8>) -> Instruction {
+    let (op, _) = Op::push_of_size(operand.len()).expect("a usize fits in a PUSH");
+
+    Instruction {
+        operand: Some(operand),
+        ..self::op(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_code_returns_the_runtime() {
+        let runtime = [0x60, 0x2a, 0x00];
+        assert_eq!(
+            deploy(&runtime),
+            [0x60, 0x03, 0x80, 0x60, 0x0b, 0x60, 0x00, 0x39, 0x60, 0x00, 0xf3, 0x60, 0x2a, 0x00]
+        );
+
+        let runtime = vec![0; 0x1234];
+        let code = deploy(&runtime);
+        assert_eq!(code[..3], [0x61, 0x12, 0x34]);
+        assert_eq!(code[4..6], [0x60, 0x0c]);
+        assert_eq!(code.len(), 0x0c + runtime.len());
+
+        // The offset needs a PUSH2 once the init code is long enough
+        let code = deploy_after(&[0x5b; 0xf6], &[0x00]);
+        assert_eq!(code[0xf6..0xfb], [0x60, 0x01, 0x80, 0x61, 0x01]);
+        assert_eq!(code[0xfb], 0x02);
+        assert_eq!(code[0x102..], [0x00]);
+    }
+}
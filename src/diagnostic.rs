@@ -0,0 +1,331 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::{
+    analysis::{
+        check_jumps, check_stack, find_unreachable, MAX_CODE_SIZE, MAX_INITCODE_SIZE,
+        MAX_STACK_DEPTH,
+    },
+    check_fork, deploy_after,
+    op::is_hex,
+    AssembleError, Fork, Instruction, Lexer, Op, Parser, Resolver,
+};
+
+/// How serious a diagnostic is, only errors make a program fail the check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+/// A problem found by one of the validation passes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+
+    /// Source line and column, starting at 1, when they are known
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+
+    /// What kind of problem it is, the variant name for an `AssembleError`
+    pub code: &'static str,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl fmt::Display, code: &'static str) -> Self {
+        Self {
+            level: Level::Error,
+            message: message.to_string(),
+            line: None,
+            column: None,
+            code,
+        }
+    }
+
+    pub fn warning(message: impl fmt::Display, code: &'static str) -> Self {
+        Self {
+            level: Level::Warning,
+            ..Self::error(message, code)
+        }
+    }
+
+    /// Set the line the problem is on
+    pub fn line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+
+        self
+    }
+
+    /// Set the column the problem is on, 0 when it is not known
+    pub fn column(mut self, column: usize) -> Self {
+        self.column = (column > 0).then_some(column);
+
+        self
+    }
+
+    /// The same problem as an error, how `--strict` treats warnings
+    pub fn strict(self) -> Self {
+        Self {
+            level: Level::Error,
+            ..self
+        }
+    }
+}
+
+impl From<&AssembleError> for Diagnostic {
+    fn from(error: &AssembleError) -> Self {
+        Self {
+            line: error.line(),
+            column: error.column(),
+            ..Self::error(error, error.code())
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.level {
+            Level::Warning => write!(f, "warning: {}", self.message),
+            Level::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// Run every validation pass over the lexed program and collect what they find rather
+/// than stopping at the first problem. A pass that fails leaves nothing for the passes
+/// after it, so those are skipped. The `.init` and `.runtime` sections are checked as
+/// code of their own
+pub fn check(mut lexer: Lexer, fork: Fork) -> Vec<Diagnostic> {
+    let Some(runtime) = lexer.split_runtime() else {
+        let (mut diagnostics, size) = check_code(lexer, fork);
+        diagnostics.extend(size.map_or(vec![], |size| size_warnings(size, None)));
+
+        return diagnostics;
+    };
+
+    let (mut diagnostics, init) = check_code(lexer, fork);
+    let (runtime_diagnostics, runtime) = check_code(runtime, fork);
+    diagnostics.extend(runtime_diagnostics);
+
+    if let (Some(init), Some(runtime)) = (init, runtime) {
+        let creation = deploy_after(&vec![0; init], &vec![0; runtime]).len();
+        diagnostics.extend(size_warnings(runtime, Some(creation)));
+    }
+
+    diagnostics
+}
+
+/// The diagnostics of one piece of code, with its size in bytes when it parses
+fn check_code(mut lexer: Lexer, fork: Fork) -> (Vec<Diagnostic>, Option<usize>) {
+    let mut diagnostics = vec![];
+
+    if let Err(error) = check_fork(&lexer.result, fork) {
+        diagnostics.push(Diagnostic::from(&AssembleError::from(error)));
+    }
+
+    if let Err(error) = Resolver::new().fork(fork).resolve(&mut lexer.result) {
+        diagnostics.extend(error.errors().iter().map(Diagnostic::from));
+
+        return (diagnostics, None);
+    }
+
+    diagnostics.extend(stack_diagnostics(&lexer.result));
+
+    let instructions = match Parser::new(&lexer.result).spans(&lexer.spans).parse() {
+        Ok(instructions) => instructions,
+
+        Err(error) => {
+            diagnostics.extend(error.errors().iter().map(Diagnostic::from));
+
+            return (diagnostics, None);
+        }
+    };
+
+    diagnostics.extend(warnings(&instructions));
+
+    (
+        diagnostics,
+        Some(instructions.iter().map(Instruction::size).sum()),
+    )
+}
+
+/// What the stack check of the resolved tokens finds, the underflows and leftovers are
+/// warnings as jumps are not followed
+pub fn stack_diagnostics(tokens: &[(Op, &str, usize)]) -> Vec<Diagnostic> {
+    let report = check_stack(tokens);
+    let mut diagnostics = vec![];
+
+    // The stack is checked as straight-line code, so an underflow may be a false alarm.
+    // One at a DUP or SWAP is the StackTooDeep error below instead
+    let underflow = report
+        .underflow
+        .filter(|underflow| report.too_deep.as_ref() != Some(underflow));
+    if let Some(underflow) = underflow {
+        diagnostics.push(
+            Diagnostic::warning(
+                format!(
+                    "stack underflow on line {} at {}, needs {} items but the stack has {}",
+                    underflow.line, underflow.op, underflow.needed, underflow.available
+                ),
+                "StackUnderflow",
+            )
+            .line(underflow.line),
+        );
+    }
+
+    if let Some(leftover) = report.leftover {
+        let place = match leftover.stop {
+            true => format!("STOP on line {} leaves", leftover.line),
+            false => format!("the code ends on line {} with", leftover.line),
+        };
+
+        diagnostics.push(
+            Diagnostic::warning(
+                format!(
+                    "{} {} items on the stack, as far as it can be told without following jumps",
+                    place, leftover.items
+                ),
+                "LeftoverStack",
+            )
+            .line(leftover.line),
+        );
+    }
+
+    if let Some(fault) = report.too_deep {
+        diagnostics.push(
+            Diagnostic::error(
+                format!(
+                    "{} on line {} reaches {} items deep but the stack has {}",
+                    fault.op, fault.line, fault.needed, fault.available
+                ),
+                "StackTooDeep",
+            )
+            .line(fault.line),
+        );
+    }
+
+    if report.max_height > MAX_STACK_DEPTH {
+        diagnostics.push(Diagnostic::error(
+            format!(
+                "the stack reaches {} items, more than the {} the EVM allows",
+                report.max_height, MAX_STACK_DEPTH
+            ),
+            "StackOverflow",
+        ));
+    }
+
+    diagnostics
+}
+
+/// Warnings about parsed instructions that assemble but are likely mistakes
+pub fn warnings(instructions: &[Instruction]) -> Vec<Diagnostic> {
+    let unreachable = find_unreachable(instructions).map(|unreachable| {
+        Diagnostic::warning(
+            format!(
+                "{} on line {} is unreachable, it follows {} with no JUMPDEST in between",
+                unreachable.op, unreachable.line, unreachable.after
+            ),
+            "Unreachable",
+        )
+        .line(unreachable.line)
+        .column(unreachable.column)
+    });
+
+    let jumps = check_jumps(instructions).into_iter().map(|jump| {
+        Diagnostic::warning(
+            format!(
+                "{} on line {} goes to 0x{:04x} which is not a JUMPDEST",
+                jump.op, jump.line, jump.target
+            ),
+            "BadJump",
+        )
+        .line(jump.line)
+        .column(jump.column)
+    });
+
+    unreachable.into_iter().chain(jumps).collect()
+}
+
+/// Warnings only given with `--lint` or `--strict`, of the lexed program before its labels
+/// are resolved. A PUSHn of a literal with leading zero bytes would fit a smaller PUSH, a
+/// PUSH32 is left alone as it is often a full word on purpose
+pub fn lint(lexer: &Lexer) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for (index, pair) in lexer.result.windows(2).enumerate() {
+        let [(op, _, line), (Op::VALUE(value), _, _)] = pair else {
+            continue;
+        };
+
+        // Labels and expressions are not literals, their width is the one they need
+        let Some((size, hex)) = op
+            .is_push()
+            .map(usize::from)
+            .filter(|&size| size > 0 && size < Op::MAX_PUSH_SIZE)
+            .zip(value.strip_prefix("0x"))
+            .filter(|(size, hex)| hex.len() == size * 2 && is_hex(hex))
+        else {
+            continue;
+        };
+
+        let needed = hex.trim_start_matches('0').len().max(1).div_ceil(2);
+        if needed < size {
+            let (smaller, _) = Op::push_of_size(needed).expect("a smaller PUSH exists");
+
+            diagnostics.push(
+                Diagnostic::warning(
+                    format!(
+                        "{} on line {} pushes {} which fits in {}",
+                        op, line, value, smaller
+                    ),
+                    "WidePush",
+                )
+                .line(*line)
+                .column(lexer.spans.get(index).map_or(0, |span| span.column)),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+/// Warnings for a runtime of no bytes or of `runtime` bytes over the EIP-170 limit and for
+/// init code of `initcode` bytes, when the program is deployed, over the EIP-3860 limit
+pub fn size_warnings(runtime: usize, initcode: Option<usize>) -> Vec<Diagnostic> {
+    let empty = (runtime == 0).then(|| {
+        Diagnostic::warning(
+            "the program assembles to no bytecode, it has no instructions or data",
+            "EmptyProgram",
+        )
+    });
+
+    let runtime = (runtime > MAX_CODE_SIZE).then(|| {
+        Diagnostic::warning(
+            format!(
+                "the runtime code is {} bytes, more than the {} byte limit of EIP-170",
+                runtime, MAX_CODE_SIZE
+            ),
+            "CodeSize",
+        )
+    });
+
+    let initcode = initcode
+        .filter(|&size| size > MAX_INITCODE_SIZE)
+        .map(|size| {
+            Diagnostic::warning(
+                format!(
+                    "the init code is {} bytes, more than the {} byte limit of EIP-3860",
+                    size, MAX_INITCODE_SIZE
+                ),
+                "InitcodeSize",
+            )
+        });
+
+    empty.into_iter().chain(runtime).chain(initcode).collect()
+}
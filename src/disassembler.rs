@@ -0,0 +1,270 @@
+use crate::{instruction::to_hex, Op};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// A PUSH at the end of the bytecode that has fewer bytes left than its operand needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruncatedPush {
+    /// Byte offset of the PUSH opcode
+    pub offset: usize,
+    pub op: Op,
+
+    /// Bytes of the operand that are there
+    pub available: usize,
+}
+
+/// Convert bytecode back into mnemonics, unknown bytes are rendered as `INVALID(0xXX)`
+/// and a PUSH cut off by the end of the bytecode as `PUSHn <incomplete: k of n bytes>`
+pub fn disassemble(bytecode: &[u8]) -> String {
+    instructions(bytecode, false)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Disassemble one instruction per line after its offset, counting from `base` for code
+/// that starts there in a larger contract, like `0x0100  PUSH1 0x80`
+pub fn disassemble_at(bytecode: &[u8], base: usize) -> String {
+    instructions(bytecode, false)
+        .into_iter()
+        .map(|(offset, text)| format!("0x{:04x}  {}\n", base + offset, text))
+        .collect()
+}
+
+/// Convert bytecode back into source that assembles to the same bytes, unknown bytes and a
+/// PUSH cut off by the end of the bytecode are `.data` rather than text for a reader
+///
+/// ```
+/// assert_eq!(easm::disassemble_source(&[0x60, 0x01, 0x0c]), "PUSH1 0x01 .data 0x0c");
+/// ```
+pub fn disassemble_source(bytecode: &[u8]) -> String {
+    instructions(bytecode, true)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Every instruction of the bytecode with its offset, as source for the assembler when
+/// `source` is set
+fn instructions(bytecode: &[u8], source: bool) -> Vec<(usize, String)> {
+    let mut result = vec![];
+    let mut cursor = 0;
+
+    while let Some(byte) = bytecode.get(cursor) {
+        let offset = cursor;
+        cursor += 1;
+
+        let text = match Op::from_byte(*byte) {
+            Some(op) => match op.is_push().filter(|&size| size > 0) {
+                Some(size) => {
+                    let end = (cursor + size as usize).min(bytecode.len());
+                    let incomplete = end - cursor < size as usize;
+                    let operand = match incomplete {
+                        true => format!("<incomplete: {} of {} bytes>", end - cursor, size),
+                        false => format!("0x{}", to_hex(&bytecode[cursor..end])),
+                    };
+
+                    cursor = end;
+                    match incomplete && source {
+                        true => format!(".data 0x{}", to_hex(&bytecode[offset..end])),
+                        false => format!("{} {}", op, operand),
+                    }
+                }
+
+                None => op.to_string(),
+            },
+
+            None if source => format!(".data 0x{:02x}", byte),
+            None => format!("INVALID(0x{:02x})", byte),
+        };
+
+        result.push((offset, text));
+    }
+
+    result
+}
+
+/// Every run of bytes that differs between the old and the new bytecode, after its offset
+/// with the bytes of each side and the instruction each run starts in, like
+/// `0x0001  01 -> 02  PUSH1 0x01 -> PUSH1 0x02`. Empty when the bytecodes are the same
+pub fn diff(old: &[u8], new: &[u8]) -> String {
+    let (old_instructions, new_instructions) = (instructions(old, false), instructions(new, false));
+    let end = old.len().max(new.len());
+
+    let mut result = String::new();
+    let mut cursor = 0;
+
+    while cursor < end {
+        if old.get(cursor) == new.get(cursor) {
+            cursor += 1;
+            continue;
+        }
+
+        let start = cursor;
+        while cursor < end && old.get(cursor) != new.get(cursor) {
+            cursor += 1;
+        }
+
+        result.push_str(&format!(
+            "0x{:04x}  {} -> {}  {} -> {}\n",
+            start,
+            run(old, start, cursor),
+            run(new, start, cursor),
+            instruction_at(old, &old_instructions, start),
+            instruction_at(new, &new_instructions, start)
+        ));
+    }
+
+    result
+}
+
+/// Hex of the bytes from `start` to `end` that the bytecode has, `-` for none
+fn run(bytecode: &[u8], start: usize, end: usize) -> String {
+    match bytecode.get(start..end.min(bytecode.len())) {
+        Some(bytes) if !bytes.is_empty() => to_hex(bytes),
+        _ => "-".to_string(),
+    }
+}
+
+/// The instruction of the bytecode the byte at `offset` is part of, `end of code` past
+/// its last byte
+fn instruction_at<'a>(
+    bytecode: &[u8],
+    instructions: &'a [(usize, String)],
+    offset: usize,
+) -> &'a str {
+    let index = instructions.partition_point(|&(start, _)| start <= offset);
+
+    match offset < bytecode.len() {
+        true => &instructions[index - 1].1,
+        false => "end of code",
+    }
+}
+
+/// Keep the hex of every byte and follow each opcode with its mnemonic, like
+/// `60(PUSH1) 01 00(STOP)`. Unknown bytes are followed by `(INVALID)`
+pub fn annotate(bytecode: &[u8]) -> String {
+    let mut result: Vec<String> = vec![];
+    let mut cursor = 0;
+
+    while let Some(byte) = bytecode.get(cursor) {
+        cursor += 1;
+
+        let op = Op::from_byte(*byte);
+        let mnemonic = op.as_ref().map_or("INVALID".to_string(), Op::to_string);
+        result.push(format!("{:02x}({})", byte, mnemonic));
+
+        let size = op.as_ref().and_then(Op::is_push).unwrap_or(0) as usize;
+        let end = (cursor + size).min(bytecode.len());
+
+        result.extend(
+            bytecode[cursor..end]
+                .iter()
+                .map(|byte| format!("{:02x}", byte)),
+        );
+        cursor = end;
+    }
+
+    result.join(" ")
+}
+
+/// The PUSH the bytecode ends in the middle of, if any
+pub fn truncated_push(bytecode: &[u8]) -> Option<TruncatedPush> {
+    let mut cursor = 0;
+
+    while let Some(byte) = bytecode.get(cursor) {
+        let op = Op::from_byte(*byte);
+        let size = op.as_ref().and_then(Op::is_push).unwrap_or(0) as usize;
+        let available = bytecode.len() - cursor - 1;
+
+        if available < size {
+            return op.map(|op| TruncatedPush {
+                offset: cursor,
+                op,
+                available,
+            });
+        }
+
+        cursor += 1 + size;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_pushes_and_invalid_bytes() {
+        assert_eq!(
+            disassemble(&[0x60, 0x01, 0x60, 0x02, 0x00, 0x0c]),
+            "PUSH1 0x01 PUSH1 0x02 STOP INVALID(0x0c)"
+        );
+    }
+
+    #[test]
+    fn offsets_count_from_the_base() {
+        let bytecode = [0x60, 0x80, 0x61, 0x01, 0x02, 0x00];
+
+        assert_eq!(
+            disassemble_at(&bytecode, 0),
+            "0x0000  PUSH1 0x80\n0x0002  PUSH2 0x0102\n0x0005  STOP\n"
+        );
+        assert_eq!(
+            disassemble_at(&bytecode, 0x100),
+            "0x0100  PUSH1 0x80\n0x0102  PUSH2 0x0102\n0x0105  STOP\n"
+        );
+    }
+
+    #[test]
+    fn annotate_keeps_the_bytes() {
+        assert_eq!(
+            annotate(&[0x60, 0x01, 0x60, 0x02, 0x00]),
+            "60(PUSH1) 01 60(PUSH1) 02 00(STOP)"
+        );
+        assert_eq!(
+            annotate(&[0x0c, 0x5f, 0x61, 0xff]),
+            "0c(INVALID) 5f(PUSH0) 61(PUSH2) ff"
+        );
+    }
+
+    #[test]
+    fn diff_shows_the_runs_that_differ() {
+        let old = [0x60, 0x01, 0x60, 0x02, 0x01];
+
+        assert_eq!(diff(&old, &old), "");
+        assert_eq!(
+            diff(&old, &[0x60, 0x01, 0x60, 0x03, 0x01, 0x00]),
+            "0x0003  02 -> 03  PUSH1 0x02 -> PUSH1 0x03\n\
+             0x0005  - -> 00  end of code -> STOP\n"
+        );
+    }
+
+    #[test]
+    fn truncated_push_data() {
+        let bytecode = [0x00, 0x7f, 0x01, 0x02, 0x03];
+
+        assert_eq!(
+            disassemble(&bytecode),
+            "STOP PUSH32 <incomplete: 3 of 32 bytes>"
+        );
+        assert_eq!(
+            truncated_push(&bytecode),
+            Some(TruncatedPush {
+                offset: 1,
+                op: Op::PUSH32,
+                available: 3,
+            })
+        );
+
+        assert_eq!(disassemble(&[0x61]), "PUSH2 <incomplete: 0 of 2 bytes>");
+        assert_eq!(truncated_push(&[0x61, 0x01, 0x02]), None);
+    }
+}
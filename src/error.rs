@@ -0,0 +1,371 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{error::Error, fmt::Display, ops::Range};
+
+/// Failures that `AssembleError` has a variant for, tagged where they happen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Kind {
+    UnknownToken(String),
+    BadOperandWidth { operand: String, expected: usize },
+    UndefinedLabel(String),
+    DuplicateLabel { label: String, first: usize },
+    OddLengthHex(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LexError {
+    line: Option<usize>,
+    description: String,
+
+    /// Source line of the error with the byte range of the offending token
+    context: Option<(String, Range<usize>)>,
+
+    kind: Option<Kind>,
+}
+
+impl LexError {
+    pub fn new(description: String) -> Self {
+        Self {
+            line: None,
+            description,
+            context: None,
+            kind: None,
+        }
+    }
+
+    /// Tag the Error with the variant it becomes in `AssembleError`
+    pub(crate) fn kind(mut self, kind: Kind) -> Self {
+        self.kind = Some(kind);
+
+        self
+    }
+
+    /// Set line that Error happend
+    pub(crate) fn line(mut self, l: usize) -> Self {
+        self.line = Some(l);
+
+        self
+    }
+
+    /// Set the line unless the word inside a block that caused the Error already did
+    pub(crate) fn or_line(mut self, l: usize) -> Self {
+        self.line = self.line.or(Some(l));
+
+        self
+    }
+
+    /// Set the source line and the byte range of the token that caused the Error
+    pub(crate) fn context(mut self, source_line: &str, token: Range<usize>) -> Self {
+        self.context = Some((source_line.to_string(), token));
+
+        self
+    }
+
+    /// Column of the token that caused the Error starting at 1, when it is known
+    fn column(&self) -> Option<usize> {
+        self.context
+            .as_ref()
+            .map(|(source_line, token)| source_line[..token.start].chars().count() + 1)
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.line {
+            Some(line) => write!(
+                f,
+                "Error when lexing on line {} with description {}",
+                line, self.description
+            ),
+
+            None => write!(f, "Error when lexing with description {}", self.description),
+        }?;
+
+        if let Some((source_line, token)) = &self.context {
+            // Keep tabs so the marker lines up with the source
+            let indent: String = source_line[..token.start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let marker = "^".repeat(source_line[token.clone()].chars().count().max(1));
+
+            write!(f, "\n{}\n{}{}", source_line, indent, marker)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for LexError {
+    fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    description: String,
+
+    /// Line of the error, which the description already names
+    line: Option<usize>,
+
+    /// Column of the instruction the error is in starting at 1, when its span is known
+    column: Option<usize>,
+
+    kind: Option<Kind>,
+}
+
+impl ParseError {
+    pub fn new(description: String) -> Self {
+        Self {
+            description,
+            line: None,
+            column: None,
+            kind: None,
+        }
+    }
+
+    /// Set line that Error happend
+    pub(crate) fn line(mut self, l: usize) -> Self {
+        self.line = Some(l);
+
+        self
+    }
+
+    /// Set the column of the instruction the Error is in, a column of 0 is not known
+    pub(crate) fn column(mut self, column: usize) -> Self {
+        self.column = (column > 0).then_some(column);
+
+        self
+    }
+
+    /// Tag the Error with the variant it becomes in `AssembleError`
+    pub(crate) fn kind(mut self, kind: Kind) -> Self {
+        self.kind = Some(kind);
+
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Error when parsing with description {}",
+            self.description
+        )
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Any error of assembling, the failures a caller may want to handle get their own
+/// variant with the line and column they happened on and `message`, the full description
+#[derive(Debug, Clone)]
+pub enum AssembleError {
+    /// A word that is neither an opcode, a label nor a literal
+    UnknownToken {
+        line: Option<usize>,
+        column: Option<usize>,
+        token: String,
+        message: String,
+    },
+
+    /// An operand that does not fit the bytes it has
+    BadOperandWidth {
+        line: Option<usize>,
+        column: Option<usize>,
+        operand: String,
+        expected: usize,
+        message: String,
+    },
+
+    UndefinedLabel {
+        line: Option<usize>,
+        column: Option<usize>,
+        label: String,
+        message: String,
+    },
+
+    /// A label defined again on `line` after its first definition on `first`
+    DuplicateLabel {
+        line: Option<usize>,
+        column: Option<usize>,
+        label: String,
+        first: usize,
+        message: String,
+    },
+
+    OddLengthHex {
+        line: Option<usize>,
+        column: Option<usize>,
+        literal: String,
+        message: String,
+    },
+
+    Lex(LexError),
+    Parse(ParseError),
+
+    /// Every error of a pass that went on after its first failure, in source order
+    Many(Vec<AssembleError>),
+}
+
+impl AssembleError {
+    /// Combine the errors of a pass, a single error is returned as it is
+    pub(crate) fn many<E: Into<AssembleError>>(errors: Vec<E>) -> Self {
+        let mut errors: Vec<Self> = errors.into_iter().map(Into::into).collect();
+
+        match errors.len() {
+            1 => errors.remove(0),
+            _ => Self::Many(errors),
+        }
+    }
+
+    /// The errors this is made of, only `Many` has more than one
+    pub fn errors(&self) -> &[AssembleError] {
+        match self {
+            Self::Many(errors) => errors,
+            error => core::slice::from_ref(error),
+        }
+    }
+
+    /// Line of the error, the first one of `Many`
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::UnknownToken { line, .. }
+            | Self::BadOperandWidth { line, .. }
+            | Self::UndefinedLabel { line, .. }
+            | Self::DuplicateLabel { line, .. }
+            | Self::OddLengthHex { line, .. } => *line,
+            Self::Lex(error) => error.line,
+            Self::Parse(error) => error.line,
+            Self::Many(errors) => errors.first().and_then(Self::line),
+        }
+    }
+
+    /// Column of the error starting at 1, when the lexer or the span of an instruction
+    /// gives it
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            Self::UnknownToken { column, .. }
+            | Self::BadOperandWidth { column, .. }
+            | Self::UndefinedLabel { column, .. }
+            | Self::DuplicateLabel { column, .. }
+            | Self::OddLengthHex { column, .. } => *column,
+            Self::Lex(error) => error.column(),
+            Self::Parse(error) => error.column,
+            Self::Many(errors) => errors.first().and_then(Self::column),
+        }
+    }
+
+    /// Name of the variant, like `UndefinedLabel`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownToken { .. } => "UnknownToken",
+            Self::BadOperandWidth { .. } => "BadOperandWidth",
+            Self::UndefinedLabel { .. } => "UndefinedLabel",
+            Self::DuplicateLabel { .. } => "DuplicateLabel",
+            Self::OddLengthHex { .. } => "OddLengthHex",
+            Self::Lex(_) => "Lex",
+            Self::Parse(_) => "Parse",
+            Self::Many(_) => "Many",
+        }
+    }
+
+    /// Turn a tagged lex or parse error into its variant
+    fn tagged(kind: Kind, line: Option<usize>, column: Option<usize>, message: String) -> Self {
+        match kind {
+            Kind::UnknownToken(token) => Self::UnknownToken {
+                line,
+                column,
+                token,
+                message,
+            },
+            Kind::BadOperandWidth { operand, expected } => Self::BadOperandWidth {
+                line,
+                column,
+                operand,
+                expected,
+                message,
+            },
+            Kind::UndefinedLabel(label) => Self::UndefinedLabel {
+                line,
+                column,
+                label,
+                message,
+            },
+            Kind::DuplicateLabel { label, first } => Self::DuplicateLabel {
+                line,
+                column,
+                label,
+                first,
+                message,
+            },
+            Kind::OddLengthHex(literal) => Self::OddLengthHex {
+                line,
+                column,
+                literal,
+                message,
+            },
+        }
+    }
+}
+
+impl From<LexError> for AssembleError {
+    fn from(error: LexError) -> Self {
+        match error.kind.clone() {
+            Some(kind) => Self::tagged(kind, error.line, error.column(), error.to_string()),
+            None => Self::Lex(error),
+        }
+    }
+}
+
+impl From<ParseError> for AssembleError {
+    fn from(error: ParseError) -> Self {
+        match error.kind.clone() {
+            Some(kind) => Self::tagged(kind, error.line, error.column, error.to_string()),
+            None => Self::Parse(error),
+        }
+    }
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownToken { message, .. }
+            | Self::BadOperandWidth { message, .. }
+            | Self::UndefinedLabel { message, .. }
+            | Self::DuplicateLabel { message, .. }
+            | Self::OddLengthHex { message, .. } => write!(f, "{}", message),
+            Self::Lex(error) => write!(f, "{}", error),
+            Self::Parse(error) => write!(f, "{}", error),
+            Self::Many(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+
+                    write!(f, "{}", error)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for AssembleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Lex(error) => Some(error),
+            Self::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{LexError, Op};
+use alloc::format;
+
+/// EVM hard forks that introduced new opcodes, in release order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fork {
+    Frontier,
+    Homestead,
+    Byzantium,
+    Constantinople,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    #[default]
+    Cancun,
+}
+
+impl Fork {
+    /// Every fork in release order
+    pub const ALL: &'static [Fork] = &[
+        Self::Frontier,
+        Self::Homestead,
+        Self::Byzantium,
+        Self::Constantinople,
+        Self::Istanbul,
+        Self::Berlin,
+        Self::London,
+        Self::Paris,
+        Self::Shanghai,
+        Self::Cancun,
+    ];
+
+    /// Lowercase name of the fork, as accepted by `from_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Frontier => "frontier",
+            Self::Homestead => "homestead",
+            Self::Byzantium => "byzantium",
+            Self::Constantinople => "constantinople",
+            Self::Istanbul => "istanbul",
+            Self::Berlin => "berlin",
+            Self::London => "london",
+            Self::Paris => "paris",
+            Self::Shanghai => "shanghai",
+            Self::Cancun => "cancun",
+        }
+    }
+
+    /// Find a fork by its lowercase name, `merge` is accepted for paris
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "merge" => Some(Self::Paris),
+            name => Self::ALL.iter().copied().find(|fork| fork.name() == name),
+        }
+    }
+}
+
+/// Check that every token is available in `fork`
+pub fn check_fork(tokens: &[(Op, &str, usize)], fork: Fork) -> Result<(), LexError> {
+    for (op, _, line) in tokens {
+        let introduced = op.introduced_in();
+
+        if introduced > fork {
+            return Err(LexError::new(format!(
+                "{} needs the {} fork but the target is {}",
+                op,
+                introduced.name(),
+                fork.name()
+            ))
+            .line(*line));
+        }
+    }
+
+    Ok(())
+}
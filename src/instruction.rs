@@ -0,0 +1,135 @@
+use crate::{Op, ParseError, Span};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// An opcode grouped with its operand, as emitted into the bytecode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub op: Op,
+
+    /// Opcode byte, `None` for data which only emits its operand
+    pub opcode: Option<u8>,
+
+    /// Bytes that follow the opcode, like the value of a PUSH
+    pub operand: Option<Vec<u8>>,
+
+    /// Source line of the opcode
+    pub line: usize,
+
+    /// Source column of the opcode starting at 1, 0 when it is not known
+    pub column: usize,
+
+    /// Characters of the opcode in the source, 0 when it is not known
+    pub len: usize,
+}
+
+impl Instruction {
+    /// Bytes this instruction emits
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.opcode.into_iter().collect();
+        bytes.extend(self.operand.iter().flatten());
+
+        bytes
+    }
+
+    /// Where the opcode is in the source
+    pub fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            len: self.len,
+        }
+    }
+
+    /// Number of bytes this instruction emits
+    pub fn size(&self) -> usize {
+        self.opcode.iter().count() + self.operand.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Check that the opcode byte and the operand width agree with the op
+    pub fn validate(&self) -> Result<(), ParseError> {
+        let error = |description: String| {
+            Err(ParseError::new(format!(
+                "{} on line {} {}",
+                self.op, self.line, description
+            )))
+        };
+
+        match &self.op {
+            Op::VALUE(_) | Op::PUSH => return error("must be resolved first".to_string()),
+
+            Op::DATA(bytes) => {
+                if self.opcode.is_some() || self.operand.as_ref() != Some(bytes) {
+                    return error("must only emit its bytes".to_string());
+                }
+
+                return Ok(());
+            }
+
+            _ => {}
+        }
+
+        if self.opcode != self.op.opcode() {
+            return error(format!(
+                "has the opcode byte {:?} instead of {:?}",
+                self.opcode,
+                self.op.opcode()
+            ));
+        }
+
+        match (self.op.is_push(), &self.operand) {
+            (Some(size), Some(operand)) if operand.len() == size as usize => Ok(()),
+            (Some(0), None) => Ok(()),
+            (Some(size), _) => error(format!("expects an operand of {} bytes", size)),
+            (None, None) => Ok(()),
+            (None, Some(_)) => error("takes no operand".to_string()),
+        }
+    }
+
+    /// Hex encoded bytes of this instruction
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes())
+    }
+}
+
+/// Bytes of the whole program
+pub fn bytecode(instructions: &[Instruction]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(instructions.iter().map(Instruction::size).sum());
+
+    for instruction in instructions {
+        bytes.extend(instruction.opcode);
+        bytes.extend(instruction.operand.iter().flatten());
+    }
+
+    bytes
+}
+
+/// Encode bytes as lowercase hex without a prefix
+pub fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[(byte >> 4) as usize] as char);
+        hex.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+
+    hex
+}
+
+/// Decode a hex literal (with an optional 0x prefix), `None` if it is not valid hex
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
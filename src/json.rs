@@ -0,0 +1,202 @@
+use crate::{keccak::keccak256, to_hex, Diagnostic, Fork, Instruction, Level, Op};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Serialize tokens as a JSON array with one object per token, like
+/// `{"op": "PUSH1", "byte": "60", "line": 1}`, values, labels and data also get a `"value"`
+pub fn tokens_json(tokens: &[(Op, &str, usize)]) -> String {
+    let objects: Vec<String> = tokens
+        .iter()
+        .map(|(op, byte, line)| {
+            let (name, value) = match op {
+                Op::VALUE(value) => ("VALUE".to_string(), Some(value.clone())),
+                Op::LABEL(name) => ("LABEL".to_string(), Some(name.clone())),
+                Op::DATA(bytes) => ("DATA".to_string(), Some(format!("0x{}", to_hex(bytes)))),
+                op => (op.to_string(), None),
+            };
+
+            let value = value
+                .map(|value| format!(", \"value\": {}", string(&value)))
+                .unwrap_or_default();
+
+            format!(
+                "  {{\"op\": {}{}, \"byte\": {}, \"line\": {}}}",
+                string(&name),
+                value,
+                string(byte),
+                line
+            )
+        })
+        .collect();
+
+    if objects.is_empty() {
+        return "[]".to_string();
+    }
+
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+/// Serialize the byte range of every instruction with where it came from, like
+/// `{"start": 0, "length": 2, "line": 1, "col": 1}`
+pub fn source_map(instructions: &[Instruction]) -> String {
+    let mut start = 0;
+
+    let objects: Vec<String> = instructions
+        .iter()
+        .map(|instruction| {
+            let object = format!(
+                "  {{\"start\": {}, \"length\": {}, \"line\": {}, \"col\": {}}}",
+                start,
+                instruction.size(),
+                instruction.line,
+                instruction.column
+            );
+            start += instruction.size();
+
+            object
+        })
+        .collect();
+
+    if objects.is_empty() {
+        return "[]".to_string();
+    }
+
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+/// Serialize diagnostics as a JSON array for editors, like `{"severity": "error",
+/// "message": "...", "line": 1, "col": 5, "code": "UnknownToken"}`, an unknown line or
+/// column is `null`
+pub fn diagnostics_json(diagnostics: &[Diagnostic]) -> String {
+    let number = |value: Option<usize>| value.map_or("null".to_string(), |value| value.to_string());
+
+    let objects: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let severity = match diagnostic.level {
+                Level::Warning => "warning",
+                Level::Error => "error",
+            };
+
+            format!(
+                "  {{\"severity\": {}, \"message\": {}, \"line\": {}, \"col\": {}, \"code\": {}}}",
+                string(severity),
+                string(&diagnostic.message),
+                number(diagnostic.line),
+                number(diagnostic.column),
+                string(diagnostic.code)
+            )
+        })
+        .collect();
+
+    if objects.is_empty() {
+        return "[]".to_string();
+    }
+
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+/// Serialize a record of a build for CI to check an artifact against the exact sources it
+/// came from. Every input file and the bytecode have their keccak-256, like
+/// `{"inputs": [{"file": "a.easm", "keccak256": "0x..."}], "fork": "cancun", ...}`
+pub fn manifest(
+    inputs: &[(&str, &[u8])],
+    fork: Fork,
+    bytecode: &[u8],
+    labels: &BTreeMap<String, usize>,
+    constants: &BTreeMap<String, String>,
+) -> String {
+    let hash = |bytes: &[u8]| string(&format!("0x{}", to_hex(&keccak256(bytes))));
+
+    let inputs: Vec<String> = inputs
+        .iter()
+        .map(|(file, content)| {
+            format!(
+                "    {{\"file\": {}, \"keccak256\": {}}}",
+                string(file),
+                hash(content)
+            )
+        })
+        .collect();
+
+    let inputs = match inputs.is_empty() {
+        true => "[]".to_string(),
+        false => format!("[\n{}\n  ]", inputs.join(",\n")),
+    };
+
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, offset)| format!("{}: {}", string(name), offset))
+        .collect();
+
+    let constants: Vec<String> = constants
+        .iter()
+        .map(|(name, value)| format!("{}: {}", string(name), string(value)))
+        .collect();
+
+    format!(
+        "{{\n  \"inputs\": {},\n  \"fork\": {},\n  \"keccak256\": {},\n  \"length\": {},\n  \
+         \"labels\": {{{}}},\n  \"constants\": {{{}}}\n}}",
+        inputs,
+        string(fork.name()),
+        hash(bytecode),
+        bytecode.len(),
+        labels.join(", "),
+        constants.join(", ")
+    )
+}
+
+/// Quote and escape a JSON string
+pub(crate) fn string(value: &str) -> String {
+    let mut json = String::from('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_hashes_the_inputs_and_the_bytecode() {
+        let manifest = manifest(
+            &[("empty.easm", b"")],
+            Fork::London,
+            &[0x00],
+            &BTreeMap::from([("start".to_string(), 0)]),
+            &BTreeMap::new(),
+        );
+
+        // keccak-256 of no bytes and of a single zero byte
+        assert_eq!(
+            manifest,
+            "{\n  \"inputs\": [\n    {\"file\": \"empty.easm\", \"keccak256\": \
+             \"0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470\"}\n  ],\n  \
+             \"fork\": \"london\",\n  \
+             \"keccak256\": \"0xbc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a\",\n  \
+             \"length\": 1,\n  \"labels\": {\"start\": 0},\n  \"constants\": {}\n}"
+        );
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(string("a\"b\\c\n"), r#""a\"b\\c\n""#);
+    }
+}
@@ -0,0 +1,133 @@
+/// Round constants of Keccak-f[1600]
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation of every lane in the order the rho and pi steps visit them
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// Bytes absorbed per permutation for a 256-bit output
+const RATE: usize = 136;
+
+/// Keccak-256 as used by the EVM, with the original 0x01 padding rather than SHA-3's
+pub(crate) fn keccak256(data: &[u8]) -> [u8; 32] {
+    sponge(data, 0x01)
+}
+
+/// Absorb the data padded with the domain byte and squeeze out 32 bytes
+fn sponge(data: &[u8], domain: u8) -> [u8; 32] {
+    let mut state = [0u64; 25];
+
+    let mut padded = data.to_vec();
+    padded.push(domain);
+    padded.resize(padded.len().div_ceil(RATE) * RATE, 0);
+    *padded.last_mut().unwrap() |= 0x80;
+
+    for block in padded.chunks(RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        permute(&mut state);
+    }
+
+    let mut hash = [0u8; 32];
+    for (bytes, lane) in hash.chunks_mut(8).zip(state) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+
+    hash
+}
+
+/// Keccak-f[1600] permutation
+fn permute(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // Theta
+        let mut columns = [0u64; 5];
+        for (x, column) in columns.iter_mut().enumerate() {
+            *column = (0..5).fold(0, |parity, y| parity ^ state[x + 5 * y]);
+        }
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for (&lane, rotation) in LANES.iter().zip(ROTATIONS) {
+            let current = state[lane];
+            state[lane] = last.rotate_left(rotation);
+            last = current;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let row: [u64; 5] = core::array::from_fn(|x| state[x + 5 * y]);
+            for x in 0..5 {
+                state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        state[0] ^= round_constant;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_hex;
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(
+            to_hex(&keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            to_hex(&keccak256(b"The quick brown fox jumps over the lazy dog")),
+            "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15"
+        );
+
+        // SHA3-256 only differs in the padding, it covers input longer than a block
+        assert_eq!(
+            to_hex(&sponge(&[b'a'; 200], 0x06)),
+            "cce34485baf2bf2aca99b94833892a4f52896d3d153f7b840cc4f9fe695f1387"
+        );
+        assert_eq!(
+            to_hex(&keccak256(b"transfer(address,uint256)"))[..8],
+            *"a9059cbb"
+        );
+    }
+}
@@ -0,0 +1,1702 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{iter, iter::Enumerate, ops::Range};
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Kind,
+    instruction::{decode_hex, to_hex},
+    keccak::keccak256,
+    literal::{
+        char_to_byte, decimal_to_bytes, digits_to_bytes, is_char, is_decimal, is_string,
+        negative_to_bytes, radix_literal, string_to_bytes, strip_separators,
+    },
+    op::{hex_byte, is_hex, is_identifier},
+    AssembleError, LexError, Op,
+};
+
+// Without std there are no files, so there is never a path to resolve an include against
+#[cfg(not(feature = "std"))]
+type Path = str;
+#[cfg(not(feature = "std"))]
+type PathBuf = String;
+
+#[derive(Debug, Clone, Default)]
+pub struct Lexer {
+    /// Result, every token with its opcode byte and the line it was found on
+    pub result: Vec<(Op, &'static str, usize)>,
+
+    /// Where the word each token of the result was lexed from is, parallel to the result
+    pub spans: Vec<Span>,
+
+    /// Constants declared with `#define NAME value`
+    pub constants: BTreeMap<String, String>,
+
+    /// Macros declared with `macro NAME { ... }`
+    pub macros: BTreeMap<String, Vec<String>>,
+
+    /// Encode decimal, binary and octal PUSH operands least significant byte first,
+    /// hex operands are always kept as written
+    pub little_endian: bool,
+
+    /// Most tokens the program may have, lexing stops with an error past it so untrusted
+    /// source can not exhaust memory
+    pub max_ops: Option<usize>,
+
+    /// Directories searched in order for an `#include` that is not next to the file
+    /// including it
+    pub include_paths: Vec<PathBuf>,
+
+    /// Opcodes added to the built-in ones by uppercase mnemonic, found before them
+    pub opcodes: BTreeMap<String, u8>,
+
+    /// Index in the result of the first token of the `.runtime` section, the tokens before
+    /// it are the `.init` section. `None` for a program without sections
+    pub runtime: Option<usize>,
+
+    /// Every file read so far, lexed or included, in the order each was first read
+    pub files: Vec<PathBuf>,
+
+    /// Constants given from outside the source, a `#define` of one of them is skipped so
+    /// the value given from outside is kept
+    pub overrides: BTreeSet<String>,
+
+    /// Constants defined ahead of lexing whose `#define` is not reached yet
+    forward: BTreeSet<String>,
+
+    /// Tokens of the program that are no longer in the result, taken by `Tokens` or
+    /// lexed into another lexer before this one
+    flushed: usize,
+}
+
+/// A word of the source with where it was found
+#[derive(Debug, Clone)]
+struct Word {
+    text: String,
+    line: usize,
+
+    /// Source line the word was split from, with the byte range of the word in it
+    source_line: Rc<str>,
+    range: Range<usize>,
+}
+
+impl Word {
+    /// Column of the word starting at 1 and its length, both in characters
+    fn position(&self) -> (usize, usize) {
+        let column = self.source_line[..self.range.start].chars().count() + 1;
+
+        (column, self.source_line[self.range.clone()].chars().count())
+    }
+}
+
+/// Lines of the source without a leading byte order mark, ended by `\n`, `\r\n` or a lone
+/// `\r` so files saved by any editor keep their line numbers
+#[derive(Debug, Clone)]
+struct Lines<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Lines<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            rest: source.strip_prefix('\u{feff}').unwrap_or(source),
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let end = self.rest.find(['\n', '\r']).unwrap_or(self.rest.len());
+        let line = &self.rest[..end];
+
+        let ending = match &self.rest[end..] {
+            rest if rest.starts_with("\r\n") => 2,
+            "" => 0,
+            _ => 1,
+        };
+        self.rest = &self.rest[end + ending..];
+
+        Some(line)
+    }
+}
+
+/// Where a token was found in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+
+    /// Column of the word the token was lexed from, starting at 1
+    pub column: usize,
+
+    /// Characters of the word the token was lexed from
+    pub len: usize,
+}
+
+/// Largest offset, boundary or count of a directive, far past the size of any contract
+/// but small enough that the padding it makes fits in memory
+const MAX_OFFSET: usize = 1 << 24;
+
+/// Most tokens `.repeat` and macros may expand a program to without `max_ops`, far past the
+/// tokens of any contract but few enough that nested expansions can not exhaust memory
+const MAX_EXPANSION: usize = 1 << 20;
+
+/// A token with its opcode byte and the line it was found on
+type Token = (Op, &'static str, usize);
+
+/// Words a word may take after it, `.align N 0xPP` takes the most
+const LOOKAHEAD: usize = 2;
+
+/// Label a section ends with, like the `.init` or `.runtime` label it starts with it can
+/// not be written in the source. `collect` takes them out of the tokens
+const SECTION_END: &str = ".end";
+
+/// Lexes the source lazily, reading lines only as far as the next token needs.
+/// A block is read to its end before it is lexed and included files are read whole
+#[derive(Debug)]
+pub struct Tokens<'a> {
+    lexer: Lexer,
+    lines: Enumerate<Lines<'a>>,
+
+    /// File the source was read from, includes are resolved relative to it
+    file: Option<PathBuf>,
+    including: Vec<PathBuf>,
+    conditions: Vec<Condition>,
+
+    /// Words read from the source but not lexed yet
+    words: VecDeque<Word>,
+
+    /// Tokens that no later word can change, with their span, and errors in source order
+    ready: VecDeque<Result<(Token, Span), LexError>>,
+
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// Lex the source, included files are resolved relative to the current directory.
+    /// The source is read as it goes, so a constant is only known below its `#define`
+    pub fn new(source: &'a str) -> Self {
+        Self::with_lexer(Lexer::new(), source, None)
+    }
+
+    fn with_lexer(lexer: Lexer, source: &'a str, file: Option<PathBuf>) -> Self {
+        Self {
+            lexer,
+            lines: Lines::new(source).enumerate(),
+            including: file.iter().cloned().collect(),
+            file,
+            conditions: vec![],
+            words: VecDeque::new(),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// The next token with the span it was lexed from
+    fn next_token(&mut self) -> Option<Result<(Token, Span), LexError>> {
+        loop {
+            // The last two tokens can still change, a PUSH takes the size of a string operand
+            // after it and an operand may be followed by the rest of an expression
+            let keep = if self.done { 0 } else { 2 };
+            self.flush(keep);
+
+            if let Some(token) = self.ready.pop_front() {
+                return Some(token);
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Err(error) = self.fill() {
+                return Some(Err(error));
+            }
+
+            let Some(word) = self.words.pop_front() else {
+                self.done = true;
+                continue;
+            };
+
+            let (column, len) = word.position();
+            let line = word.line;
+
+            let lexed = self.lexer.lex_directive(word, &mut self.words);
+
+            // A macro expands to many tokens, they all take the span of its name
+            self.lexer.spans_of_word(column, len);
+
+            if let Err(error) = lexed {
+                // A literal after a bad word is most likely its operand, not another error.
+                // An opcode like `add` is spelled with hex digits but is not a literal
+                while self.words.front().is_some_and(|word| {
+                    (is_hex(&word.text) || is_decimal(&word.text))
+                        && matches!(Op::from_str(&word.text), Ok((Op::VALUE(_), _)))
+                }) {
+                    self.words.pop_front();
+                }
+
+                // The tokens before the error come out first
+                self.flush(0);
+                self.ready.push_back(Err(error.or_line(line)));
+            }
+        }
+    }
+
+    /// Move all but the last `keep` lexed tokens to the ready tokens
+    fn flush(&mut self, keep: usize) {
+        let ready = self.lexer.result.len().saturating_sub(keep);
+        self.lexer.flushed += ready;
+
+        self.ready.extend(
+            self.lexer
+                .result
+                .drain(..ready)
+                .zip(self.lexer.spans.drain(..ready))
+                .map(Ok),
+        );
+    }
+
+    /// Read lines until the next word has every word it may take after it
+    fn fill(&mut self) -> Result<(), LexError> {
+        while !self.filled() {
+            let Some((number, line)) = self.lines.next() else {
+                return match self.conditions.pop() {
+                    Some(condition) => Err(Lexer::unclosed(&condition)),
+                    None => Ok(()),
+                };
+            };
+
+            self.lexer
+                .preprocess(
+                    line,
+                    number + 1,
+                    self.file.as_deref(),
+                    &mut self.including,
+                    &mut self.conditions,
+                    &mut self.words,
+                )
+                .map_err(|error| error.or_line(number + 1))?;
+        }
+
+        Ok(())
+    }
+
+    /// Are enough words read to lex the next one, a block must be read to its end
+    fn filled(&self) -> bool {
+        match self.words.front().map(|word| word.text.as_str()) {
+            Some("macro" | ".repeat" | ".init" | ".runtime") => {
+                let mut depth = 0;
+
+                self.words.iter().any(|word| {
+                    match word.text.as_str() {
+                        "{" => depth += 1,
+                        "}" | "endmacro" => depth -= 1,
+                        _ => return false,
+                    }
+
+                    depth <= 0
+                })
+            }
+
+            _ => self.words.len() > LOOKAHEAD,
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<(Op, Span), AssembleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().map(|token| {
+            token
+                .map(|((op, _, _), span)| (op, span))
+                .map_err(AssembleError::from)
+        })
+    }
+}
+
+/// An `#ifdef` block being read
+#[derive(Debug, Clone, Copy)]
+struct Condition {
+    /// Are the blocks around this one included
+    enclosing: bool,
+
+    /// Was the name of the `#ifdef` defined
+    defined: bool,
+
+    /// Past the `#else` of the block
+    otherwise: bool,
+
+    /// Line of the `#ifdef`
+    line: usize,
+}
+
+impl Condition {
+    fn active(&self) -> bool {
+        self.enclosing && self.defined != self.otherwise
+    }
+}
+
+impl Lexer {
+    pub fn new() -> Self {
+        Self {
+            result: vec![],
+            spans: vec![],
+            constants: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            little_endian: false,
+            max_ops: None,
+            include_paths: vec![],
+            opcodes: BTreeMap::new(),
+            runtime: None,
+            files: vec![],
+            overrides: BTreeSet::new(),
+            forward: BTreeSet::new(),
+            flushed: 0,
+        }
+    }
+
+    /// Start lexing, included files are resolved relative to the current directory
+    pub fn lex(&mut self, source: &str) -> Result<(), AssembleError> {
+        self.define_ahead(source, None, &mut vec![])?;
+        let lexer = self.take_state();
+
+        self.collect(Tokens::with_lexer(lexer, source, None))
+    }
+
+    /// Read and lex the file, included files are resolved relative to it
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn lex_file(&mut self, path: &Path) -> Result<(), AssembleError> {
+        let (path, source) = Self::read(path)?;
+        self.read_file(&path);
+        self.define_ahead(&source, Some(&path), &mut vec![path.clone()])?;
+        let lexer = self.take_state();
+
+        self.collect(Tokens::with_lexer(lexer, &source, Some(path)))
+    }
+
+    /// Add an opcode for a chain with opcodes of its own. A mnemonic that is already a
+    /// built-in opcode is an error unless `replace` is set, then the new byte is used
+    pub fn add_opcode(&mut self, name: &str, byte: u8, replace: bool) -> Result<(), LexError> {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) || !is_identifier(name) {
+            return Err(LexError::new(format!(
+                "{} is not a valid opcode mnemonic",
+                name
+            )));
+        }
+
+        if let Ok((op, _)) = Op::from_str(name) {
+            if !matches!(op, Op::VALUE(_) | Op::LABEL(_)) && !replace {
+                return Err(LexError::new(format!(
+                    "custom opcode {} is already the built-in {}",
+                    name, op
+                )));
+            }
+        }
+
+        self.opcodes.insert(name.to_ascii_uppercase(), byte);
+
+        Ok(())
+    }
+
+    /// Add every opcode of a table with a line like `FOO = 0xf9` for each, lines starting
+    /// with `#` are comments
+    pub fn add_opcodes(&mut self, table: &str, replace: bool) -> Result<(), LexError> {
+        for (number, line) in table
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let opcode = line
+                .split_once('=')
+                .map(|(name, byte)| (name.trim(), byte.trim()))
+                .and_then(|(name, byte)| {
+                    let byte = u8::from_str_radix(byte.strip_prefix("0x")?, 16).ok()?;
+
+                    Some((name, byte))
+                });
+
+            let Some((name, byte)) = opcode else {
+                return Err(LexError::new(format!(
+                    "line {} of the opcode table is not like FOO = 0xf9",
+                    number
+                )));
+            };
+
+            self.add_opcode(name, byte, replace)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the source to its end and lex it, included files are resolved relative to the
+    /// working directory
+    #[cfg(feature = "std")]
+    pub fn lex_reader(&mut self, mut reader: impl std::io::Read) -> Result<(), AssembleError> {
+        let mut source = String::new();
+        reader
+            .read_to_string(&mut source)
+            .map_err(|error| LexError::new(format!("can not read the source: {}", error)))?;
+
+        self.lex(&source)
+    }
+
+    /// Split the tokens of the `.runtime` section off into a lexer of their own, leaving the
+    /// `.init` section. `None` for a program without sections
+    pub fn split_runtime(&mut self) -> Option<Lexer> {
+        let start = self.runtime.take()?;
+        let result = self.result.split_off(start);
+        let spans = self.spans.split_off(start.min(self.spans.len()));
+
+        Some(Self {
+            result,
+            spans,
+            ..self.clone()
+        })
+    }
+
+    /// Give the tokens lexed from the word at `column` the span of the word, on the line of
+    /// each token.
+    /// A word may also merge the tokens before it, whose spans are dropped with them
+    fn spans_of_word(&mut self, column: usize, len: usize) {
+        self.spans.truncate(self.result.len());
+
+        for index in self.spans.len()..self.result.len() {
+            let (_, _, line) = self.result[index];
+            self.spans.push(Span { line, column, len });
+        }
+    }
+
+    /// Move the constants and macros into a new lexer, leaving the tokens lexed so far
+    fn take_state(&mut self) -> Self {
+        Self {
+            result: vec![],
+            spans: vec![],
+            constants: core::mem::take(&mut self.constants),
+            macros: core::mem::take(&mut self.macros),
+            little_endian: self.little_endian,
+            max_ops: self.max_ops,
+            include_paths: self.include_paths.clone(),
+            opcodes: self.opcodes.clone(),
+            runtime: None,
+            files: vec![],
+            overrides: self.overrides.clone(),
+            forward: core::mem::take(&mut self.forward),
+            flushed: self.flushed + self.result.len(),
+        }
+    }
+
+    /// Append every token to the result, lexing goes on after a bad word so every error
+    /// is reported
+    fn collect(&mut self, mut tokens: Tokens) -> Result<(), AssembleError> {
+        let mut errors = vec![];
+
+        // Was there an `.init` section, is the token in a section and the first line outside
+        let (mut init, mut inside, mut outside) = (false, false, None);
+
+        while let Some(token) = tokens.next_token() {
+            match token {
+                Ok(((Op::LABEL(label), _, line), _)) if label.starts_with('.') => {
+                    let error = match label.as_str() {
+                        SECTION_END => {
+                            inside = false;
+                            continue;
+                        }
+
+                        _ if inside => format!("{} can not be in another section", label),
+
+                        _ if self.runtime.is_some() => {
+                            format!("{} comes after the .runtime section", label)
+                        }
+
+                        ".init" if init => "the program has a second .init section".to_string(),
+
+                        ".init" => {
+                            (init, inside) = (true, true);
+                            continue;
+                        }
+
+                        _ => {
+                            self.runtime = Some(self.result.len());
+                            inside = true;
+                            continue;
+                        }
+                    };
+
+                    // The tokens of the section are not outside of one
+                    inside = true;
+                    errors.push(LexError::new(error).line(line));
+                }
+
+                Ok((token, span)) => {
+                    if !inside {
+                        outside.get_or_insert(token.2);
+                    }
+
+                    if self.max_ops.is_some_and(|max| self.result.len() == max) {
+                        errors.push(
+                            LexError::new(format!(
+                                "the program has more than the limit of {} tokens",
+                                self.result.len()
+                            ))
+                            .line(token.2),
+                        );
+
+                        break;
+                    }
+
+                    self.result.push(token);
+                    self.spans.push(span);
+                }
+
+                Err(error) => errors.push(error),
+            }
+        }
+
+        self.constants = tokens.lexer.constants;
+        self.macros = tokens.lexer.macros;
+
+        if init && self.runtime.is_none() {
+            errors.push(LexError::new(
+                "the .init section has no .runtime section after it".to_string(),
+            ));
+        }
+
+        if let Some(line) = outside.filter(|_| init || self.runtime.is_some()) {
+            errors.push(
+                LexError::new(format!(
+                    "line {} is outside the .init and .runtime sections",
+                    line
+                ))
+                .line(line),
+            );
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(AssembleError::many(errors)),
+        }
+    }
+
+    /// Split the source into words with their line, handling the preprocessor directives
+    fn collect_words(
+        &mut self,
+        source: &str,
+        file: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+        words: &mut VecDeque<Word>,
+    ) -> Result<(), LexError> {
+        let mut conditions: Vec<Condition> = vec![];
+
+        for (number, line) in Lines::new(source).enumerate() {
+            self.preprocess(line, number + 1, file, including, &mut conditions, words)
+                .map_err(|error| error.or_line(number + 1))?;
+        }
+
+        match conditions.last() {
+            Some(condition) => Err(Self::unclosed(condition)),
+            None => Ok(()),
+        }
+    }
+
+    /// Split a line into words, handling `#define`, `#include` and `#ifdef` blocks
+    fn preprocess(
+        &mut self,
+        line: &str,
+        number: usize,
+        file: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+        conditions: &mut Vec<Condition>,
+        words: &mut VecDeque<Word>,
+    ) -> Result<(), LexError> {
+        let directive = Self::strip_comment(line).split_whitespace().next();
+
+        if let Some(directive @ ("#ifdef" | "#else" | "#endif")) = directive {
+            return self.condition(directive, line, number, conditions);
+        }
+
+        // Lines of a block whose condition is false are not lexed at all
+        if !conditions.last().is_none_or(Condition::active) {
+            return Ok(());
+        }
+
+        let line_words = Self::split_words(Self::strip_comment(line))?;
+
+        match line_words.first() {
+            Some(&"#define") => self.define(&line_words[1..])?,
+
+            Some(&"#include") => {
+                let directive = Self::strip_comment(line).trim();
+                let included = directive["#include".len()..].trim();
+
+                // Errors in the included file have a line of their own
+                self.include(included, file, including, words)
+                    .map_err(|error| error.line(number))?;
+            }
+
+            Some(_) => {
+                let source_line: Rc<str> = Rc::from(line);
+
+                words.extend(line_words.into_iter().map(|word| Word {
+                    text: word.to_string(),
+                    line: number,
+                    source_line: source_line.clone(),
+                    range: Self::token_range(line, word),
+                }))
+            }
+
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn unclosed(condition: &Condition) -> LexError {
+        LexError::new(format!(
+            "#ifdef on line {} is never closed with #endif",
+            condition.line
+        ))
+    }
+
+    /// Open, flip or close a conditional block of `#ifdef NAME`, `#else` and `#endif`
+    fn condition(
+        &self,
+        directive: &str,
+        line: &str,
+        number: usize,
+        conditions: &mut Vec<Condition>,
+    ) -> Result<(), LexError> {
+        let arguments: Vec<&str> = Self::strip_comment(line)
+            .split_whitespace()
+            .skip(1)
+            .collect();
+
+        match (directive, arguments.as_slice()) {
+            ("#ifdef", [name]) => conditions.push(Condition {
+                enclosing: conditions.last().is_none_or(Condition::active),
+                defined: self.constants.contains_key(*name) && !self.forward.contains(*name),
+                otherwise: false,
+                line: number,
+            }),
+
+            ("#ifdef", _) => return Err(LexError::new("#ifdef expects a name".to_string())),
+
+            (_, [_, ..]) => return Err(LexError::new(format!("{} takes no arguments", directive))),
+
+            ("#else", []) => match conditions.last_mut() {
+                Some(condition) if !condition.otherwise => condition.otherwise = true,
+                Some(_) => return Err(LexError::new("#ifdef has a second #else".to_string())),
+                None => return Err(LexError::new("#else without an #ifdef".to_string())),
+            },
+
+            _ => {
+                if conditions.pop().is_none() {
+                    return Err(LexError::new("#endif without an #ifdef".to_string()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Splice the words of an `#include "path"` into `words`
+    fn include(
+        &mut self,
+        included: &str,
+        file: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+        words: &mut VecDeque<Word>,
+    ) -> Result<(), LexError> {
+        let (path, source) = self.open_included(included, file, including)?;
+
+        including.push(path.clone());
+        self.collect_words(&source, Some(&path), including, words)?;
+        including.pop();
+
+        Ok(())
+    }
+
+    /// Path and content of the file of an `#include "path"` that is not already being read
+    fn open_included(
+        &self,
+        included: &str,
+        file: Option<&Path>,
+        including: &[PathBuf],
+    ) -> Result<(PathBuf, String), LexError> {
+        let name = included
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| LexError::new("#include expects a quoted path".to_string()))?;
+
+        let (path, source) = self.read_included(name, file)?;
+
+        if including.contains(&path) {
+            return Err(LexError::new(format!(
+                "{} includes itself through a cycle",
+                name
+            )));
+        }
+
+        Ok((path, source))
+    }
+
+    /// Add the file to the files read, once
+    fn read_file(&mut self, path: &Path) {
+        if !self.files.iter().any(|file| file == path) {
+            self.files.push(path.into());
+        }
+    }
+
+    /// Define every constant of the source and the files it includes before it is lexed,
+    /// so a constant can be used above its `#define` like a label
+    fn define_ahead(
+        &mut self,
+        source: &str,
+        file: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<(), LexError> {
+        self.forward.clear();
+        let known = self.constants.clone();
+
+        self.collect_defines(source, file, including)?;
+
+        self.forward = self
+            .constants
+            .keys()
+            .filter(|name| !known.contains_key(*name))
+            .cloned()
+            .collect();
+
+        Ok(())
+    }
+
+    /// Run the `#define`, `#include` and `#ifdef` lines of the source, skipping every other
+    /// line so the errors in them are left to the lexing after it
+    fn collect_defines(
+        &mut self,
+        source: &str,
+        file: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<(), LexError> {
+        let mut conditions: Vec<Condition> = vec![];
+
+        for (number, line) in Lines::new(source).enumerate() {
+            let directive = Self::strip_comment(line).trim();
+
+            match directive.split_whitespace().next() {
+                Some(name @ ("#ifdef" | "#else" | "#endif")) => {
+                    self.condition(name, line, number + 1, &mut conditions)
+                }
+
+                _ if !conditions.last().is_none_or(Condition::active) => Ok(()),
+
+                Some("#define") => {
+                    Self::split_words(directive).and_then(|words| self.define(&words[1..]))
+                }
+
+                Some("#include") => self
+                    .open_included(directive["#include".len()..].trim(), file, including)
+                    .and_then(|(path, source)| {
+                        self.read_file(&path);
+                        including.push(path.clone());
+                        self.collect_defines(&source, Some(&path), including)?;
+                        including.pop();
+
+                        Ok(())
+                    })
+                    .map_err(|error| error.line(number + 1)),
+
+                _ => Ok(()),
+            }
+            .map_err(|error| error.or_line(number + 1))?;
+        }
+
+        match conditions.last() {
+            Some(condition) => Err(Self::unclosed(condition)),
+            None => Ok(()),
+        }
+    }
+
+    /// Read the file of an `#include`, relative to the file that includes it or else to the
+    /// first of the include paths that has it
+    #[cfg(feature = "std")]
+    fn read_included(
+        &self,
+        name: &str,
+        file: Option<&Path>,
+    ) -> Result<(PathBuf, String), LexError> {
+        let next_to = match file.and_then(Path::parent) {
+            Some(directory) => directory.join(name),
+            None => PathBuf::from(name),
+        };
+
+        let tried: Vec<PathBuf> = iter::once(next_to)
+            .chain(
+                self.include_paths
+                    .iter()
+                    .map(|directory| directory.join(name)),
+            )
+            .collect();
+
+        match tried.iter().find(|path| path.is_file()) {
+            Some(path) => Self::read(path),
+
+            None => Err(LexError::new(format!(
+                "can not find {}, tried {}",
+                name,
+                tried
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn read_included(&self, name: &str, _: Option<&Path>) -> Result<(PathBuf, String), LexError> {
+        Err(LexError::new(format!(
+            "can not include {}, reading files needs the std feature",
+            name
+        )))
+    }
+
+    /// Read the file, returning its canonical path and its content
+    #[cfg(feature = "std")]
+    fn read(path: &Path) -> Result<(PathBuf, String), LexError> {
+        let error = |error: std::io::Error| {
+            LexError::new(format!("can not read {}: {}", path.display(), error))
+        };
+
+        let canonical = fs::canonicalize(path).map_err(error)?;
+        let source = fs::read_to_string(&canonical).map_err(error)?;
+
+        Ok((canonical, source))
+    }
+
+    /// Lex a directive with the words it takes, or any other word on its own
+    fn lex_directive(
+        &mut self,
+        Word {
+            text: word,
+            line,
+            source_line,
+            range,
+        }: Word,
+        words: &mut VecDeque<Word>,
+    ) -> Result<(), LexError> {
+        if word == "macro" {
+            return self.macro_definition(&mut iter::from_fn(|| words.pop_front()));
+        }
+
+        // `end - start` after a PUSH joins the operand into an expression the resolver evaluates
+        if (word == "+" || word == "-") && self.pending_operand().is_some() {
+            let term = match words.pop_front() {
+                Some(term) => self.term(&term.text)?,
+                None => return Err(LexError::new(format!("{} expects a term after it", word))),
+            };
+
+            if let Some(value) = self.pending_operand() {
+                value.push_str(&word);
+                value.push_str(&term);
+            }
+
+            return Ok(());
+        }
+
+        if word.starts_with("sizeof(") {
+            let text = Self::call(word, line, words)?;
+
+            return self.size_of(&text, line);
+        }
+
+        if word.starts_with("ret(") || word.starts_with("rev(") {
+            let text = Self::call(word, line, words)?;
+
+            return self.memory_exit(&text, line);
+        }
+
+        if word == ".repeat" {
+            let count = match words.pop_front() {
+                Some(brace) if brace.text == "{" => {
+                    words.push_front(brace);
+                    Err(LexError::new(format!("{} expects a count", word)))
+                }
+                Some(count) => Self::number(&word, "count", &count.text),
+                None => return Err(LexError::new(format!("{} expects a count", word))),
+            };
+
+            // The block is skipped even after a bad count, so its words are not lexed
+            let body = Self::block(&word, &mut iter::from_fn(|| words.pop_front()));
+            let (count, body) = (count?, body?);
+            self.reserve(body.len(), count)?;
+
+            // A nested `.repeat` is expanded again each time
+            return self.lex_block(iter::repeat_n(body, count).flatten().collect());
+        }
+
+        if word == ".init" || word == ".runtime" {
+            let body = Self::block(&word, &mut iter::from_fn(|| words.pop_front()))?;
+
+            self.result.push((Op::LABEL(word), "", line));
+            self.lex_block(body.into())?;
+            self.result
+                .push((Op::LABEL(SECTION_END.to_string()), "", line));
+
+            return Ok(());
+        }
+
+        // `.raw` is the same as `.data`, it is meant to be used between instructions
+        if word == ".data" || word == ".raw" {
+            let bytes = match words.pop_front() {
+                Some(hex) => Self::data(&word, &hex.text)?,
+                None => return Err(LexError::new(format!("{} expects a hex string", word))),
+            };
+            self.result.push((Op::DATA(bytes), "", line));
+
+            return Ok(());
+        }
+
+        if word == "#selector" {
+            let selector = match words.pop_front() {
+                Some(signature) if is_string(&signature.text) => Self::selector(&signature.text)?,
+                _ => {
+                    return Err(LexError::new(
+                        "#selector expects a quoted function signature".to_string(),
+                    ))
+                }
+            };
+            let (push, byte) = Op::push_of_size(4).expect("PUSH4 is an opcode");
+
+            self.result.push((push, byte, line));
+            self.result.push((selector, "", line));
+
+            return Ok(());
+        }
+
+        if word == ".org" {
+            let offset = match words.pop_front() {
+                Some(offset) => Self::offset(&word, &offset.text)?,
+                None => return Err(LexError::new(format!("{} expects an offset", word))),
+            };
+            self.result.push((Op::ORG(offset), "", line));
+
+            return Ok(());
+        }
+
+        // The pad byte is optional, a hex literal after the boundary can only be the pad
+        if word == ".align" {
+            let align = match words.pop_front() {
+                Some(boundary) => {
+                    let pad = match words.front() {
+                        Some(pad) if pad.text.starts_with("0x") => words.pop_front(),
+                        _ => None,
+                    };
+                    Self::align(
+                        &word,
+                        &boundary.text,
+                        pad.as_ref().map(|pad| pad.text.as_str()),
+                    )?
+                }
+                None => return Err(LexError::new(format!("{} expects a boundary", word))),
+            };
+            self.result.push((align, "", line));
+
+            return Ok(());
+        }
+
+        self.lex_word(&word, line, &mut vec![])
+            .map_err(|error| error.context(&source_line, range))
+    }
+
+    /// Fail unless `count` copies of a body of `words` words fit in the tokens the program may
+    /// have. A `.repeat` or macro is checked before it is expanded, as a nested one could
+    /// otherwise exhaust memory long before its tokens are counted
+    fn reserve(&self, words: usize, count: usize) -> Result<(), LexError> {
+        let total = words
+            .checked_mul(count)
+            .and_then(|expanded| expanded.checked_add(self.flushed + self.result.len()));
+
+        if let Some(max) = self.max_ops {
+            if total.is_none_or(|total| total > max) {
+                return Err(LexError::new(format!(
+                    "the program has more than the limit of {} tokens",
+                    max
+                )));
+            }
+        }
+
+        match total.is_some_and(|total| total <= MAX_EXPANSION) {
+            true => Ok(()),
+            false => Err(LexError::new(format!(
+                "the program expands to more than {} tokens",
+                MAX_EXPANSION
+            ))),
+        }
+    }
+
+    /// Lex the words of a block, every word keeps its own line and column
+    fn lex_block(&mut self, mut body: VecDeque<Word>) -> Result<(), LexError> {
+        while let Some(word) = body.pop_front() {
+            let (column, len) = word.position();
+            let line = word.line;
+
+            self.lex_directive(word, &mut body)
+                .map_err(|error| error.or_line(line))?;
+            self.spans_of_word(column, len);
+        }
+
+        Ok(())
+    }
+
+    /// Lex a single word, expanding constants and macros
+    fn lex_word(
+        &mut self,
+        word: &str,
+        line: usize,
+        expanding: &mut Vec<String>,
+    ) -> Result<(), LexError> {
+        let word = match self.constants.get(word) {
+            Some(value) => value.clone(),
+            None => word.to_string(),
+        };
+        let word = word.as_str();
+
+        if let Some(body) = self.macros.get(word).cloned() {
+            if expanding.iter().any(|name| name == word) {
+                return Err(LexError::new(format!("macro {} expands to itself", word)));
+            }
+
+            self.reserve(body.len(), 1)?;
+
+            expanding.push(word.to_string());
+            for word in body {
+                self.lex_word(&word, line, expanding)?;
+            }
+            expanding.pop();
+
+            return Ok(());
+        }
+
+        if is_string(word) {
+            return self.string_operand(word, line);
+        }
+
+        // A character is the number of its byte, so a PUSH pads it like a decimal
+        let byte;
+        let word = match is_char(word) {
+            true => {
+                byte = char_to_byte(word)?.to_string();
+                byte.as_str()
+            }
+            false => word,
+        };
+
+        let ungrouped = strip_separators(word)?;
+        let word = ungrouped.as_deref().unwrap_or(word);
+
+        // `[hex]` is a short form of `.raw hex`
+        if let Some(hex) = word
+            .strip_prefix('[')
+            .and_then(|word| word.strip_suffix(']'))
+        {
+            let bytes = Self::data(".raw", hex)?;
+            self.result.push((Op::DATA(bytes), "", line));
+
+            return Ok(());
+        }
+
+        if let Some(magnitude) = word.strip_prefix('-').filter(|word| is_decimal(word)) {
+            if let Some(size) = self.pending_push().map(Op::is_push) {
+                return self.negative_operand(word, magnitude, size, line);
+            }
+        }
+
+        let (mut op, byte) = match self.opcodes.get(&word.to_ascii_uppercase()) {
+            Some(&opcode) => (
+                Op::CUSTOM(word.to_ascii_uppercase(), opcode),
+                hex_byte(opcode),
+            ),
+            None => Op::from_str(word)?,
+        };
+
+        if let Op::VALUE(value) = &mut op {
+            match self.pending_push().map(Op::is_push) {
+                Some(size) if is_decimal(value) || radix_literal(value).is_some() => {
+                    *value = Self::number_operand(value, size)?;
+
+                    if self.little_endian {
+                        *value = self.little_endian_operand(value, size);
+                    } else if size.is_none() {
+                        // Unprefixed, so the resolver tells the number from a hex literal
+                        // whose digits give the width of the PUSH
+                        value.drain(..2);
+                    }
+                }
+
+                Some(_) => {}
+
+                // Outside of a PUSH a word that is not a literal is most likely a misspelled opcode
+                None if !is_hex(value) => {
+                    return Err(LexError::new(format!(
+                        "{} is neither a known opcode nor a valid literal",
+                        value
+                    ))
+                    .kind(Kind::UnknownToken(value.clone())))
+                }
+
+                // Most likely meant as an operand of the opcode before it, which takes none
+                None => {
+                    let nullary = self
+                        .result
+                        .last()
+                        .map(|(op, _, _)| op)
+                        .filter(|op| op.opcode().is_some() && !matches!(op, Op::LABEL(_)))
+                        .map(|op| format!(", {} takes no operand", op))
+                        .unwrap_or_default();
+
+                    return Err(LexError::new(format!(
+                        "{} is not the operand of a PUSH{}, use .data for raw bytes",
+                        value, nullary
+                    )));
+                }
+            }
+        }
+
+        self.result.push((op, byte, line));
+
+        Ok(())
+    }
+
+    /// Join a call like `sizeof(start, end)`, which is split at its spaces, up to its `)`.
+    /// The rest of the call is on the same line
+    fn call(mut text: String, line: usize, words: &mut VecDeque<Word>) -> Result<String, LexError> {
+        while !text.ends_with(')') {
+            match words.pop_front() {
+                Some(next) if next.line == line => {
+                    text.push(' ');
+                    text.push_str(&next.text);
+                }
+                _ => return Err(LexError::new(format!("{} is missing its )", text))),
+            }
+        }
+
+        Ok(text)
+    }
+
+    /// Lex `ret(offset, length)` as `PUSH length PUSH offset RETURN` and `rev(offset, length)`
+    /// the same with REVERT. The length is pushed first as both take the offset from the
+    /// top of the stack
+    fn memory_exit(&mut self, text: &str, line: usize) -> Result<(), LexError> {
+        let (name, args) = text.split_once('(').expect("a call has its (");
+        let exit = if name == "ret" { "return" } else { "revert" };
+
+        let args = args
+            .strip_suffix(')')
+            .and_then(|args| args.split_once(','))
+            .map(|(offset, length)| (offset.trim(), length.trim()))
+            .filter(|(offset, length)| {
+                [offset, length]
+                    .iter()
+                    .all(|arg| !arg.is_empty() && !arg.contains([',', ' ']))
+            });
+
+        let Some((offset, length)) = args else {
+            return Err(LexError::new(format!(
+                "{} expects an offset and a length like {}(offset, length)",
+                text, name
+            )));
+        };
+
+        for word in ["push", length, "push", offset, exit] {
+            self.lex_word(word, line, &mut vec![])?;
+        }
+
+        Ok(())
+    }
+
+    /// Lex `sizeof(start, end)`, the bytes between two labels, as the operand of a PUSH
+    fn size_of(&mut self, text: &str, line: usize) -> Result<(), LexError> {
+        let labels = text
+            .strip_prefix("sizeof(")
+            .and_then(|labels| labels.strip_suffix(')'))
+            .and_then(|labels| labels.split_once(','))
+            .map(|(start, end)| (start.trim(), end.trim()))
+            .filter(|(start, end)| is_identifier(start) && is_identifier(end));
+
+        let Some((start, end)) = labels else {
+            return Err(LexError::new(format!(
+                "{} expects two labels like sizeof(start, end)",
+                text
+            )));
+        };
+
+        if self.pending_push().is_none() {
+            return Err(LexError::new(format!(
+                "{} is not the operand of a PUSH",
+                text
+            )));
+        }
+
+        self.result
+            .push((Op::VALUE(format!("sizeof({},{})", start, end)), "", line));
+
+        Ok(())
+    }
+
+    /// The ABI selector of a function signature, the first 4 bytes of its keccak-256
+    fn selector(signature: &str) -> Result<Op, LexError> {
+        let hash = keccak256(&string_to_bytes(signature)?);
+
+        Ok(Op::VALUE(format!("0x{}", to_hex(&hash[..4]))))
+    }
+
+    /// Parse the hex or decimal byte offset of a directive
+    fn offset(directive: &str, offset: &str) -> Result<usize, LexError> {
+        Self::number(directive, "offset", offset)
+    }
+
+    /// Parse the hex or decimal number of a directive, the offset or count it is named by
+    /// `what` in the errors
+    fn number(directive: &str, what: &str, number: &str) -> Result<usize, LexError> {
+        let parsed = match number.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None if is_decimal(number) => number.parse().ok(),
+            None => None,
+        };
+
+        let parsed = parsed.ok_or_else(|| {
+            LexError::new(format!(
+                "{} expects a hex or decimal {} but got {}",
+                directive, what, number
+            ))
+        })?;
+
+        if parsed > MAX_OFFSET {
+            return Err(LexError::new(format!(
+                "{} {} is more than the largest {} 0x{:x}",
+                directive, number, what, MAX_OFFSET
+            )));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parse the boundary and the pad byte, zero by default, of an `.align` directive
+    fn align(directive: &str, boundary: &str, pad: Option<&str>) -> Result<Op, LexError> {
+        let boundary = match Self::offset(directive, boundary)? {
+            0 => return Err(LexError::new(format!("{} 0 aligns to nothing", directive))),
+            boundary => boundary,
+        };
+
+        match pad
+            .map(|pad| Self::data(directive, pad))
+            .transpose()?
+            .as_deref()
+        {
+            None => Ok(Op::ALIGN(boundary, 0)),
+            Some(&[pad]) => Ok(Op::ALIGN(boundary, pad)),
+            Some(_) => Err(LexError::new(format!(
+                "the pad of {} must be a single byte",
+                directive
+            ))),
+        }
+    }
+
+    /// Decode the even length hex string of a `.data` or `.raw` directive
+    fn data(directive: &str, hex: &str) -> Result<Vec<u8>, LexError> {
+        if !is_hex(hex) {
+            return Err(LexError::new(format!(
+                "{} {} is not a hex string",
+                directive, hex
+            )));
+        }
+
+        decode_hex(hex).ok_or_else(|| {
+            LexError::new(format!(
+                "{} {} has an odd number of hex digits",
+                directive, hex
+            ))
+            .kind(Kind::OddLengthHex(hex.to_string()))
+        })
+    }
+
+    /// The words of a `{ ... }` block up to its matching `}`, blocks can be nested
+    fn block(
+        directive: &str,
+        words: &mut impl Iterator<Item = Word>,
+    ) -> Result<Vec<Word>, LexError> {
+        if words.next().is_none_or(|word| word.text != "{") {
+            return Err(LexError::new(format!("{} expects a {{", directive)));
+        }
+
+        let mut body = vec![];
+        let mut depth = 0;
+        loop {
+            match words.next() {
+                Some(word) if word.text == "}" && depth == 0 => return Ok(body),
+
+                Some(word) => {
+                    match word.text.as_str() {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        _ => {}
+                    }
+
+                    body.push(word);
+                }
+
+                None => return Err(LexError::new(format!("{} is never closed", directive))),
+            }
+        }
+    }
+
+    /// Record a `macro NAME { ... }` block, closed by `}` or `endmacro`
+    fn macro_definition(&mut self, words: &mut impl Iterator<Item = Word>) -> Result<(), LexError> {
+        let name = match words.next() {
+            Some(name) if Self::is_free_name(&name.text) => name.text,
+
+            _ => return Err(LexError::new("macro expects a name".to_string())),
+        };
+
+        if words.next().is_none_or(|word| word.text != "{") {
+            return Err(LexError::new(format!("macro {} expects a {{", name)));
+        }
+
+        let mut body = vec![];
+        loop {
+            match words.next().map(|word| word.text) {
+                Some(word) if word == "}" || word == "endmacro" => break,
+
+                Some(word) if word == "macro" => {
+                    return Err(LexError::new(format!(
+                        "macro {} can not define another macro",
+                        name
+                    )))
+                }
+
+                Some(word) => body.push(word),
+
+                None => return Err(LexError::new(format!("macro {} is never closed", name))),
+            }
+        }
+
+        if self.macros.insert(name.clone(), body).is_some() {
+            return Err(LexError::new(format!("macro {} is already defined", name)));
+        }
+
+        Ok(())
+    }
+
+    /// Record a `#define NAME value` constant
+    fn define(&mut self, words: &[&str]) -> Result<(), LexError> {
+        let [name, value] = words else {
+            return Err(LexError::new(
+                "#define expects a name and a value".to_string(),
+            ));
+        };
+
+        // Defined ahead of lexing, the constant can be used from here on
+        if self.forward.remove(*name) {
+            return Ok(());
+        }
+
+        if self.overrides.contains(*name) {
+            return Ok(());
+        }
+
+        if !Self::is_free_name(name) {
+            return Err(LexError::new(format!(
+                "{} can not be used as a constant name",
+                name
+            )));
+        }
+
+        if self
+            .constants
+            .insert(name.to_string(), value.to_string())
+            .is_some()
+        {
+            return Err(LexError::new(format!(
+                "constant {} is already defined",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Can the name be given to a constant or macro without shadowing an opcode
+    fn is_free_name(name: &str) -> bool {
+        is_identifier(name) && matches!(Op::from_str(name), Ok((Op::VALUE(_), _)))
+    }
+
+    /// Returns the last lexed token if it is a PUSH that expects an operand
+    fn pending_push(&self) -> Option<&Op> {
+        self.result
+            .last()
+            .map(|(op, _, _)| op)
+            .filter(|op| matches!(op, Op::PUSH) || op.is_push().is_some_and(|size| size > 0))
+    }
+
+    /// Returns the operand of the last lexed PUSH if it is the last token
+    fn pending_operand(&mut self) -> Option<&mut String> {
+        let [.., (push, _, _), (Op::VALUE(value), _, _)] = self.result.as_mut_slice() else {
+            return None;
+        };
+
+        (matches!(push, Op::PUSH) || push.is_push().is_some_and(|size| size > 0)).then_some(value)
+    }
+
+    /// A label or a literal of an operand expression, literals become hex
+    fn term(&self, word: &str) -> Result<String, LexError> {
+        let word = self.constants.get(word).map_or(word, String::as_str);
+        let ungrouped = strip_separators(word)?;
+        let word = ungrouped.as_deref().unwrap_or(word);
+
+        if is_decimal(word) || radix_literal(word).is_some() {
+            return Self::number_operand(word, None);
+        }
+
+        match Op::from_str(word) {
+            Ok((Op::VALUE(value), _)) => Ok(value),
+            _ => Err(LexError::new(format!(
+                "{} can not be a term of an operand expression",
+                word
+            ))),
+        }
+    }
+
+    /// Lex a string literal operand, a bare PUSH gets the smallest PUSHn that fits it
+    fn string_operand(&mut self, word: &str, line: usize) -> Result<(), LexError> {
+        let mut bytes = string_to_bytes(word)?;
+
+        if bytes.is_empty() {
+            return Err(LexError::new(
+                "an empty string can not be pushed".to_string(),
+            ));
+        }
+
+        let size = match self.pending_push().map(Op::is_push) {
+            Some(Some(size)) => usize::from(size),
+
+            Some(None) => {
+                let (op, byte) = Op::push_of_size(bytes.len()).ok_or_else(|| {
+                    LexError::new(format!("string {} is longer than 32 bytes", word)).kind(
+                        Kind::BadOperandWidth {
+                            operand: word.to_string(),
+                            expected: 32,
+                        },
+                    )
+                })?;
+
+                if let Some(push) = self.result.last_mut() {
+                    push.0 = op;
+                    push.1 = byte;
+                }
+
+                bytes.len()
+            }
+
+            None => {
+                return Err(LexError::new(format!(
+                    "string {} can only be used as the operand of a PUSH",
+                    word
+                )))
+            }
+        };
+
+        if bytes.len() > size {
+            return Err(
+                LexError::new(format!("string {} does not fit in {} bytes", word, size)).kind(
+                    Kind::BadOperandWidth {
+                        operand: word.to_string(),
+                        expected: size,
+                    },
+                ),
+            );
+        }
+
+        bytes.splice(0..0, vec![0; size - bytes.len()]);
+        self.result
+            .push((Op::VALUE(format!("0x{}", to_hex(&bytes))), "", line));
+
+        Ok(())
+    }
+
+    /// Encode a decimal, binary or octal PUSH operand as hex, padded to `size` bytes if the
+    /// PUSH has one
+    fn number_operand(value: &str, size: Option<u8>) -> Result<String, LexError> {
+        let mut bytes = match radix_literal(value) {
+            Some((digits, radix)) => digits_to_bytes(digits, radix).ok_or_else(|| {
+                LexError::new(format!(
+                    "{} is not a valid {} literal",
+                    value,
+                    if radix == 2 { "binary" } else { "octal" }
+                ))
+            })?,
+
+            None => decimal_to_bytes(value),
+        };
+
+        if let Some(size) = size.map(usize::from) {
+            if bytes.len() > size {
+                return Err(
+                    LexError::new(format!("{} does not fit in {} bytes", value, size)).kind(
+                        Kind::BadOperandWidth {
+                            operand: value.to_string(),
+                            expected: size,
+                        },
+                    ),
+                );
+            }
+
+            bytes.splice(0..0, vec![0; size - bytes.len()]);
+        }
+
+        Ok(format!("0x{}", to_hex(&bytes)))
+    }
+
+    /// Lex a negative decimal PUSH operand as two's complement of the PUSH width,
+    /// a bare PUSH gets the full 32 bytes of a stack word
+    fn negative_operand(
+        &mut self,
+        word: &str,
+        magnitude: &str,
+        size: Option<u8>,
+        line: usize,
+    ) -> Result<(), LexError> {
+        let width = size.unwrap_or(32) as usize;
+        let bytes = negative_to_bytes(magnitude, width).ok_or_else(|| {
+            LexError::new(format!("{} does not fit in {} bytes", word, width)).kind(
+                Kind::BadOperandWidth {
+                    operand: word.to_string(),
+                    expected: width,
+                },
+            )
+        })?;
+
+        let mut value = format!("0x{}", to_hex(&bytes));
+        if self.little_endian {
+            value = self.little_endian_operand(&value, size);
+        }
+
+        self.result.push((Op::VALUE(value), "", line));
+
+        Ok(())
+    }
+
+    /// Reverse the bytes of a number operand. A bare PUSH gets the width of the operand
+    /// first, the resolver would otherwise drop the zeros that end up leading
+    fn little_endian_operand(&mut self, value: &str, size: Option<u8>) -> String {
+        let mut bytes = decode_hex(value).expect("number operands are encoded as hex");
+
+        if size.is_none() {
+            if let (Some(push), Some((op, byte))) =
+                (self.result.last_mut(), Op::push_of_size(bytes.len()))
+            {
+                push.0 = op;
+                push.1 = byte;
+            }
+        }
+
+        bytes.reverse();
+
+        format!("0x{}", to_hex(&bytes))
+    }
+
+    /// Byte range of a word within the line it was split from
+    fn token_range(source_line: &str, word: &str) -> Range<usize> {
+        let start = word.as_ptr() as usize - source_line.as_ptr() as usize;
+
+        start..start + word.len()
+    }
+
+    /// Split a line on whitespace, keeping double quoted strings in one word
+    fn split_words(line: &str) -> Result<Vec<&str>, LexError> {
+        let mut words = vec![];
+        let mut start = None;
+        let mut quote = None;
+        let mut escaped = false;
+
+        for (i, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quote.is_some() => escaped = true,
+                _ if quote == Some(c) => quote = None,
+
+                // A character literal can only start a word, unlike a string
+                '"' if quote.is_none() => quote = Some(c),
+                '\'' if quote.is_none() && start.is_none() => quote = Some(c),
+
+                _ if c.is_whitespace() && quote.is_none() => {
+                    if let Some(start) = start.take() {
+                        words.push(&line[start..i]);
+                    }
+
+                    continue;
+                }
+
+                _ => {}
+            }
+
+            start.get_or_insert(i);
+        }
+
+        match quote {
+            Some('"') => return Err(LexError::new("string is never closed".to_string())),
+            Some(_) => return Err(LexError::new("character is never closed".to_string())),
+            None => {}
+        }
+
+        words.extend(start.map(|start| &line[start..]));
+
+        Ok(words)
+    }
+
+    /// Remove a `;` or `//` comment and everything after it from the line,
+    /// comment markers inside strings are kept
+    fn strip_comment(line: &str) -> &str {
+        let mut quote = None;
+        let mut escaped = false;
+        let mut previous = ' ';
+
+        for (i, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quote.is_some() => escaped = true,
+                _ if quote == Some(c) => quote = None,
+                _ if quote.is_some() => {}
+                '"' => quote = Some(c),
+                '\'' if previous.is_whitespace() => quote = Some(c),
+                ';' => return &line[..i],
+                '/' if line[i..].starts_with("//") => return &line[..i],
+                _ => {}
+            }
+
+            previous = c;
+        }
+
+        line
+    }
+}
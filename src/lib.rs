@@ -0,0 +1,201 @@
+//! EVM assembler
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::path::Path;
+
+mod analysis;
+mod assembler;
+mod deploy;
+mod diagnostic;
+mod disassembler;
+mod error;
+mod fork;
+mod instruction;
+mod json;
+mod keccak;
+mod lexer;
+mod listing;
+mod literal;
+mod metadata;
+mod object;
+mod op;
+mod optimizer;
+mod parser;
+mod resolver;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use analysis::{
+    check_jumps, check_stack, control_flow, coverage, ends_with_terminator, estimate_gas,
+    find_unreachable, stack_heights, stats, BadJump, ControlFlow, Coverage, GasEstimate, Leftover,
+    StackReport, StackUnderflow, Stats, Unreachable, MAX_CODE_SIZE, MAX_INITCODE_SIZE,
+    MAX_STACK_DEPTH,
+};
+pub use assembler::{Assembler, AssemblerBuilder};
+pub use deploy::{deploy, deploy_after};
+pub use diagnostic::{check, lint, size_warnings, stack_diagnostics, warnings, Diagnostic, Level};
+pub use disassembler::{
+    annotate, diff, disassemble, disassemble_at, disassemble_source, truncated_push, TruncatedPush,
+};
+pub use error::{AssembleError, LexError, ParseError};
+pub use fork::{check_fork, Fork};
+pub use instruction::{bytecode, to_hex, Instruction};
+pub use json::{diagnostics_json, manifest, source_map, tokens_json};
+pub use lexer::{Lexer, Span, Tokens};
+pub use listing::{explain, gas_listing, ir, listing, trace};
+pub use metadata::metadata;
+pub use object::{link, Object, Relocation};
+pub use op::Op;
+pub use optimizer::optimize;
+pub use parser::Parser;
+pub use resolver::Resolver;
+#[cfg(feature = "wasm")]
+pub use wasm::assemble_js;
+
+/// Assemble the source into hex encoded bytecode. A source of `.init { ... }` and
+/// `.runtime { ... }` sections assembles into creation bytecode that returns its runtime
+pub fn assemble(source: &str) -> Result<String, AssembleError> {
+    Ok(to_hex(&assemble_bytes(source)?))
+}
+
+/// Assemble the source into the bytes of its bytecode, sections as `assemble` does
+///
+/// ```
+/// let bytes = easm::assemble_bytes("push1 0x80 push1 0x40 mstore").unwrap();
+/// assert_eq!(bytes.len(), 5);
+/// ```
+pub fn assemble_bytes(source: &str) -> Result<Vec<u8>, AssembleError> {
+    assemble_sections(lex(source)?, |lexer| assemble_lexer(lexer, Fork::default()))
+}
+
+/// Assemble the file into hex encoded bytecode, sections as `assemble` does
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn assemble_file(path: impl AsRef<Path>) -> Result<String, AssembleError> {
+    let code = assemble_sections(lex_files([path])?, |lexer| {
+        assemble_lexer(lexer, Fork::default())
+    })?;
+
+    Ok(to_hex(&code))
+}
+
+/// Assemble the source read from a file, socket or pipe into hex encoded bytecode
+///
+/// ```
+/// let source = std::io::Cursor::new("push1 0x01 push1 0x02 add");
+/// assert_eq!(easm::assemble_reader(source).unwrap(), "6001600201");
+/// ```
+#[cfg(feature = "std")]
+pub fn assemble_reader<R: std::io::Read>(reader: R) -> Result<String, AssembleError> {
+    let mut lexer = Lexer::new();
+    lexer.lex_reader(reader)?;
+
+    let code = assemble_sections(lexer, |lexer| assemble_lexer(lexer, Fork::default()))?;
+
+    Ok(to_hex(&code))
+}
+
+/// Assemble tokens returned by `tokenize` into hex encoded bytecode
+pub fn assemble_tokens(tokens: &[(Op, &str, usize)]) -> Result<String, AssembleError> {
+    let instructions = Parser::new(tokens).parse()?;
+
+    Ok(to_hex(&bytecode(&instructions)))
+}
+
+/// Resolve the labels of a lexed program for the fork and assemble it into the bytes
+/// of its bytecode
+pub fn assemble_lexer(mut lexer: Lexer, fork: Fork) -> Result<Vec<u8>, AssembleError> {
+    Resolver::new().fork(fork).resolve(&mut lexer.result)?;
+    let instructions = Parser::new(&lexer.result).spans(&lexer.spans).parse()?;
+
+    Ok(bytecode(&instructions))
+}
+
+/// Assemble a lexed program with `assemble`, or its `.init` and `.runtime` sections each on
+/// their own into creation bytecode that returns the runtime
+pub fn assemble_sections<E>(
+    mut lexer: Lexer,
+    mut assemble: impl FnMut(Lexer) -> Result<Vec<u8>, E>,
+) -> Result<Vec<u8>, E> {
+    match lexer.split_runtime() {
+        Some(runtime) => Ok(deploy_after(&assemble(lexer)?, &assemble(runtime)?)),
+        None => assemble(lexer),
+    }
+}
+
+/// Assemble instructions built without the lexer into bytecode, labels are
+/// not resolved on this path so jumps must use their offsets
+pub fn assemble_ops(instructions: &[Instruction]) -> Result<Vec<u8>, AssembleError> {
+    for instruction in instructions {
+        instruction.validate()?;
+    }
+
+    Ok(bytecode(instructions))
+}
+
+/// Lex the source and resolve its labels, a program of sections is two pieces of code
+/// so it is an error
+pub fn tokenize(source: &str) -> Result<Vec<(Op, &'static str, usize)>, AssembleError> {
+    resolve(lex(source)?)
+}
+
+/// Lex the file and resolve its labels
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn tokenize_file(
+    path: impl AsRef<Path>,
+) -> Result<Vec<(Op, &'static str, usize)>, AssembleError> {
+    tokenize_files([path])
+}
+
+/// Lex the files in order as one program and resolve its labels,
+/// labels and constants of a file are visible from the files after it
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn tokenize_files<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+) -> Result<Vec<(Op, &'static str, usize)>, AssembleError> {
+    resolve(lex_files(paths)?)
+}
+
+/// Lex the source without resolving its labels
+pub fn lex(source: &str) -> Result<Lexer, AssembleError> {
+    let mut lexer = Lexer::new();
+    lexer.lex(source)?;
+
+    Ok(lexer)
+}
+
+/// Lex the files in order as one program without resolving its labels
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn lex_files<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+) -> Result<Lexer, AssembleError> {
+    let mut lexer = Lexer::new();
+    for path in paths {
+        lexer.lex_file(path.as_ref())?;
+    }
+
+    Ok(lexer)
+}
+
+fn resolve(mut lexer: Lexer) -> Result<Vec<(Op, &'static str, usize)>, AssembleError> {
+    if lexer.runtime.is_some() {
+        return Err(ParseError::new(
+            "a program of .init and .runtime sections can only be assembled, \
+             its sections are two pieces of code"
+                .to_string(),
+        )
+        .into());
+    }
+
+    let mut resolver = Resolver::new();
+    resolver.resolve(&mut lexer.result)?;
+
+    Ok(lexer.result)
+}
@@ -0,0 +1,112 @@
+use crate::{analysis::stack_heights, instruction::to_hex, Instruction, Op};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+};
+
+/// Render the instructions as source with nothing left to expand, one per line like
+/// `PUSH2 0x0005`. Macros, constants and labels are already replaced by what they stand
+/// for, so the text assembles back to the same bytecode. A label is its JUMPDEST with the
+/// name in a comment, as a local name like `1@0` would not lex
+pub fn ir(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| match &instruction.op {
+            Op::LABEL(name) => format!("JUMPDEST ; {}\n", name),
+            _ => text(instruction) + "\n",
+        })
+        .collect()
+}
+
+/// Render one line per instruction with its byte offset, mnemonic, operand and bytes,
+/// like `0x0000  PUSH1 0x01  6001`
+pub fn listing(instructions: &[Instruction]) -> String {
+    render(instructions, |_, instruction| instruction.to_hex())
+}
+
+/// Render the listing with the stack height before and after every instruction, like
+/// `0x0000  PUSH1 0x01  [0 -> 1]`. The heights are of straight-line code, so a JUMPDEST
+/// is marked as the place they may stop being accurate and an underflow as where they go wrong
+pub fn trace(instructions: &[Instruction]) -> String {
+    let heights = stack_heights(instructions);
+
+    render(instructions, |index, instruction| {
+        let (before, after) = heights[index];
+        let (pops, _) = instruction.op.stack_io();
+
+        let note = match &instruction.op {
+            _ if pops > 0 && before < pops as isize => "  underflow",
+            Op::JUMPDEST | Op::LABEL(_) => "  jump target, jumps may arrive with another height",
+            _ => "",
+        };
+
+        format!("[{} -> {}]{}", before, after, note)
+    })
+}
+
+/// Render the listing with the opcode byte of every instruction and what it does, like
+/// `0x0000  ADD  (0x01) pops a, b; pushes (a + b) mod 2^256`
+pub fn explain(instructions: &[Instruction]) -> String {
+    render(instructions, |_, instruction| match instruction.opcode {
+        Some(byte) => format!("(0x{:02x}) {}", byte, instruction.op.describe()),
+        None => instruction.op.describe().to_string(),
+    })
+}
+
+/// Render the listing with the static gas of every instruction and the running total,
+/// like `0x0000  PUSH1 0x01      3       3`. An opcode that costs more depending on its
+/// arguments or state is marked with `*` and left out of the total
+pub fn gas_listing(instructions: &[Instruction]) -> String {
+    let mut total = 0;
+
+    render(instructions, |_, instruction| match &instruction.op {
+        // Data is not executed
+        Op::DATA(_) => String::new(),
+
+        op if op.has_dynamic_gas() => format!("{:>6}  {:>6}", format!("{}*", op.base_gas()), total),
+
+        op => {
+            total += op.base_gas();
+
+            format!("{:>5}   {:>6}", op.base_gas(), total)
+        }
+    })
+}
+
+/// Render a row per instruction of its offset, text and the annotation of its index
+fn render(
+    instructions: &[Instruction],
+    mut annotate: impl FnMut(usize, &Instruction) -> String,
+) -> String {
+    let mut offset = 0;
+    let mut rows = vec![];
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        rows.push((offset, text(instruction), annotate(index, instruction)));
+        offset += instruction.size();
+    }
+
+    let width = rows
+        .iter()
+        .map(|(_, text, _)| text.len())
+        .max()
+        .unwrap_or(0);
+
+    rows.iter()
+        .map(|(offset, text, annotation)| {
+            format!("0x{:04x}  {:<width$}  {}\n", offset, text, annotation)
+        })
+        .collect()
+}
+
+/// The instruction as it is written in the source, with its operand in hex
+fn text(instruction: &Instruction) -> String {
+    match (&instruction.op, &instruction.operand) {
+        (Op::DATA(bytes), _) => format!(".data 0x{}", to_hex(bytes)),
+
+        (op, Some(operand)) => format!("{} 0x{}", op, to_hex(operand)),
+
+        (op, None) => op.to_string(),
+    }
+}
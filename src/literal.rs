@@ -0,0 +1,144 @@
+use crate::LexError;
+use alloc::{format, string::String, vec, vec::Vec};
+
+/// Is the value an unprefixed decimal integer
+pub(crate) fn is_decimal(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Remove the `_` separators that group the digits of a number like `1_000` or
+/// `0xdead_beef`, `None` if the word has none. A separator at the start or end of the
+/// digits or next to another is an error
+pub(crate) fn strip_separators(word: &str) -> Result<Option<String>, LexError> {
+    let number = word.strip_prefix('-').unwrap_or(word);
+    if !number.starts_with(|c: char| c.is_ascii_digit()) || !number.contains('_') {
+        return Ok(None);
+    }
+
+    let digits = ["0x", "0b", "0o"]
+        .iter()
+        .find_map(|prefix| number.strip_prefix(prefix))
+        .unwrap_or(number);
+
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(LexError::new(format!(
+            "{} has a misplaced _, separators go between two digits",
+            word
+        )));
+    }
+
+    Ok(Some(word.replace('_', "")))
+}
+
+/// Convert a decimal integer into its shortest big-endian bytes (at least one byte)
+pub(crate) fn decimal_to_bytes(value: &str) -> Vec<u8> {
+    digits_to_bytes(value, 10).expect("decimal digits were checked")
+}
+
+/// Split a `0b` binary or `0o` octal literal into its digits and radix
+pub(crate) fn radix_literal(value: &str) -> Option<(&str, u32)> {
+    match value.get(..2)? {
+        "0b" => Some((&value[2..], 2)),
+        "0o" => Some((&value[2..], 8)),
+        _ => None,
+    }
+}
+
+/// Convert digits of the radix into their shortest big-endian bytes (at least one byte),
+/// `None` if there are no digits or one is not valid in the radix
+pub(crate) fn digits_to_bytes(digits: &str, radix: u32) -> Option<Vec<u8>> {
+    if digits.is_empty() {
+        return None;
+    }
+
+    let mut bytes = vec![0u8];
+
+    for c in digits.chars() {
+        let mut carry = c.to_digit(radix)?;
+
+        for byte in bytes.iter_mut().rev() {
+            let product = *byte as u32 * radix + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Encode a negative decimal integer, given without its sign, as `size` bytes of
+/// two's complement, `None` if it is too negative to fit
+pub(crate) fn negative_to_bytes(magnitude: &str, size: usize) -> Option<Vec<u8>> {
+    let mut bytes = decimal_to_bytes(magnitude);
+    if bytes.len() > size {
+        return None;
+    }
+    bytes.splice(0..0, vec![0; size - bytes.len()]);
+
+    // The smallest value of the width is -0x80..00, anything with the sign bit set beyond it overflows
+    if bytes[0] & 0x80 != 0 && (bytes[0] != 0x80 || bytes[1..].iter().any(|&byte| byte != 0)) {
+        return None;
+    }
+
+    let mut carry = true;
+    for byte in bytes.iter_mut().rev() {
+        (*byte, carry) = (!*byte).overflowing_add(carry as u8);
+    }
+
+    Some(bytes)
+}
+
+/// Is the value a double quoted string literal
+pub(crate) fn is_string(value: &str) -> bool {
+    value.len() >= 2 && value.starts_with('"') && value.ends_with('"')
+}
+
+/// Is the value a single quoted character literal like `'A'`
+pub(crate) fn is_char(value: &str) -> bool {
+    value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'')
+}
+
+/// The byte of a character literal, which must be a single ASCII character
+pub(crate) fn char_to_byte(value: &str) -> Result<u8, LexError> {
+    match string_to_bytes(value)?[..] {
+        [byte] => Ok(byte),
+        _ => Err(LexError::new(format!(
+            "character literal {} must be a single ASCII character",
+            value
+        ))),
+    }
+}
+
+/// Decode a double quoted string literal into its UTF-8 bytes, handling escape sequences
+pub(crate) fn string_to_bytes(value: &str) -> Result<Vec<u8>, LexError> {
+    let mut string = String::new();
+
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some('0') => string.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => string.push(c),
+
+            _ => {
+                return Err(LexError::new(format!(
+                    "{} has an unknown escape sequence",
+                    value
+                )))
+            }
+        }
+    }
+
+    Ok(string.into_bytes())
+}
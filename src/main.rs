@@ -1,209 +1,897 @@
 use std::{
-    env::args,
+    collections::BTreeMap,
+    env::{self, args},
     error::Error,
-    fmt::{Debug, Display},
     fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
 };
 
-#[derive(Debug, Clone)]
-pub enum Op {
-    MSTORE,
-    MLOAD,
-    CREATE,
-    EXTCODECOPY,
-    PUSH1,
-    POP,
-    DUP1,
-    SWAP1,
-    VALUE(String),
-    STOP,
-    ADD,
-    MUL,
-    SUB,
-    DIV,
-    SDIV,
-    MOD,
-    SMOD,
-    ADDMOD,
-    MULMOD,
-    EXP,
-    SIGNEXTEND,
-    GT,
-    SLT,
-    SGT,
-    EQ,
-    ISZERO,
-    AND,
-    OR,
-    XOR,
-    NOT,
-    BYTE,
-    SHL,
-    SHR,
-    SAR,
-    SHA3,
-    ADDRESS,
-    BALANCE,
-    CALLVALUE,
-    RETURN,
-}
-
-impl Op {
-    pub fn from_str(str: &'static str) -> Result<(Self, &str), Box<dyn Error>> {
-        match str {
-            "MSTORE" | "mstore" => Ok((Self::MSTORE, "52")),
-            "MLOAD" | "mload" => Ok((Self::MLOAD, "51")),
-            "CREATE" | "create" => Ok((Self::CREATE, "f0")),
-            "EXTCODECOPY" | "extcodecopy" => Ok((Self::EXTCODECOPY, "3c")),
-            "PUSH1" | "push1" => Ok((Self::PUSH1, "60")),
-            "POP" | "pop" => Ok((Self::POP, "50")),
-            "DUP1" | "dup1" => Ok((Self::DUP1, "80")),
-            "SWAP1" | "swap1" => Ok((Self::SWAP1, "90")),
-            "STOP" | "stop" => Ok((Self::STOP, "00")),
-            "ADD" | "add" => Ok((Self::ADD, "01")),
-            "MUL" | "mul" => Ok((Self::MUL, "02")),
-            "SUB" | "sub" => Ok((Self::SUB, "03")),
-            "DIV" | "div" => Ok((Self::DIV, "04")),
-            "SDIV" | "sdiv" => Ok((Self::SDIV, "05")),
-            "MOD" | "mod" => Ok((Self::MOD, "06")),
-            "SMOD" | "smod" => Ok((Self::SMOD, "07")),
-            "ADDMOD" | "addmod" => Ok((Self::ADDMOD, "08")),
-            "MULMOD" | "mulmod" => Ok((Self::MULMOD, "09")),
-            "EXP" | "exp" => Ok((Self::EXP, "0a")),
-            "SIGNEXTEND" | "signextend" => Ok((Self::SIGNEXTEND, "0b")),
-            "GT" | "gt" => Ok((Self::GT, "11")),
-            "SLT" | "slt" => Ok((Self::SLT, "12")),
-            "SGT" | "sgt" => Ok((Self::SGT, "13")),
-            "EQ" | "eq" => Ok((Self::EQ, "14")),
-            "CALLVALUE" | "callvalue" => Ok((Self::CALLVALUE, "34")),
-            "RETURN" | "return" => Ok((Self::RETURN, "f3")),
-
-            _ => Ok((Self::VALUE(str.to_string()), "0")),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct LexError {
-    line: Option<usize>,
-    description: &'static str,
-}
-
-impl LexError {
-    pub fn new(description: &'static str) -> Self {
-        Self {
-            line: None,
-            description,
-        }
-    }
-
-    /// Set line that Error happend
-    fn line(&mut self, l: usize) -> Self {
-        self.line = Some(l);
-
-        *self
-    }
-}
-
-impl Display for LexError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.line {
-            Some(line) => write!(
-                f,
-                "Error when lexing on line {} with description {}",
-                line, self.description
-            ),
-
-            None => write!(f, "Error when lexing with description {}", self.description),
-        }
-    }
-}
-
-impl Error for LexError {
-    fn description(&self) -> &str {
-        self.description
+mod config;
+
+use config::{Config, Format, Mode, USAGE};
+
+fn main() {
+    let config = match Config::parse(args().skip(1)) {
+        Ok(config) => config,
+
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+
+    #[cfg(feature = "watch")]
+    if config.watch {
+        watch(&config);
+    }
+
+    if let Err(error) = run(&config) {
+        eprintln!("{}", error);
+        process::exit(1);
     }
 }
 
-#[derive(Debug, Clone)]
-struct Lexer<'a> {
-    /// Result
-    result: Vec<(Op, &'a str)>,
+fn run(config: &Config) -> Result<(), Box<dyn Error>> {
+    if let Mode::Disassemble(hex) = &config.mode {
+        let bytecode = decode_hex(hex.strip_prefix("0x").unwrap_or(hex))?;
+        let base = config.base.unwrap_or(0);
+
+        match config.base {
+            Some(base) => print!("{}", easm::disassemble_at(&bytecode, base)),
+            None => println!("{}", easm::disassemble(&bytecode)),
+        }
+
+        if let Some(push) = easm::truncated_push(&bytecode) {
+            eprintln!(
+                "warning: the bytecode ends at 0x{:04x} in the middle of the {} at 0x{:04x}, it has {} of its {} bytes",
+                base + bytecode.len(),
+                push.op,
+                base + push.offset,
+                push.available,
+                push.op.is_push().unwrap_or(0)
+            );
+        }
+
+        return Ok(());
+    }
+
+    if let Mode::Annotate(hex) = &config.mode {
+        println!(
+            "{}",
+            easm::annotate(&decode_hex(hex.strip_prefix("0x").unwrap_or(hex))?)
+        );
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Link {
+        let objects = config
+            .inputs
+            .iter()
+            .map(|path| {
+                let text = fs::read_to_string(path)?;
+                easm::Object::parse(&text).map_err(|error| format!("{}: {}", path, error).into())
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        return output(config, easm::to_hex(&easm::link(&objects)?));
+    }
+
+    // `--explain add` explains the opcode itself, unless there is a file of that name
+    if let (Mode::Explain, None, [name]) = (&config.mode, &config.code, config.inputs.as_slice()) {
+        let opcode = easm::Op::from_str(name)
+            .ok()
+            .and_then(|(op, _)| Some((op.opcode()?, op)))
+            .filter(|_| !Path::new(name).exists());
+
+        if let Some((byte, op)) = opcode {
+            println!("{} (0x{:02x}): {}", op, byte, op.describe());
+
+            return Ok(());
+        }
+    }
+
+    let mut lexer = lexer(config)?;
+
+    // Each file is a program of its own, they only share the settings of the lexer
+    if config.mode == Mode::Diff {
+        let mut programs = vec![];
+        for input in &config.inputs {
+            let mut lexer = lexer.clone();
+            lexer.lex_file(Path::new(input))?;
+
+            programs.push(easm::assemble_sections(lexer, |lexer| {
+                easm::check_fork(&lexer.result, config.fork)?;
+                easm::assemble_lexer(lexer, config.fork)
+            })?);
+        }
+
+        let diff = easm::diff(&programs[0], &programs[1]);
+        print!("{}", diff);
+
+        if !diff.is_empty() {
+            process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    // The config rejects --code together with files
+    let lexed = match (&config.code, config.inputs.as_slice()) {
+        (Some(code), _) => lexer.lex(code),
+
+        (None, []) if io::stdin().is_terminal() => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+
+        (None, []) => lexer.lex_reader(io::stdin()),
+
+        (None, inputs) => inputs
+            .iter()
+            .try_for_each(|input| lexer.lex_file(Path::new(input))),
+    }
+    .map(|()| lexer);
+
+    if matches!(config.mode, Mode::Check | Mode::DiagnosticsJson) {
+        let mut diagnostics = match lexed {
+            Ok(lexer) if config.lint || config.strict => {
+                let lints = easm::lint(&lexer);
+                [easm::check(lexer, config.fork), lints].concat()
+            }
+            Ok(lexer) => easm::check(lexer, config.fork),
+            Err(error) => error.errors().iter().map(easm::Diagnostic::from).collect(),
+        };
+
+        if config.strict {
+            diagnostics = diagnostics
+                .into_iter()
+                .map(easm::Diagnostic::strict)
+                .collect();
+        }
+
+        match config.mode {
+            Mode::DiagnosticsJson => println!("{}", easm::diagnostics_json(&diagnostics)),
+
+            _ => {
+                for diagnostic in &diagnostics {
+                    eprintln!("{}", diagnostic);
+                }
+            }
+        }
+
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.level == easm::Level::Error)
+        {
+            process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    let mut lexer = lexed?;
+
+    if lexer.runtime.is_some() {
+        return sections(config, lexer);
+    }
+
+    easm::check_fork(&lexer.result, config.fork)?;
+
+    if config.optimize {
+        let saved = easm::optimize(&mut lexer, config.fork);
+        eprintln!("optimizer saved {} bytes", saved);
+    }
+
+    if config.verbose {
+        eprintln!("tokens:");
+        for (op, byte, line) in &lexer.result {
+            eprintln!("  line {}: {} {}", line, op, byte);
+        }
+    }
+
+    // Literals are told apart from labels before they are resolved
+    let lints = match config.lint || config.strict {
+        true => easm::lint(&lexer),
+        false => vec![],
+    };
+
+    let mut resolver = match config.emit_obj {
+        true => easm::Resolver::new().relocatable(),
+        false => easm::Resolver::new(),
+    }
+    .fork(config.fork);
+    resolver.resolve(&mut lexer.result)?;
+
+    let symbols = symbols(&resolver, &lexer);
+
+    if config.verbose {
+        eprintln!("symbols:");
+        for symbol in &symbols {
+            eprintln!("  {}", symbol);
+        }
+    }
+
+    if config.mode == Mode::Symbols {
+        for symbol in &symbols {
+            println!("{}", symbol);
+        }
+
+        return Ok(());
+    }
+
+    let tokens = lexer.result;
+    let spans = lexer.spans;
+
+    if config.mode == Mode::Gas {
+        let estimate = easm::estimate_gas(&tokens);
+        println!("{}", estimate.total);
+
+        if estimate.dynamic > 0 {
+            println!("{} dynamic-cost instructions not counted", estimate.dynamic);
+        }
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Stack {
+        let report = easm::check_stack(&tokens);
+        println!("linear stack check (best effort, jumps are not followed)");
+
+        // A DUP or SWAP too deep for the stack is the error below
+        let underflow = report
+            .underflow
+            .filter(|underflow| report.too_deep.as_ref() != Some(underflow));
+        if let Some(underflow) = underflow {
+            println!(
+                "stack underflow on line {} at {}, needs {} items but the stack has {}",
+                underflow.line, underflow.op, underflow.needed, underflow.available
+            );
+        }
+
+        if let Some(leftover) = report.leftover {
+            match leftover.stop {
+                true => println!(
+                    "STOP on line {} leaves {} items on the stack",
+                    leftover.line, leftover.items
+                ),
+                false => println!(
+                    "the code ends on line {} with {} items on the stack",
+                    leftover.line, leftover.items
+                ),
+            }
+        }
+
+        println!("final stack height: {}", report.height);
+        println!("max stack height: {}", report.max_height);
+
+        if let Some(fault) = report.too_deep {
+            return Err(format!(
+                "{} on line {} reaches {} items deep but the stack has {}",
+                fault.op, fault.line, fault.needed, fault.available
+            )
+            .into());
+        }
+
+        if report.max_height > easm::MAX_STACK_DEPTH {
+            return Err(format!(
+                "the stack reaches {} items, more than the {} the EVM allows",
+                report.max_height,
+                easm::MAX_STACK_DEPTH
+            )
+            .into());
+        }
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Listing {
+        print!("{}", easm::listing(&easm::Parser::new(&tokens).parse()?));
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Explain {
+        print!("{}", easm::explain(&easm::Parser::new(&tokens).parse()?));
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Ir {
+        print!("{}", easm::ir(&easm::Parser::new(&tokens).parse()?));
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::ListingGas {
+        println!("static gas and running total, * marks a dynamic cost left out of the total");
+        print!(
+            "{}",
+            easm::gas_listing(&easm::Parser::new(&tokens).parse()?)
+        );
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Trace {
+        println!("linear stack trace (best effort, jumps are not followed)");
+        print!("{}", easm::trace(&easm::Parser::new(&tokens).parse()?));
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::TokensJson {
+        println!("{}", easm::tokens_json(&tokens));
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Cfg {
+        let flow = easm::control_flow(&easm::Parser::new(&tokens).parse()?);
+        let sources = |offsets: &[usize]| {
+            offsets
+                .iter()
+                .map(|offset| format!("0x{:04x}", offset))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        for (target, jumps) in &flow.targets {
+            // A JUMPDEST nothing jumps to constantly is listed without sources
+            println!(
+                "{}",
+                format!("0x{:04x} <- {}", target, sources(jumps)).trim_end()
+            );
+        }
+
+        if !flow.dynamic.is_empty() {
+            println!("<dynamic> <- {}", sources(&flow.dynamic));
+        }
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Coverage {
+        let coverage = easm::coverage(&easm::Parser::new(&tokens).parse()?);
+        println!(
+            "used {} of {} opcodes:",
+            coverage.used.len(),
+            coverage.used.len() + coverage.unused.len()
+        );
+
+        for op in &coverage.used {
+            match op.is_deprecated() {
+                true => println!("  {}  deprecated", op),
+                false => println!("  {}", op),
+            }
+        }
+
+        println!("unused:");
+        for op in &coverage.unused {
+            println!("  {}", op);
+        }
+
+        return Ok(());
+    }
+
+    if config.mode == Mode::Stats {
+        let stats = easm::stats(&easm::Parser::new(&tokens).parse()?);
+        println!("size: {} bytes", stats.size);
+        println!("instructions: {}", stats.instructions);
+
+        let width = stats
+            .histogram
+            .iter()
+            .map(|(mnemonic, _)| mnemonic.len())
+            .max()
+            .unwrap_or(0);
+
+        for (mnemonic, count) in &stats.histogram {
+            println!("  {:<width$}  {}", mnemonic, count);
+        }
+
+        if stats.size > easm::MAX_CODE_SIZE {
+            println!(
+                "exceeds the {} byte contract size limit of EIP-170",
+                easm::MAX_CODE_SIZE
+            );
+        }
+
+        return Ok(());
+    }
+
+    let mut instructions = easm::Parser::new(&tokens).spans(&spans).parse()?;
+
+    if config.auto_stop {
+        auto_stop(&mut instructions);
+    }
+
+    let stack = match config.lint || config.strict {
+        true => stack_warnings(&tokens),
+        false => vec![],
+    };
+    warn(
+        config,
+        [lints, stack, easm::warnings(&instructions)].concat(),
+    )?;
+
+    if config.emit_obj {
+        let object = easm::Object::new(&instructions, &resolver).to_string();
+
+        match &config.output {
+            Some(path) => fs::write(path, object)?,
+            None => print!("{}", object),
+        }
+
+        return Ok(());
+    }
+
+    // The config rejects every flag that needs the bytecode itself with --count-only
+    if config.count_only {
+        let size = instructions.iter().map(easm::Instruction::size).sum();
+        check_size(config, size, None)?;
+        println!("{}", size);
+
+        return Ok(());
+    }
+
+    let mut bytecode = easm::to_hex(&easm::bytecode(&instructions));
+
+    if let Some(path) = &config.sourcemap {
+        fs::write(path, easm::source_map(&instructions))?;
+    }
+
+    if config.verbose {
+        eprintln!("bytecode: {} bytes", bytecode.len() / 2);
+    }
+
+    if config.verify {
+        verify(&decode_hex(&bytecode)?)?;
+    }
+
+    if let Some(entry) = &config.entry {
+        let prefix = decode_hex(entry.strip_prefix("0x").unwrap_or(entry))
+            .map_err(|_| format!("--entry {} is not hex", entry))?;
+        let code = decode_hex(&bytecode)?;
+
+        if !code.starts_with(&prefix) {
+            return Err(format!(
+                "the code starts with 0x{} instead of the --entry 0x{}",
+                easm::to_hex(&code[..prefix.len().min(code.len())]),
+                easm::to_hex(&prefix)
+            )
+            .into());
+        }
+    }
+
+    // The trailer is data after the code, it is left out of the source map and verification
+    if let Some(hash) = &config.metadata {
+        let bytes = decode_hex(hash.strip_prefix("0x").unwrap_or(hash))
+            .map_err(|_| format!("--metadata-hash {} is not hex", hash))?;
+        let trailer = easm::metadata(&bytes).ok_or_else(|| {
+            format!(
+                "--metadata-hash is {} bytes, expected a 34 byte IPFS or 32 byte swarm hash",
+                bytes.len()
+            )
+        })?;
+
+        bytecode.push_str(&easm::to_hex(&trailer));
+    }
+
+    let runtime = bytecode.len() / 2;
+
+    // The source map and the verification are of the runtime, not of the init code
+    if config.deploy {
+        bytecode = easm::to_hex(&easm::deploy(&decode_hex(&bytecode)?));
+    }
+
+    let initcode = config.deploy.then_some(bytecode.len() / 2);
+    check_size(config, runtime, initcode)?;
+
+    if let Some(path) = &config.manifest {
+        let manifest = manifest(
+            config,
+            &lexer.files,
+            &decode_hex(&bytecode)?,
+            &resolver.labels,
+            &lexer.constants,
+        )?;
+
+        fs::write(path, manifest)?;
+    }
+
+    output(config, bytecode)
 }
 
-impl<'a> Lexer<'a> {
-    pub fn new() -> Self {
-        Self { result: vec![] }
+/// Lexer with the settings of the config, before it lexes anything
+fn lexer(config: &Config) -> Result<easm::Lexer, Box<dyn Error>> {
+    let mut lexer = easm::Lexer::new();
+    lexer.little_endian = config.little_endian;
+    lexer.max_ops = config.max_ops;
+    lexer.include_paths = config.include_paths.iter().map(PathBuf::from).collect();
+
+    if let Some(path) = &config.opcodes {
+        lexer.add_opcodes(&fs::read_to_string(path)?, config.override_opcodes)?;
     }
+    for name in &config.defines {
+        lexer.constants.insert(name.clone(), "1".to_string());
+        lexer.overrides.insert(name.clone());
+    }
+
+    Ok(lexer)
+}
 
-    /// Start lexing
-    fn lex(&mut self, source: &'static str) -> Result<(), Box<dyn Error>> {
-        let mut splited = source.split_whitespace();
+/// Assemble the FILEs again every time one of them, a file they include or the opcode
+/// table changes, until the process is stopped
+#[cfg(feature = "watch")]
+fn watch(config: &Config) -> ! {
+    loop {
+        match run(config) {
+            Ok(()) => eprintln!("assembled {}", config.inputs.join(", ")),
+            Err(error) => eprintln!("{}", error),
+        }
 
-        while let Some(word) = splited.next() {
-            match Op::from_str(word) {
-                Ok(op) => self.result.push(op),
+        // The files are found again as an #include may have been added or removed
+        let mut files: Vec<PathBuf> = config
+            .inputs
+            .iter()
+            .chain(&config.opcodes)
+            .map(PathBuf::from)
+            .collect();
 
-                Err(error) => panic!("{}", error),
+        if let Ok(mut lexer) = lexer(config) {
+            for input in &config.inputs {
+                // The files read before an error are still watched
+                let _ = lexer.lex_file(Path::new(input));
             }
+
+            files.extend(lexer.files);
         }
 
-        Ok(())
+        wait_for_change(&files);
     }
 }
 
-#[derive(Debug)]
-struct Parser<'a> {
-    cursor: usize,
-    tokens: &'a Vec<(Op, &'a str)>,
+/// Return once a file is changed and then left alone for a moment, an editor may save a
+/// file in more than one write
+#[cfg(feature = "watch")]
+fn wait_for_change(files: &[PathBuf]) {
+    use std::{thread, time::Duration};
+
+    const POLL: Duration = Duration::from_millis(200);
+
+    let modified = || -> Vec<_> {
+        files
+            .iter()
+            .map(|file| {
+                fs::metadata(file)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect()
+    };
+
+    let unchanged = modified();
+    let mut last = unchanged.clone();
+
+    loop {
+        thread::sleep(POLL);
+        let now = modified();
+
+        if now == last && now != unchanged {
+            return;
+        }
+
+        last = now;
+    }
 }
 
-impl<'a> Iterator for Parser<'a> {
-    type Item = (Op, &'a str);
+/// Append a STOP unless the code already ends with a terminating opcode. The STOP goes
+/// after any trailing data, so the offsets of the program stay the same
+fn auto_stop(instructions: &mut Vec<easm::Instruction>) {
+    if easm::ends_with_terminator(instructions) {
+        return;
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let result = match self.tokens.get(self.cursor) {
-            Some(token) => Some(token.clone()),
-            None => None,
+    instructions.push(easm::Instruction {
+        op: easm::Op::STOP,
+        opcode: Some(0x00),
+        operand: None,
+        line: instructions
+            .last()
+            .map_or(1, |instruction| instruction.line),
+        column: 0,
+        len: 0,
+    });
+}
+
+/// Assemble the `.init` and `.runtime` sections into creation bytecode that returns the
+/// runtime, each section has labels of its own and is linted and warned about on its own.
+/// `--auto-stop` only ends the runtime, the init code must run on into the code after it
+fn sections(config: &Config, lexer: easm::Lexer) -> Result<(), Box<dyn Error>> {
+    if config.mode != Mode::Assemble {
+        return Err("a program of .init and .runtime sections can only be assembled".into());
+    }
+
+    if config.emit_obj
+        || config.deploy
+        || config.count_only
+        || config.metadata.is_some()
+        || config.entry.is_some()
+        || config.verify
+        || config.sourcemap.is_some()
+        || config.manifest.is_some()
+    {
+        return Err(
+            "a program of .init and .runtime sections can not be used with \
+                    --emit-obj, --deploy, --count-only, --metadata, --entry, --verify, \
+                    --sourcemap or --manifest"
+                .into(),
+        );
+    }
+
+    // The init section is assembled first, then the runtime
+    let mut sizes = vec![];
+    let creation = easm::assemble_sections(lexer, |mut lexer| -> Result<_, Box<dyn Error>> {
+        easm::check_fork(&lexer.result, config.fork)?;
+
+        if config.optimize {
+            let saved = easm::optimize(&mut lexer, config.fork);
+            eprintln!("optimizer saved {} bytes", saved);
+        }
+
+        let lints = match config.lint || config.strict {
+            true => easm::lint(&lexer),
+            false => vec![],
         };
 
-        self.cursor += 1;
+        easm::Resolver::new()
+            .fork(config.fork)
+            .resolve(&mut lexer.result)?;
+        let mut instructions = easm::Parser::new(&lexer.result)
+            .spans(&lexer.spans)
+            .parse()?;
 
-        result
+        if config.auto_stop && sizes.len() == 1 {
+            auto_stop(&mut instructions);
+        }
+
+        let stack = match config.lint || config.strict {
+            true => stack_warnings(&lexer.result),
+            false => vec![],
+        };
+        warn(
+            config,
+            [lints, stack, easm::warnings(&instructions)].concat(),
+        )?;
+
+        let code = easm::bytecode(&instructions);
+        sizes.push(code.len());
+
+        Ok(code)
+    })?;
+
+    check_size(config, sizes[1], Some(creation.len()))?;
+
+    output(config, easm::to_hex(&creation))
+}
+
+/// The manifest of the build, a file is named relative to the working directory when it is
+/// in it so the manifest is the same on every machine
+fn manifest(
+    config: &Config,
+    files: &[PathBuf],
+    bytecode: &[u8],
+    labels: &BTreeMap<String, usize>,
+    constants: &BTreeMap<String, String>,
+) -> Result<String, Box<dyn Error>> {
+    let directory = env::current_dir()?;
+
+    let mut inputs = vec![];
+    for file in files {
+        let name = file.strip_prefix(&directory).unwrap_or(file);
+        inputs.push((name.display().to_string(), fs::read(file)?));
     }
+
+    let inputs: Vec<(&str, &[u8])> = inputs
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.as_slice()))
+        .collect();
+
+    Ok(easm::manifest(
+        &inputs,
+        config.fork,
+        bytecode,
+        labels,
+        constants,
+    ))
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<(Op, &'a str)>) -> Self {
-        Self { cursor: 0, tokens }
+/// Warn about the size of the code, failing with --max-size or --require-nonempty
+fn check_size(
+    config: &Config,
+    runtime: usize,
+    initcode: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let warnings = easm::size_warnings(runtime, initcode);
+    for warning in &warnings {
+        let fatal = match warning.code {
+            "EmptyProgram" => config.require_nonempty,
+            _ => config.max_size,
+        };
+
+        if fatal {
+            return Err(warning.message.clone().into());
+        }
     }
 
-    fn parse(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
-        let mut result: Vec<String> = vec![];
+    warn(config, warnings)
+}
 
-        while let Some(token) = self.next() {
-            match token {
-                (Op::VALUE(value), "0") => result.push(value.trim_start_matches("0x").to_string()),
+/// What the stack check finds for `--lint` and `--strict`, all as warnings since the check
+/// does not follow jumps and only `analyze --check` or `--strict` fail on it
+fn stack_warnings(tokens: &[(easm::Op, &str, usize)]) -> Vec<easm::Diagnostic> {
+    easm::stack_diagnostics(tokens)
+        .into_iter()
+        .map(|diagnostic| easm::Diagnostic {
+            level: easm::Level::Warning,
+            ..diagnostic
+        })
+        .collect()
+}
 
-                _ => {
-                    result.push(token.1.to_string());
-                }
-            }
+/// Print the warnings, with `--strict` as errors that stop the assembly
+fn warn(config: &Config, warnings: Vec<easm::Diagnostic>) -> Result<(), Box<dyn Error>> {
+    for warning in warnings.iter().cloned() {
+        match config.strict {
+            true => eprintln!("{}", warning.strict()),
+            false => eprintln!("{}", warning),
         }
+    }
 
-        Ok(result)
+    if config.strict && !warnings.is_empty() {
+        return Err("the warnings are errors with --strict".into());
     }
+
+    Ok(())
 }
 
-fn main() {
-    let file_addr = args().nth(1).unwrap();
-    let source = Box::new(fs::read_to_string(file_addr).unwrap());
+/// Write the hex bytecode to the output in its format
+fn output(config: &Config, bytecode: String) -> Result<(), Box<dyn Error>> {
+    let format = config.format.unwrap_or(match &config.output {
+        Some(path) if Path::new(path).extension().is_some_and(|ext| ext == "bin") => Format::Bin,
+        _ => Format::Hex,
+    });
 
-    let mut lexer = Lexer::new();
-    lexer.lex(Box::leak(source)).unwrap();
+    if format == Format::Bin && config.columns.is_some() {
+        return Err("--columns only applies to hex output".into());
+    }
 
-    let mut parser = Parser::new(&lexer.result);
-    let result = parser.parse().unwrap();
+    if format == Format::Bin && config.uppercase {
+        return Err("--output-case only applies to hex output".into());
+    }
+
+    match (format, &config.output) {
+        (Format::Bin, Some(path)) => fs::write(path, decode_hex(&bytecode)?)?,
+
+        (Format::Bin, None) => io::stdout().write_all(&decode_hex(&bytecode)?)?,
+
+        (Format::Hex, Some(path)) => fs::write(path, rows(&bytecode, config))?,
+
+        (Format::Hex, None) => println!("{}", rows(&bytecode, config)),
+    }
+
+    Ok(())
+}
+
+/// The hex output, split into rows of `--columns` bytes that each start with their
+/// offset, with every row prefixed when asked. Only the bytecode takes `--output-case`
+fn rows(bytecode: &str, config: &Config) -> String {
+    let upper;
+    let bytecode = match config.uppercase {
+        true => {
+            upper = bytecode.to_uppercase();
+            upper.as_str()
+        }
+        false => bytecode,
+    };
+
+    let Some(columns) = config.columns else {
+        return with_prefix(bytecode.to_string(), config.hex_prefix);
+    };
+
+    bytecode
+        .as_bytes()
+        .chunks(columns * 2)
+        .enumerate()
+        .map(|(row, hex)| {
+            let hex = String::from_utf8_lossy(hex).into_owned();
+            format!(
+                "{:04x}  {}",
+                row * columns,
+                with_prefix(hex, config.hex_prefix)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Disassemble the bytecode and assemble it again, erroring where the two differ
+fn verify(bytecode: &[u8]) -> Result<(), Box<dyn Error>> {
+    let reassembled = easm::assemble(&easm::disassemble_source(bytecode)).map_err(|error| {
+        format!(
+            "verification failed, the disassembled bytecode does not assemble: {}",
+            error
+        )
+    })?;
+    let reassembled = decode_hex(&reassembled)?;
+
+    let mismatch =
+        (0..bytecode.len().max(reassembled.len())).find(|&i| bytecode.get(i) != reassembled.get(i));
+
+    if let Some(offset) = mismatch {
+        let byte = |bytes: &[u8]| {
+            bytes
+                .get(offset)
+                .map_or("nothing".to_string(), |byte| format!("0x{:02x}", byte))
+        };
+
+        return Err(format!(
+            "verification failed at byte 0x{:04x}, assembled {} but the round trip gave {}",
+            offset,
+            byte(bytecode),
+            byte(&reassembled)
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// One line per label with its offset sorted by offset, followed by the constants
+/// with their value sorted by name
+fn symbols(resolver: &easm::Resolver, lexer: &easm::Lexer) -> Vec<String> {
+    let mut labels: Vec<_> = resolver.labels.iter().collect();
+    labels.sort_by_key(|(name, offset)| (**offset, *name));
+
+    let mut constants: Vec<_> = lexer.constants.iter().collect();
+    constants.sort();
+
+    labels
+        .into_iter()
+        .map(|(name, offset)| format!("label {} 0x{:04x}", name, offset))
+        .chain(
+            constants
+                .into_iter()
+                .map(|(name, value)| format!("constant {} {}", name, value)),
+        )
+        .collect()
+}
+
+/// Prepend 0x to the hex bytecode if `prefix` is set
+fn with_prefix(bytecode: String, prefix: bool) -> String {
+    if prefix {
+        format!("0x{}", bytecode)
+    } else {
+        bytecode
+    }
+}
+
+/// Decode a hex string into bytes
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err("bytecode has an odd number of hex digits".into());
+    }
 
-    println!("{}", result.join("").to_string());
+    (0..hex.len())
+        .step_by(2)
+        .map(
+            |i| match hex.get(i..i + 2).map(|byte| u8::from_str_radix(byte, 16)) {
+                Some(Ok(byte)) => Ok(byte),
+                _ => Err(format!("bytecode has a non hex character at {}", i).into()),
+            },
+        )
+        .collect()
 }
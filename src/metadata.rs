@@ -0,0 +1,45 @@
+use alloc::{vec, vec::Vec};
+
+/// The CBOR metadata trailer solc appends to the runtime, a map of the hash followed by
+/// the length of the map as two big-endian bytes. A 34 byte hash is an IPFS multihash
+/// and a 32 byte hash a swarm hash, `None` for any other length
+pub fn metadata(hash: &[u8]) -> Option<Vec<u8>> {
+    let key = match hash.len() {
+        34 => "ipfs",
+        32 => "bzzr1",
+        _ => return None,
+    };
+
+    // A map of one pair, a text key and a byte string value of at most 255 bytes
+    let mut cbor = vec![0xa1, 0x60 + key.len() as u8];
+    cbor.extend(key.as_bytes());
+    cbor.extend([0x58, hash.len() as u8]);
+    cbor.extend(hash);
+
+    let length = (cbor.len() as u16).to_be_bytes();
+    cbor.extend(length);
+
+    Some(cbor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailer_ends_with_its_length() {
+        let mut hash = vec![0x12, 0x20];
+        hash.extend([0xab; 32]);
+
+        let trailer = metadata(&hash).unwrap();
+        assert_eq!(trailer[..7], [0xa1, 0x64, b'i', b'p', b'f', b's', 0x58]);
+        assert_eq!(trailer[7], 34);
+
+        let length = trailer.len() - 2;
+        assert_eq!(trailer[length..], (length as u16).to_be_bytes());
+        assert_eq!(length, 42);
+
+        assert_eq!(metadata(&[0; 32]).unwrap()[..7], *b"\xa1\x65bzzr1");
+        assert!(metadata(&[0; 20]).is_none());
+    }
+}
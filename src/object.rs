@@ -0,0 +1,289 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::{
+    bytecode,
+    error::Kind,
+    instruction::{decode_hex, to_hex},
+    AssembleError, Instruction, ParseError, Resolver,
+};
+
+/// First line of an object, which also tells its format apart from source
+const HEADER: &str = "easm object";
+
+/// An operand in the code of an object that is patched when the object is linked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Byte offset of the operand in the code
+    pub offset: usize,
+
+    /// Bytes the operand takes
+    pub width: usize,
+
+    /// Label of another object the operand refers to, `None` for an offset in this
+    /// object that moves with its code
+    pub symbol: Option<String>,
+}
+
+/// Code assembled on its own to be linked with other objects. Its labels are the
+/// symbols other objects can refer to
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Object {
+    pub code: Vec<u8>,
+
+    /// Offset of every label in the code
+    pub symbols: BTreeMap<String, usize>,
+
+    pub relocations: Vec<Relocation>,
+}
+
+impl Object {
+    /// Object of instructions resolved by a relocatable resolver
+    pub fn new(instructions: &[Instruction], resolver: &Resolver) -> Self {
+        // A local label like `1:` is resolved as `1@0` and is not visible from other objects
+        let symbols = resolver
+            .labels
+            .iter()
+            .filter(|(name, _)| !name.contains('@'))
+            .map(|(name, &offset)| (name.clone(), offset))
+            .collect();
+
+        Self {
+            code: bytecode(instructions),
+            symbols,
+            relocations: resolver.relocations.clone(),
+        }
+    }
+
+    /// Read an object written by its `Display`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(ParseError::new(format!(
+                "object does not start with \"{}\"",
+                HEADER
+            )));
+        }
+
+        let mut object = Self::default();
+        for (number, line) in lines {
+            let error = || {
+                ParseError::new(format!("line {} of the object is malformed", number)).line(number)
+            };
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+
+                ["code", hex] => object.code = decode_hex(hex).ok_or_else(error)?,
+
+                ["code"] => {}
+
+                ["symbol", name, offset] => {
+                    object
+                        .symbols
+                        .insert(name.to_string(), number_of(offset).ok_or_else(error)?);
+                }
+
+                ["relocation", offset, width, ref symbol @ ..] if symbol.len() <= 1 => {
+                    let relocation = Relocation {
+                        offset: number_of(offset).ok_or_else(error)?,
+                        width: width.parse().map_err(|_| error())?,
+                        symbol: symbol.first().map(|symbol| symbol.to_string()),
+                    };
+
+                    if !(1..=32).contains(&relocation.width) {
+                        return Err(error());
+                    }
+
+                    object.relocations.push(relocation);
+                }
+
+                _ => return Err(error()),
+            }
+        }
+
+        for relocation in &object.relocations {
+            let end = relocation.offset.checked_add(relocation.width);
+            if end.is_none_or(|end| end > object.code.len()) {
+                return Err(ParseError::new(format!(
+                    "relocation at 0x{:04x} is outside the {} bytes of code",
+                    relocation.offset,
+                    object.code.len()
+                )));
+            }
+        }
+
+        Ok(object)
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "code {}", to_hex(&self.code))?;
+
+        for (name, offset) in &self.symbols {
+            writeln!(f, "symbol {} 0x{:04x}", name, offset)?;
+        }
+
+        for relocation in &self.relocations {
+            write!(
+                f,
+                "relocation 0x{:04x} {}",
+                relocation.offset, relocation.width
+            )?;
+
+            match &relocation.symbol {
+                Some(symbol) => writeln!(f, " {}", symbol)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Place the objects one after the other and patch every relocation with the final
+/// offset it refers to. Every symbol that is defined twice or not at all is an error
+pub fn link(objects: &[Object]) -> Result<Vec<u8>, AssembleError> {
+    let mut errors = vec![];
+
+    let mut bases = Vec::with_capacity(objects.len());
+    let mut symbols: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut code = vec![];
+
+    for (index, object) in objects.iter().enumerate() {
+        bases.push(code.len());
+
+        for (name, &offset) in &object.symbols {
+            if let Some((_, first)) = symbols.insert(name, (code.len() + offset, index)) {
+                errors.push(ParseError::new(format!(
+                    "label {} is defined in object {} and again in object {}",
+                    name,
+                    first + 1,
+                    index + 1
+                )));
+            }
+        }
+
+        code.extend(&object.code);
+    }
+
+    for (index, (object, base)) in objects.iter().zip(bases).enumerate() {
+        for relocation in &object.relocations {
+            let start = base + relocation.offset;
+            let operand = &mut code[start..start + relocation.width];
+
+            let value = match &relocation.symbol {
+                None => read(operand).and_then(|offset| offset.checked_add(base)),
+
+                Some(symbol) => match symbols.get(symbol.as_str()) {
+                    Some(&(offset, _)) => Some(offset),
+
+                    None => {
+                        errors.push(
+                            ParseError::new(format!(
+                                "label {} used in object {} is not defined",
+                                symbol,
+                                index + 1
+                            ))
+                            .kind(Kind::UndefinedLabel(symbol.clone())),
+                        );
+
+                        continue;
+                    }
+                },
+            };
+
+            if !value.is_some_and(|value| write(operand, value)) {
+                let label = relocation.symbol.as_deref().unwrap_or("offset");
+
+                errors.push(
+                    ParseError::new(format!(
+                        "{} at 0x{:04x} of object {} does not fit {} bytes once linked",
+                        label,
+                        relocation.offset,
+                        index + 1,
+                        relocation.width
+                    ))
+                    .kind(Kind::BadOperandWidth {
+                        operand: label.to_string(),
+                        expected: relocation.width,
+                    }),
+                );
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(AssembleError::many(errors));
+    }
+
+    Ok(code)
+}
+
+/// Big-endian operand as an offset, `None` when it is too large
+fn read(operand: &[u8]) -> Option<usize> {
+    operand.iter().try_fold(0usize, |value, &byte| {
+        value.checked_mul(256)?.checked_add(byte as usize)
+    })
+}
+
+/// Write the offset big-endian into the operand, false when it does not fit
+fn write(operand: &mut [u8], value: usize) -> bool {
+    let bytes = value.to_be_bytes();
+    let width = operand.len().min(bytes.len());
+
+    if bytes[..bytes.len() - width].iter().any(|&byte| byte != 0) {
+        return false;
+    }
+
+    operand.fill(0);
+    let start = operand.len() - width;
+    operand[start..].copy_from_slice(&bytes[bytes.len() - width..]);
+
+    true
+}
+
+fn number_of(value: &str) -> Option<usize> {
+    usize::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_round_trip_as_text() {
+        let object = Object {
+            code: vec![0x61, 0x00, 0x00, 0x56],
+            symbols: BTreeMap::from([("main".to_string(), 0)]),
+            relocations: vec![Relocation {
+                offset: 1,
+                width: 2,
+                symbol: Some("transfer".to_string()),
+            }],
+        };
+
+        let text = object.to_string();
+        assert_eq!(
+            text,
+            "easm object\ncode 61000056\nsymbol main 0x0000\nrelocation 0x0001 2 transfer\n"
+        );
+        assert_eq!(Object::parse(&text).unwrap(), object);
+
+        assert!(Object::parse("code 00").is_err());
+        assert!(Object::parse("easm object\ncode 00\nrelocation 0x0000 2\n").is_err());
+        assert!(
+            Object::parse("easm object\ncode 0000\nrelocation 0xffffffffffffffff 2\n").is_err()
+        );
+    }
+}
@@ -0,0 +1,1946 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Display, str::FromStr};
+#[cfg(feature = "std")]
+use std::{sync::OnceLock, vec};
+
+use crate::{error::Kind, instruction::to_hex, Fork, LexError};
+
+/// Every opcode mnemonic understood by `Op::from_str`
+pub const MNEMONICS: &[&str] = &[
+    "MSTORE",
+    "MLOAD",
+    "SLOAD",
+    "SSTORE",
+    "TLOAD",
+    "TSTORE",
+    "MCOPY",
+    "JUMP",
+    "JUMPI",
+    "PC",
+    "MSIZE",
+    "GAS",
+    "JUMPDEST",
+    "CREATE",
+    "CREATE2",
+    "EXTCODECOPY",
+    "EXTCODESIZE",
+    "EXTCODEHASH",
+    "PUSH1",
+    "PUSH2",
+    "PUSH3",
+    "PUSH4",
+    "PUSH5",
+    "PUSH6",
+    "PUSH7",
+    "PUSH8",
+    "PUSH9",
+    "PUSH10",
+    "PUSH11",
+    "PUSH12",
+    "PUSH13",
+    "PUSH14",
+    "PUSH15",
+    "PUSH16",
+    "PUSH17",
+    "PUSH18",
+    "PUSH19",
+    "PUSH20",
+    "PUSH21",
+    "PUSH22",
+    "PUSH23",
+    "PUSH24",
+    "PUSH25",
+    "PUSH26",
+    "PUSH27",
+    "PUSH28",
+    "PUSH29",
+    "PUSH30",
+    "PUSH31",
+    "PUSH32",
+    "PUSH0",
+    "POP",
+    "DUP1",
+    "DUP2",
+    "DUP3",
+    "DUP4",
+    "DUP5",
+    "DUP6",
+    "DUP7",
+    "DUP8",
+    "DUP9",
+    "DUP10",
+    "DUP11",
+    "DUP12",
+    "DUP13",
+    "DUP14",
+    "DUP15",
+    "DUP16",
+    "SWAP1",
+    "SWAP2",
+    "SWAP3",
+    "SWAP4",
+    "SWAP5",
+    "SWAP6",
+    "SWAP7",
+    "SWAP8",
+    "SWAP9",
+    "SWAP10",
+    "SWAP11",
+    "SWAP12",
+    "SWAP13",
+    "SWAP14",
+    "SWAP15",
+    "SWAP16",
+    "LOG0",
+    "LOG1",
+    "LOG2",
+    "LOG3",
+    "LOG4",
+    "STOP",
+    "ADD",
+    "MUL",
+    "SUB",
+    "DIV",
+    "SDIV",
+    "MOD",
+    "SMOD",
+    "ADDMOD",
+    "MULMOD",
+    "EXP",
+    "SIGNEXTEND",
+    "LT",
+    "GT",
+    "SLT",
+    "SGT",
+    "EQ",
+    "ISZERO",
+    "AND",
+    "OR",
+    "XOR",
+    "NOT",
+    "BYTE",
+    "SHL",
+    "SHR",
+    "SAR",
+    "SHA3",
+    "ADDRESS",
+    "BALANCE",
+    "CALLVALUE",
+    "CALLDATALOAD",
+    "CALLDATASIZE",
+    "CALLDATACOPY",
+    "RETURNDATASIZE",
+    "RETURNDATACOPY",
+    "CALLER",
+    "ORIGIN",
+    "GASPRICE",
+    "CODESIZE",
+    "CODECOPY",
+    "BLOCKHASH",
+    "COINBASE",
+    "TIMESTAMP",
+    "NUMBER",
+    "PREVRANDAO",
+    "GASLIMIT",
+    "CHAINID",
+    "SELFBALANCE",
+    "BASEFEE",
+    "RETURN",
+    "REVERT",
+    "INVALID",
+    "SELFDESTRUCT",
+    "CALL",
+    "CALLCODE",
+    "DELEGATECALL",
+    "STATICCALL",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    MSTORE,
+    MLOAD,
+    SLOAD,
+    SSTORE,
+    TLOAD,
+    TSTORE,
+    MCOPY,
+    JUMP,
+    JUMPI,
+    PC,
+    MSIZE,
+    GAS,
+    JUMPDEST,
+    CREATE,
+    CREATE2,
+    EXTCODECOPY,
+    EXTCODESIZE,
+    EXTCODEHASH,
+    PUSH,
+    PUSH1,
+    PUSH2,
+    PUSH3,
+    PUSH4,
+    PUSH5,
+    PUSH6,
+    PUSH7,
+    PUSH8,
+    PUSH9,
+    PUSH10,
+    PUSH11,
+    PUSH12,
+    PUSH13,
+    PUSH14,
+    PUSH15,
+    PUSH16,
+    PUSH17,
+    PUSH18,
+    PUSH19,
+    PUSH20,
+    PUSH21,
+    PUSH22,
+    PUSH23,
+    PUSH24,
+    PUSH25,
+    PUSH26,
+    PUSH27,
+    PUSH28,
+    PUSH29,
+    PUSH30,
+    PUSH31,
+    PUSH32,
+    PUSH0,
+    POP,
+    DUP1,
+    DUP2,
+    DUP3,
+    DUP4,
+    DUP5,
+    DUP6,
+    DUP7,
+    DUP8,
+    DUP9,
+    DUP10,
+    DUP11,
+    DUP12,
+    DUP13,
+    DUP14,
+    DUP15,
+    DUP16,
+    SWAP1,
+    SWAP2,
+    SWAP3,
+    SWAP4,
+    SWAP5,
+    SWAP6,
+    SWAP7,
+    SWAP8,
+    SWAP9,
+    SWAP10,
+    SWAP11,
+    SWAP12,
+    SWAP13,
+    SWAP14,
+    SWAP15,
+    SWAP16,
+    LOG0,
+    LOG1,
+    LOG2,
+    LOG3,
+    LOG4,
+    VALUE(String),
+    LABEL(String),
+
+    /// Bytes of a `.data` or `.raw` directive, emitted as they are
+    DATA(Vec<u8>),
+
+    /// Byte offset of an `.org` directive, the resolver pads up to it with data
+    ORG(usize),
+
+    /// Boundary and pad byte of an `.align` directive, the resolver pads up to it with data
+    ALIGN(usize, u8),
+
+    /// Uppercase mnemonic and byte of an opcode added with `Lexer::add_opcode`
+    CUSTOM(String, u8),
+
+    STOP,
+    ADD,
+    MUL,
+    SUB,
+    DIV,
+    SDIV,
+    MOD,
+    SMOD,
+    ADDMOD,
+    MULMOD,
+    EXP,
+    SIGNEXTEND,
+    LT,
+    GT,
+    SLT,
+    SGT,
+    EQ,
+    ISZERO,
+    AND,
+    OR,
+    XOR,
+    NOT,
+    BYTE,
+    SHL,
+    SHR,
+    SAR,
+    SHA3,
+    ADDRESS,
+    BALANCE,
+    CALLVALUE,
+    CALLDATALOAD,
+    CALLDATASIZE,
+    CALLDATACOPY,
+    RETURNDATASIZE,
+    RETURNDATACOPY,
+    CALLER,
+    ORIGIN,
+    GASPRICE,
+    CODESIZE,
+    CODECOPY,
+    BLOCKHASH,
+    COINBASE,
+    TIMESTAMP,
+    NUMBER,
+    PREVRANDAO,
+    GASLIMIT,
+    CHAINID,
+    SELFBALANCE,
+    BASEFEE,
+    RETURN,
+    REVERT,
+    INVALID,
+    SELFDESTRUCT,
+    CALL,
+    CALLCODE,
+    DELEGATECALL,
+    STATICCALL,
+}
+
+impl Op {
+    /// Parse a token into its opcode and the hex of its byte, which is empty when it has none
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(str: &str) -> Result<(Self, &'static str), LexError> {
+        let op = Self::parse_token(str)?;
+
+        let byte = op.opcode().map_or("", hex_byte);
+
+        Ok((op, byte))
+    }
+
+    /// Parse an opcode mnemonic, a label definition or a literal
+    fn parse_token(str: &str) -> Result<Self, LexError> {
+        // Mnemonics are case-insensitive, labels and literals keep their spelling
+        match str.to_ascii_uppercase().as_str() {
+            "MSTORE" => Ok(Self::MSTORE),
+            "MLOAD" => Ok(Self::MLOAD),
+            "SLOAD" => Ok(Self::SLOAD),
+            "SSTORE" => Ok(Self::SSTORE),
+            "TLOAD" => Ok(Self::TLOAD),
+            "TSTORE" => Ok(Self::TSTORE),
+            "MCOPY" => Ok(Self::MCOPY),
+            "JUMP" => Ok(Self::JUMP),
+            "JUMPI" => Ok(Self::JUMPI),
+            "PC" => Ok(Self::PC),
+            "MSIZE" => Ok(Self::MSIZE),
+            "GAS" => Ok(Self::GAS),
+            "JUMPDEST" => Ok(Self::JUMPDEST),
+            "CREATE" => Ok(Self::CREATE),
+            "CREATE2" => Ok(Self::CREATE2),
+            "EXTCODECOPY" => Ok(Self::EXTCODECOPY),
+            "EXTCODESIZE" => Ok(Self::EXTCODESIZE),
+            "EXTCODEHASH" => Ok(Self::EXTCODEHASH),
+            // The size of a bare PUSH is inferred from its operand by the Resolver
+            "PUSH" => Ok(Self::PUSH),
+            "PUSH1" => Ok(Self::PUSH1),
+            "PUSH2" => Ok(Self::PUSH2),
+            "PUSH3" => Ok(Self::PUSH3),
+            "PUSH4" => Ok(Self::PUSH4),
+            "PUSH5" => Ok(Self::PUSH5),
+            "PUSH6" => Ok(Self::PUSH6),
+            "PUSH7" => Ok(Self::PUSH7),
+            "PUSH8" => Ok(Self::PUSH8),
+            "PUSH9" => Ok(Self::PUSH9),
+            "PUSH10" => Ok(Self::PUSH10),
+            "PUSH11" => Ok(Self::PUSH11),
+            "PUSH12" => Ok(Self::PUSH12),
+            "PUSH13" => Ok(Self::PUSH13),
+            "PUSH14" => Ok(Self::PUSH14),
+            "PUSH15" => Ok(Self::PUSH15),
+            "PUSH16" => Ok(Self::PUSH16),
+            "PUSH17" => Ok(Self::PUSH17),
+            "PUSH18" => Ok(Self::PUSH18),
+            "PUSH19" => Ok(Self::PUSH19),
+            "PUSH20" => Ok(Self::PUSH20),
+            "PUSH21" => Ok(Self::PUSH21),
+            "PUSH22" => Ok(Self::PUSH22),
+            "PUSH23" => Ok(Self::PUSH23),
+            "PUSH24" => Ok(Self::PUSH24),
+            "PUSH25" => Ok(Self::PUSH25),
+            "PUSH26" => Ok(Self::PUSH26),
+            "PUSH27" => Ok(Self::PUSH27),
+            "PUSH28" => Ok(Self::PUSH28),
+            "PUSH29" => Ok(Self::PUSH29),
+            "PUSH30" => Ok(Self::PUSH30),
+            "PUSH31" => Ok(Self::PUSH31),
+            "PUSH32" => Ok(Self::PUSH32),
+            // PUSH0 pushes a zero and takes no operand
+            "PUSH0" => Ok(Self::PUSH0),
+            "POP" => Ok(Self::POP),
+            "DUP1" => Ok(Self::DUP1),
+            "DUP2" => Ok(Self::DUP2),
+            "DUP3" => Ok(Self::DUP3),
+            "DUP4" => Ok(Self::DUP4),
+            "DUP5" => Ok(Self::DUP5),
+            "DUP6" => Ok(Self::DUP6),
+            "DUP7" => Ok(Self::DUP7),
+            "DUP8" => Ok(Self::DUP8),
+            "DUP9" => Ok(Self::DUP9),
+            "DUP10" => Ok(Self::DUP10),
+            "DUP11" => Ok(Self::DUP11),
+            "DUP12" => Ok(Self::DUP12),
+            "DUP13" => Ok(Self::DUP13),
+            "DUP14" => Ok(Self::DUP14),
+            "DUP15" => Ok(Self::DUP15),
+            "DUP16" => Ok(Self::DUP16),
+            "SWAP1" => Ok(Self::SWAP1),
+            "SWAP2" => Ok(Self::SWAP2),
+            "SWAP3" => Ok(Self::SWAP3),
+            "SWAP4" => Ok(Self::SWAP4),
+            "SWAP5" => Ok(Self::SWAP5),
+            "SWAP6" => Ok(Self::SWAP6),
+            "SWAP7" => Ok(Self::SWAP7),
+            "SWAP8" => Ok(Self::SWAP8),
+            "SWAP9" => Ok(Self::SWAP9),
+            "SWAP10" => Ok(Self::SWAP10),
+            "SWAP11" => Ok(Self::SWAP11),
+            "SWAP12" => Ok(Self::SWAP12),
+            "SWAP13" => Ok(Self::SWAP13),
+            "SWAP14" => Ok(Self::SWAP14),
+            "SWAP15" => Ok(Self::SWAP15),
+            "SWAP16" => Ok(Self::SWAP16),
+            "LOG0" => Ok(Self::LOG0),
+            "LOG1" => Ok(Self::LOG1),
+            "LOG2" => Ok(Self::LOG2),
+            "LOG3" => Ok(Self::LOG3),
+            "LOG4" => Ok(Self::LOG4),
+            "STOP" => Ok(Self::STOP),
+            "ADD" => Ok(Self::ADD),
+            "MUL" => Ok(Self::MUL),
+            "SUB" => Ok(Self::SUB),
+            "DIV" => Ok(Self::DIV),
+            "SDIV" => Ok(Self::SDIV),
+            "MOD" => Ok(Self::MOD),
+            "SMOD" => Ok(Self::SMOD),
+            "ADDMOD" => Ok(Self::ADDMOD),
+            "MULMOD" => Ok(Self::MULMOD),
+            "EXP" => Ok(Self::EXP),
+            "SIGNEXTEND" => Ok(Self::SIGNEXTEND),
+            "LT" => Ok(Self::LT),
+            "GT" => Ok(Self::GT),
+            "SLT" => Ok(Self::SLT),
+            "SGT" => Ok(Self::SGT),
+            "EQ" => Ok(Self::EQ),
+            "ISZERO" => Ok(Self::ISZERO),
+            "AND" => Ok(Self::AND),
+            "OR" => Ok(Self::OR),
+            "XOR" => Ok(Self::XOR),
+            "NOT" => Ok(Self::NOT),
+            "BYTE" => Ok(Self::BYTE),
+            "SHL" => Ok(Self::SHL),
+            "SHR" => Ok(Self::SHR),
+            "SAR" => Ok(Self::SAR),
+            // KECCAK256 is the modern name of SHA3
+            "SHA3" | "KECCAK256" => Ok(Self::SHA3),
+            "ADDRESS" => Ok(Self::ADDRESS),
+            "BALANCE" => Ok(Self::BALANCE),
+            "CALLVALUE" => Ok(Self::CALLVALUE),
+            "CALLDATALOAD" => Ok(Self::CALLDATALOAD),
+            "CALLDATASIZE" => Ok(Self::CALLDATASIZE),
+            "CALLDATACOPY" => Ok(Self::CALLDATACOPY),
+            "RETURNDATASIZE" => Ok(Self::RETURNDATASIZE),
+            "RETURNDATACOPY" => Ok(Self::RETURNDATACOPY),
+            "CALLER" => Ok(Self::CALLER),
+            "ORIGIN" => Ok(Self::ORIGIN),
+            "GASPRICE" => Ok(Self::GASPRICE),
+            "CODESIZE" => Ok(Self::CODESIZE),
+            "CODECOPY" => Ok(Self::CODECOPY),
+            "BLOCKHASH" => Ok(Self::BLOCKHASH),
+            "COINBASE" => Ok(Self::COINBASE),
+            "TIMESTAMP" => Ok(Self::TIMESTAMP),
+            "NUMBER" => Ok(Self::NUMBER),
+            // DIFFICULTY was renamed to PREVRANDAO by the merge, both names are accepted
+            "PREVRANDAO" | "DIFFICULTY" => Ok(Self::PREVRANDAO),
+            "GASLIMIT" => Ok(Self::GASLIMIT),
+            "CHAINID" => Ok(Self::CHAINID),
+            "SELFBALANCE" => Ok(Self::SELFBALANCE),
+            "BASEFEE" => Ok(Self::BASEFEE),
+            "RETURN" => Ok(Self::RETURN),
+            "REVERT" => Ok(Self::REVERT),
+            "INVALID" => Ok(Self::INVALID),
+            // SUICIDE is the legacy name of SELFDESTRUCT
+            "SELFDESTRUCT" | "SUICIDE" => Ok(Self::SELFDESTRUCT),
+            "CALL" => Ok(Self::CALL),
+            "CALLCODE" => Ok(Self::CALLCODE),
+            "DELEGATECALL" => Ok(Self::DELEGATECALL),
+            "STATICCALL" => Ok(Self::STATICCALL),
+
+            upper
+                if upper.strip_prefix("SWAP").is_some_and(|depth| {
+                    !depth.is_empty() && depth.chars().all(|c| c.is_ascii_digit())
+                }) =>
+            {
+                Err(LexError::new(
+                    "invalid swap depth, SWAP only supports depths 1 to 16".to_string(),
+                ))
+            }
+
+            // `0xgt` would otherwise be taken for a label rather than a mistyped literal
+            _ if str.starts_with("0x") && !is_hex(str) => Err(LexError::new(format!(
+                "{} is not a hex literal, 0x is followed by hex digits only",
+                str
+            ))
+            .kind(Kind::UnknownToken(str.to_string()))),
+
+            // A label marks its position with a JUMPDEST
+            _ if str.len() > 1 && str.ends_with(':') && is_identifier(&str[..str.len() - 1]) => {
+                Ok(Self::LABEL(str.trim_end_matches(':').to_string()))
+            }
+
+            _ if is_identifier(str) => Ok(Self::VALUE(str.to_string())),
+
+            _ => Err(LexError::new(
+                "invalid token, expected an opcode, a literal or a label".to_string(),
+            )
+            .kind(Kind::UnknownToken(str.to_string()))),
+        }
+    }
+
+    /// Byte of the opcode, `None` for literals, data and a PUSH without a size
+    pub fn opcode(&self) -> Option<u8> {
+        match self {
+            Self::MSTORE => Some(0x52),
+            Self::MLOAD => Some(0x51),
+            Self::SLOAD => Some(0x54),
+            Self::SSTORE => Some(0x55),
+            Self::TLOAD => Some(0x5c),
+            Self::TSTORE => Some(0x5d),
+            Self::MCOPY => Some(0x5e),
+            Self::JUMP => Some(0x56),
+            Self::JUMPI => Some(0x57),
+            Self::PC => Some(0x58),
+            Self::MSIZE => Some(0x59),
+            Self::GAS => Some(0x5a),
+            Self::JUMPDEST => Some(0x5b),
+            Self::CREATE => Some(0xf0),
+            Self::CREATE2 => Some(0xf5),
+            Self::EXTCODECOPY => Some(0x3c),
+            Self::EXTCODESIZE => Some(0x3b),
+            Self::EXTCODEHASH => Some(0x3f),
+            Self::PUSH1 => Some(0x60),
+            Self::PUSH2 => Some(0x61),
+            Self::PUSH3 => Some(0x62),
+            Self::PUSH4 => Some(0x63),
+            Self::PUSH5 => Some(0x64),
+            Self::PUSH6 => Some(0x65),
+            Self::PUSH7 => Some(0x66),
+            Self::PUSH8 => Some(0x67),
+            Self::PUSH9 => Some(0x68),
+            Self::PUSH10 => Some(0x69),
+            Self::PUSH11 => Some(0x6a),
+            Self::PUSH12 => Some(0x6b),
+            Self::PUSH13 => Some(0x6c),
+            Self::PUSH14 => Some(0x6d),
+            Self::PUSH15 => Some(0x6e),
+            Self::PUSH16 => Some(0x6f),
+            Self::PUSH17 => Some(0x70),
+            Self::PUSH18 => Some(0x71),
+            Self::PUSH19 => Some(0x72),
+            Self::PUSH20 => Some(0x73),
+            Self::PUSH21 => Some(0x74),
+            Self::PUSH22 => Some(0x75),
+            Self::PUSH23 => Some(0x76),
+            Self::PUSH24 => Some(0x77),
+            Self::PUSH25 => Some(0x78),
+            Self::PUSH26 => Some(0x79),
+            Self::PUSH27 => Some(0x7a),
+            Self::PUSH28 => Some(0x7b),
+            Self::PUSH29 => Some(0x7c),
+            Self::PUSH30 => Some(0x7d),
+            Self::PUSH31 => Some(0x7e),
+            Self::PUSH32 => Some(0x7f),
+            Self::PUSH0 => Some(0x5f),
+            Self::POP => Some(0x50),
+            Self::DUP1 => Some(0x80),
+            Self::DUP2 => Some(0x81),
+            Self::DUP3 => Some(0x82),
+            Self::DUP4 => Some(0x83),
+            Self::DUP5 => Some(0x84),
+            Self::DUP6 => Some(0x85),
+            Self::DUP7 => Some(0x86),
+            Self::DUP8 => Some(0x87),
+            Self::DUP9 => Some(0x88),
+            Self::DUP10 => Some(0x89),
+            Self::DUP11 => Some(0x8a),
+            Self::DUP12 => Some(0x8b),
+            Self::DUP13 => Some(0x8c),
+            Self::DUP14 => Some(0x8d),
+            Self::DUP15 => Some(0x8e),
+            Self::DUP16 => Some(0x8f),
+            Self::SWAP1 => Some(0x90),
+            Self::SWAP2 => Some(0x91),
+            Self::SWAP3 => Some(0x92),
+            Self::SWAP4 => Some(0x93),
+            Self::SWAP5 => Some(0x94),
+            Self::SWAP6 => Some(0x95),
+            Self::SWAP7 => Some(0x96),
+            Self::SWAP8 => Some(0x97),
+            Self::SWAP9 => Some(0x98),
+            Self::SWAP10 => Some(0x99),
+            Self::SWAP11 => Some(0x9a),
+            Self::SWAP12 => Some(0x9b),
+            Self::SWAP13 => Some(0x9c),
+            Self::SWAP14 => Some(0x9d),
+            Self::SWAP15 => Some(0x9e),
+            Self::SWAP16 => Some(0x9f),
+            Self::LOG0 => Some(0xa0),
+            Self::LOG1 => Some(0xa1),
+            Self::LOG2 => Some(0xa2),
+            Self::LOG3 => Some(0xa3),
+            Self::LOG4 => Some(0xa4),
+            Self::STOP => Some(0x00),
+            Self::ADD => Some(0x01),
+            Self::MUL => Some(0x02),
+            Self::SUB => Some(0x03),
+            Self::DIV => Some(0x04),
+            Self::SDIV => Some(0x05),
+            Self::MOD => Some(0x06),
+            Self::SMOD => Some(0x07),
+            Self::ADDMOD => Some(0x08),
+            Self::MULMOD => Some(0x09),
+            Self::EXP => Some(0x0a),
+            Self::SIGNEXTEND => Some(0x0b),
+            Self::LT => Some(0x10),
+            Self::GT => Some(0x11),
+            Self::SLT => Some(0x12),
+            Self::SGT => Some(0x13),
+            Self::EQ => Some(0x14),
+            Self::SHA3 => Some(0x20),
+            Self::CALLVALUE => Some(0x34),
+            Self::CALLDATALOAD => Some(0x35),
+            Self::CALLDATASIZE => Some(0x36),
+            Self::CALLDATACOPY => Some(0x37),
+            Self::RETURNDATASIZE => Some(0x3d),
+            Self::RETURNDATACOPY => Some(0x3e),
+            Self::CALLER => Some(0x33),
+            Self::ORIGIN => Some(0x32),
+            Self::GASPRICE => Some(0x3a),
+            Self::CODESIZE => Some(0x38),
+            Self::CODECOPY => Some(0x39),
+            Self::BLOCKHASH => Some(0x40),
+            Self::COINBASE => Some(0x41),
+            Self::TIMESTAMP => Some(0x42),
+            Self::NUMBER => Some(0x43),
+            Self::PREVRANDAO => Some(0x44),
+            Self::GASLIMIT => Some(0x45),
+            Self::CHAINID => Some(0x46),
+            Self::SELFBALANCE => Some(0x47),
+            Self::BASEFEE => Some(0x48),
+            Self::RETURN => Some(0xf3),
+            Self::REVERT => Some(0xfd),
+            Self::INVALID => Some(0xfe),
+            Self::SELFDESTRUCT => Some(0xff),
+            Self::CALL => Some(0xf1),
+            Self::CALLCODE => Some(0xf2),
+            Self::DELEGATECALL => Some(0xf4),
+            Self::STATICCALL => Some(0xfa),
+            Self::ISZERO => Some(0x15),
+            Self::AND => Some(0x16),
+            Self::OR => Some(0x17),
+            Self::XOR => Some(0x18),
+            Self::NOT => Some(0x19),
+            Self::BYTE => Some(0x1a),
+            Self::SHL => Some(0x1b),
+            Self::SHR => Some(0x1c),
+            Self::SAR => Some(0x1d),
+            Self::ADDRESS => Some(0x30),
+            Self::BALANCE => Some(0x31),
+            // A label marks its position with a JUMPDEST
+            Self::LABEL(_) => Some(0x5b),
+            Self::CUSTOM(_, byte) => Some(*byte),
+            Self::PUSH | Self::VALUE(_) | Self::DATA(_) | Self::ORG(_) | Self::ALIGN(..) => None,
+        }
+    }
+
+    /// Returns the operand size in bytes if this is a PUSH opcode, 0 for PUSH0
+    pub fn is_push(&self) -> Option<u8> {
+        match self {
+            Self::PUSH0 => Some(0),
+            Self::PUSH1 => Some(1),
+            Self::PUSH2 => Some(2),
+            Self::PUSH3 => Some(3),
+            Self::PUSH4 => Some(4),
+            Self::PUSH5 => Some(5),
+            Self::PUSH6 => Some(6),
+            Self::PUSH7 => Some(7),
+            Self::PUSH8 => Some(8),
+            Self::PUSH9 => Some(9),
+            Self::PUSH10 => Some(10),
+            Self::PUSH11 => Some(11),
+            Self::PUSH12 => Some(12),
+            Self::PUSH13 => Some(13),
+            Self::PUSH14 => Some(14),
+            Self::PUSH15 => Some(15),
+            Self::PUSH16 => Some(16),
+            Self::PUSH17 => Some(17),
+            Self::PUSH18 => Some(18),
+            Self::PUSH19 => Some(19),
+            Self::PUSH20 => Some(20),
+            Self::PUSH21 => Some(21),
+            Self::PUSH22 => Some(22),
+            Self::PUSH23 => Some(23),
+            Self::PUSH24 => Some(24),
+            Self::PUSH25 => Some(25),
+            Self::PUSH26 => Some(26),
+            Self::PUSH27 => Some(27),
+            Self::PUSH28 => Some(28),
+            Self::PUSH29 => Some(29),
+            Self::PUSH30 => Some(30),
+            Self::PUSH31 => Some(31),
+            Self::PUSH32 => Some(32),
+            _ => None,
+        }
+    }
+
+    /// Returns the fixed part of the gas cost, see `has_dynamic_gas` for opcodes
+    /// that cost more depending on their arguments or state
+    pub fn base_gas(&self) -> u64 {
+        match self {
+            Self::STOP
+            | Self::RETURN
+            | Self::VALUE(_)
+            | Self::DATA(_)
+            | Self::ORG(_)
+            | Self::ALIGN(..)
+            | Self::REVERT
+            | Self::INVALID
+            | Self::CUSTOM(..) => 0,
+            Self::JUMPDEST | Self::LABEL(_) => 1,
+            Self::PC
+            | Self::MSIZE
+            | Self::GAS
+            | Self::POP
+            | Self::ADDRESS
+            | Self::CALLVALUE
+            | Self::CALLER
+            | Self::ORIGIN
+            | Self::GASPRICE
+            | Self::CODESIZE
+            | Self::COINBASE
+            | Self::TIMESTAMP
+            | Self::NUMBER
+            | Self::PREVRANDAO
+            | Self::GASLIMIT
+            | Self::CALLDATASIZE
+            | Self::RETURNDATASIZE
+            | Self::PUSH0
+            | Self::CHAINID
+            | Self::BASEFEE => 2,
+            Self::MSTORE
+            | Self::MLOAD
+            | Self::ADD
+            | Self::SUB
+            | Self::LT
+            | Self::GT
+            | Self::SLT
+            | Self::SGT
+            | Self::EQ
+            | Self::ISZERO
+            | Self::AND
+            | Self::OR
+            | Self::XOR
+            | Self::NOT
+            | Self::BYTE
+            | Self::SHL
+            | Self::SHR
+            | Self::SAR
+            | Self::PUSH
+            | Self::PUSH1
+            | Self::PUSH2
+            | Self::PUSH3
+            | Self::PUSH4
+            | Self::PUSH5
+            | Self::PUSH6
+            | Self::PUSH7
+            | Self::PUSH8
+            | Self::PUSH9
+            | Self::PUSH10
+            | Self::PUSH11
+            | Self::PUSH12
+            | Self::PUSH13
+            | Self::PUSH14
+            | Self::PUSH15
+            | Self::PUSH16
+            | Self::PUSH17
+            | Self::PUSH18
+            | Self::PUSH19
+            | Self::PUSH20
+            | Self::PUSH21
+            | Self::PUSH22
+            | Self::PUSH23
+            | Self::PUSH24
+            | Self::PUSH25
+            | Self::PUSH26
+            | Self::PUSH27
+            | Self::PUSH28
+            | Self::PUSH29
+            | Self::PUSH30
+            | Self::PUSH31
+            | Self::PUSH32
+            | Self::DUP1
+            | Self::DUP2
+            | Self::DUP3
+            | Self::DUP4
+            | Self::DUP5
+            | Self::DUP6
+            | Self::DUP7
+            | Self::DUP8
+            | Self::DUP9
+            | Self::DUP10
+            | Self::DUP11
+            | Self::DUP12
+            | Self::DUP13
+            | Self::DUP14
+            | Self::DUP15
+            | Self::DUP16
+            | Self::SWAP1
+            | Self::SWAP2
+            | Self::SWAP3
+            | Self::SWAP4
+            | Self::SWAP5
+            | Self::SWAP6
+            | Self::SWAP7
+            | Self::SWAP8
+            | Self::SWAP9
+            | Self::SWAP10
+            | Self::SWAP11
+            | Self::SWAP12
+            | Self::SWAP13
+            | Self::SWAP14
+            | Self::SWAP15
+            | Self::SWAP16
+            | Self::CALLDATALOAD
+            | Self::CALLDATACOPY
+            | Self::CODECOPY
+            | Self::RETURNDATACOPY
+            | Self::MCOPY => 3,
+            Self::MUL
+            | Self::DIV
+            | Self::SDIV
+            | Self::MOD
+            | Self::SMOD
+            | Self::SIGNEXTEND
+            | Self::SELFBALANCE => 5,
+            Self::ADDMOD | Self::MULMOD | Self::JUMP => 8,
+            Self::EXP | Self::JUMPI => 10,
+            Self::SHA3 => 30,
+            Self::BALANCE
+            | Self::EXTCODECOPY
+            | Self::CALL
+            | Self::CALLCODE
+            | Self::DELEGATECALL
+            | Self::STATICCALL
+            | Self::SLOAD
+            | Self::SSTORE
+            | Self::TLOAD
+            | Self::TSTORE
+            | Self::EXTCODESIZE
+            | Self::EXTCODEHASH => 100,
+            Self::LOG0 => 375,
+            Self::LOG1 => 750,
+            Self::LOG2 => 1125,
+            Self::LOG3 => 1500,
+            Self::LOG4 => 1875,
+            Self::CREATE | Self::CREATE2 => 32000,
+            Self::SELFDESTRUCT => 5000,
+            Self::BLOCKHASH => 20,
+        }
+    }
+
+    /// Does the gas cost depend on the arguments or the state (beyond memory expansion)
+    pub fn has_dynamic_gas(&self) -> bool {
+        matches!(
+            self,
+            Self::EXP
+                | Self::SHA3
+                | Self::BALANCE
+                | Self::CUSTOM(..)
+                | Self::EXTCODECOPY
+                | Self::CREATE
+                | Self::LOG0
+                | Self::LOG1
+                | Self::LOG2
+                | Self::LOG3
+                | Self::LOG4
+                | Self::CALL
+                | Self::CALLCODE
+                | Self::DELEGATECALL
+                | Self::STATICCALL
+                | Self::SLOAD
+                | Self::SSTORE
+                | Self::CALLDATACOPY
+                | Self::CODECOPY
+                | Self::RETURNDATACOPY
+                | Self::SELFDESTRUCT
+                | Self::CREATE2
+                | Self::MCOPY
+                | Self::EXTCODESIZE
+                | Self::EXTCODEHASH
+        )
+    }
+
+    /// Returns how many items the opcode pops from and pushes to the stack
+    pub fn stack_io(&self) -> (u8, u8) {
+        match self {
+            Self::STOP
+            | Self::JUMPDEST
+            | Self::VALUE(_)
+            | Self::LABEL(_)
+            | Self::DATA(_)
+            | Self::ORG(_)
+            | Self::ALIGN(..)
+            | Self::INVALID => (0, 0),
+            // Nothing is known of the stack of a custom opcode
+            Self::CUSTOM(..) => (0, 0),
+            Self::PC
+            | Self::MSIZE
+            | Self::GAS
+            | Self::ADDRESS
+            | Self::CALLVALUE
+            | Self::PUSH
+            | Self::PUSH1
+            | Self::PUSH2
+            | Self::PUSH3
+            | Self::PUSH4
+            | Self::PUSH5
+            | Self::PUSH6
+            | Self::PUSH7
+            | Self::PUSH8
+            | Self::PUSH9
+            | Self::PUSH10
+            | Self::PUSH11
+            | Self::PUSH12
+            | Self::PUSH13
+            | Self::PUSH14
+            | Self::PUSH15
+            | Self::PUSH16
+            | Self::PUSH17
+            | Self::PUSH18
+            | Self::PUSH19
+            | Self::PUSH20
+            | Self::PUSH21
+            | Self::PUSH22
+            | Self::PUSH23
+            | Self::PUSH24
+            | Self::PUSH25
+            | Self::PUSH26
+            | Self::PUSH27
+            | Self::PUSH28
+            | Self::PUSH29
+            | Self::PUSH30
+            | Self::PUSH31
+            | Self::PUSH32
+            | Self::CALLER
+            | Self::ORIGIN
+            | Self::GASPRICE
+            | Self::CODESIZE
+            | Self::COINBASE
+            | Self::TIMESTAMP
+            | Self::NUMBER
+            | Self::PREVRANDAO
+            | Self::GASLIMIT
+            | Self::CALLDATASIZE
+            | Self::RETURNDATASIZE
+            | Self::PUSH0
+            | Self::CHAINID
+            | Self::SELFBALANCE
+            | Self::BASEFEE => (0, 1),
+            Self::POP | Self::JUMP | Self::SELFDESTRUCT => (1, 0),
+            Self::MLOAD
+            | Self::ISZERO
+            | Self::NOT
+            | Self::BALANCE
+            | Self::SLOAD
+            | Self::CALLDATALOAD
+            | Self::TLOAD
+            | Self::EXTCODESIZE
+            | Self::EXTCODEHASH
+            | Self::BLOCKHASH => (1, 1),
+            Self::MSTORE
+            | Self::JUMPI
+            | Self::RETURN
+            | Self::SSTORE
+            | Self::REVERT
+            | Self::TSTORE => (2, 0),
+            Self::ADD
+            | Self::MUL
+            | Self::SUB
+            | Self::DIV
+            | Self::SDIV
+            | Self::MOD
+            | Self::SMOD
+            | Self::EXP
+            | Self::SIGNEXTEND
+            | Self::LT
+            | Self::GT
+            | Self::SLT
+            | Self::SGT
+            | Self::EQ
+            | Self::AND
+            | Self::OR
+            | Self::XOR
+            | Self::BYTE
+            | Self::SHL
+            | Self::SHR
+            | Self::SAR
+            | Self::SHA3 => (2, 1),
+            Self::ADDMOD | Self::MULMOD | Self::CREATE => (3, 1),
+            Self::EXTCODECOPY => (4, 0),
+            Self::DUP1 => (1, 2),
+            Self::DUP2 => (2, 3),
+            Self::DUP3 => (3, 4),
+            Self::DUP4 => (4, 5),
+            Self::DUP5 => (5, 6),
+            Self::DUP6 => (6, 7),
+            Self::DUP7 => (7, 8),
+            Self::DUP8 => (8, 9),
+            Self::DUP9 => (9, 10),
+            Self::DUP10 => (10, 11),
+            Self::DUP11 => (11, 12),
+            Self::DUP12 => (12, 13),
+            Self::DUP13 => (13, 14),
+            Self::DUP14 => (14, 15),
+            Self::DUP15 => (15, 16),
+            Self::DUP16 => (16, 17),
+            Self::SWAP1 => (2, 2),
+            Self::SWAP2 => (3, 3),
+            Self::SWAP3 => (4, 4),
+            Self::SWAP4 => (5, 5),
+            Self::SWAP5 => (6, 6),
+            Self::SWAP6 => (7, 7),
+            Self::SWAP7 => (8, 8),
+            Self::SWAP8 => (9, 9),
+            Self::SWAP9 => (10, 10),
+            Self::SWAP10 => (11, 11),
+            Self::SWAP11 => (12, 12),
+            Self::SWAP12 => (13, 13),
+            Self::SWAP13 => (14, 14),
+            Self::SWAP14 => (15, 15),
+            Self::SWAP15 => (16, 16),
+            Self::SWAP16 => (17, 17),
+            Self::LOG0 => (2, 0),
+            Self::LOG1
+            | Self::CALLDATACOPY
+            | Self::CODECOPY
+            | Self::RETURNDATACOPY
+            | Self::MCOPY => (3, 0),
+            Self::LOG2 => (4, 0),
+            Self::LOG3 => (5, 0),
+            Self::LOG4 => (6, 0),
+            Self::CALL | Self::CALLCODE => (7, 1),
+            Self::DELEGATECALL | Self::STATICCALL => (6, 1),
+            Self::CREATE2 => (4, 1),
+        }
+    }
+
+    /// Widest operand of a PUSH, the EVM has nothing wider than PUSH32
+    pub(crate) const MAX_PUSH_SIZE: usize = 32;
+
+    /// Returns the PUSH opcode that has an operand of `size` bytes
+    pub fn push_of_size(size: usize) -> Option<(Self, &'static str)> {
+        MNEMONICS
+            .iter()
+            .filter_map(|mnemonic| Self::from_str(mnemonic).ok())
+            .find(|(op, _)| op.is_push().map(usize::from) == Some(size))
+    }
+
+    /// Fork that added the opcode to the EVM
+    pub fn introduced_in(&self) -> Fork {
+        match self {
+            Self::DELEGATECALL => Fork::Homestead,
+
+            Self::REVERT | Self::RETURNDATASIZE | Self::RETURNDATACOPY | Self::STATICCALL => {
+                Fork::Byzantium
+            }
+
+            Self::SHL | Self::SHR | Self::SAR | Self::CREATE2 | Self::EXTCODEHASH => {
+                Fork::Constantinople
+            }
+
+            Self::CHAINID | Self::SELFBALANCE => Fork::Istanbul,
+            Self::BASEFEE => Fork::London,
+            Self::PUSH0 => Fork::Shanghai,
+            Self::TLOAD | Self::TSTORE | Self::MCOPY => Fork::Cancun,
+            _ => Fork::Frontier,
+        }
+    }
+
+    /// Find the opcode of a byte, the inverse of `from_str`, `None` for undefined opcodes
+    #[cfg(feature = "std")]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        static OPS: OnceLock<Vec<Option<Op>>> = OnceLock::new();
+
+        let ops = OPS.get_or_init(|| {
+            let mut ops = vec![None; 256];
+            for mnemonic in MNEMONICS {
+                if let Ok(op) = Self::parse_token(mnemonic) {
+                    if let Some(byte) = op.opcode() {
+                        ops[byte as usize] = Some(op);
+                    }
+                }
+            }
+
+            ops
+        });
+
+        ops[byte as usize].clone()
+    }
+
+    /// Find the opcode of a byte, without std there is no table to look it up in
+    #[cfg(not(feature = "std"))]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        MNEMONICS
+            .iter()
+            .filter_map(|mnemonic| Self::parse_token(mnemonic).ok())
+            .find(|op| op.opcode() == Some(byte))
+    }
+
+    /// Is the opcode deprecated, SELFDESTRUCT by EIP-6049 and CALLCODE by DELEGATECALL
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self, Self::SELFDESTRUCT | Self::CALLCODE)
+    }
+
+    /// What the opcode does, like `pops a, b; pushes (a + b) mod 2^256` for ADD. Literals,
+    /// directives and custom opcodes have no description
+    pub fn describe(&self) -> &'static str {
+        if self.is_push().is_some_and(|size| size > 0) {
+            return "pushes the operand that follows it";
+        }
+
+        if self.is_dup().is_some() {
+            return "pushes a copy of the item at the depth of its number";
+        }
+
+        if self.is_swap().is_some() {
+            return "exchanges the top item with the item below it at the depth of its number";
+        }
+
+        match self {
+            Self::STOP => "halts execution",
+            Self::ADD => "pops a, b; pushes (a + b) mod 2^256",
+            Self::MUL => "pops a, b; pushes (a * b) mod 2^256",
+            Self::SUB => "pops a, b; pushes (a - b) mod 2^256",
+            Self::DIV => "pops a, b; pushes a / b, 0 when b is 0",
+            Self::SDIV => "pops a, b; pushes a / b as signed values, 0 when b is 0",
+            Self::MOD => "pops a, b; pushes a mod b, 0 when b is 0",
+            Self::SMOD => "pops a, b; pushes a mod b as signed values, 0 when b is 0",
+            Self::ADDMOD => "pops a, b, n; pushes (a + b) mod n, 0 when n is 0",
+            Self::MULMOD => "pops a, b, n; pushes (a * b) mod n, 0 when n is 0",
+            Self::EXP => "pops a, b; pushes a^b mod 2^256",
+            Self::SIGNEXTEND => "pops b, x; pushes x sign extended from its byte b",
+            Self::LT => "pops a, b; pushes 1 if a < b else 0",
+            Self::GT => "pops a, b; pushes 1 if a > b else 0",
+            Self::SLT => "pops a, b; pushes 1 if a < b as signed values else 0",
+            Self::SGT => "pops a, b; pushes 1 if a > b as signed values else 0",
+            Self::EQ => "pops a, b; pushes 1 if a == b else 0",
+            Self::ISZERO => "pops a; pushes 1 if a == 0 else 0",
+            Self::AND => "pops a, b; pushes a & b",
+            Self::OR => "pops a, b; pushes a | b",
+            Self::XOR => "pops a, b; pushes a ^ b",
+            Self::NOT => "pops a; pushes ~a",
+            Self::BYTE => "pops i, x; pushes byte i of x, counted from the most significant",
+            Self::SHL => "pops shift, value; pushes value << shift",
+            Self::SHR => "pops shift, value; pushes value >> shift",
+            Self::SAR => "pops shift, value; pushes value >> shift keeping its sign",
+            Self::SHA3 => "pops offset, size; pushes keccak256(memory[offset:offset+size])",
+            Self::ADDRESS => "pushes the address of the executing contract",
+            Self::BALANCE => "pops address; pushes its balance in wei",
+            Self::ORIGIN => "pushes the address that sent the transaction",
+            Self::CALLER => "pushes the address of the caller",
+            Self::CALLVALUE => "pushes the wei sent with the call",
+            Self::CALLDATALOAD => "pops i; pushes the 32 bytes of calldata at i",
+            Self::CALLDATASIZE => "pushes the size of the calldata in bytes",
+            Self::CALLDATACOPY => "pops destOffset, offset, size; copies calldata to memory",
+            Self::CODESIZE => "pushes the size of the executing code in bytes",
+            Self::CODECOPY => "pops destOffset, offset, size; copies the executing code to memory",
+            Self::GASPRICE => "pushes the gas price of the transaction",
+            Self::EXTCODESIZE => "pops address; pushes the size of its code",
+            Self::EXTCODECOPY => "pops address, destOffset, offset, size; copies its code to memory",
+            Self::RETURNDATASIZE => "pushes the size of the data the last call returned",
+            Self::RETURNDATACOPY => "pops destOffset, offset, size; copies the data the last call returned to memory",
+            Self::EXTCODEHASH => "pops address; pushes the keccak256 of its code",
+            Self::BLOCKHASH => "pops number; pushes the hash of that block, one of the last 256",
+            Self::COINBASE => "pushes the address of the block proposer",
+            Self::TIMESTAMP => "pushes the timestamp of the block",
+            Self::NUMBER => "pushes the number of the block",
+            Self::PREVRANDAO => "pushes the randomness of the beacon chain, the difficulty before the merge",
+            Self::GASLIMIT => "pushes the gas limit of the block",
+            Self::CHAINID => "pushes the chain id",
+            Self::SELFBALANCE => "pushes the balance of the executing contract",
+            Self::BASEFEE => "pushes the base fee of the block",
+            Self::POP => "pops a and drops it",
+            Self::MLOAD => "pops offset; pushes memory[offset:offset+32]",
+            Self::MSTORE => "pops offset, value; writes the 32 bytes of value to memory at offset",
+            Self::SLOAD => "pops key; pushes storage[key]",
+            Self::SSTORE => "pops key, value; writes storage[key] = value",
+            Self::JUMP => "pops counter; jumps to it, it must be a JUMPDEST",
+            Self::JUMPI => "pops counter, b; jumps to counter if b != 0",
+            Self::PC => "pushes the offset of this instruction",
+            Self::MSIZE => "pushes the size of the memory in bytes",
+            Self::GAS => "pushes the gas left, after this instruction",
+            Self::JUMPDEST => "marks a valid jump destination, does nothing",
+            Self::TLOAD => "pops key; pushes transient storage[key]",
+            Self::TSTORE => "pops key, value; writes transient storage[key] = value",
+            Self::MCOPY => "pops destOffset, offset, size; copies memory within memory",
+            Self::PUSH0 => "pushes 0",
+            Self::CREATE => "pops value, offset, size; pushes the address of a contract created from memory",
+            Self::CALL => "pops gas, address, value, argsOffset, argsSize, retOffset, retSize; pushes 1 on success else 0",
+            Self::CALLCODE => "pops gas, address, value, argsOffset, argsSize, retOffset, retSize; calls the code in this context, deprecated",
+            Self::RETURN => "pops offset, size; halts returning memory[offset:offset+size]",
+            Self::DELEGATECALL => "pops gas, address, argsOffset, argsSize, retOffset, retSize; calls the code with this caller and value",
+            Self::CREATE2 => "pops value, offset, size, salt; pushes the address of a contract at a salted address",
+            Self::STATICCALL => "pops gas, address, argsOffset, argsSize, retOffset, retSize; calls without changing state",
+            Self::REVERT => "pops offset, size; halts reverting with memory[offset:offset+size]",
+            Self::INVALID => "halts with an exceptional halt that uses all the gas",
+            Self::SELFDESTRUCT => "pops address; sends it the balance and ends the contract, deprecated",
+            Self::LOG0 | Self::LOG1 | Self::LOG2 | Self::LOG3 | Self::LOG4 => {
+                "pops offset, size and a topic for each of its number; logs memory[offset:offset+size]"
+            }
+            Self::PUSH => "pushes the operand that follows it, as wide as the operand needs",
+            Self::LABEL(_) => "marks a jump destination with this name",
+            _ => "",
+        }
+    }
+
+    /// Does the opcode end execution, code after it only runs when jumped to
+    pub fn is_terminating(&self) -> bool {
+        matches!(
+            self,
+            Self::STOP | Self::RETURN | Self::REVERT | Self::INVALID | Self::SELFDESTRUCT
+        )
+    }
+
+    /// Returns the stack depth duplicated if this is a DUP opcode
+    pub fn is_dup(&self) -> Option<u8> {
+        match self {
+            Self::DUP1 => Some(1),
+            Self::DUP2 => Some(2),
+            Self::DUP3 => Some(3),
+            Self::DUP4 => Some(4),
+            Self::DUP5 => Some(5),
+            Self::DUP6 => Some(6),
+            Self::DUP7 => Some(7),
+            Self::DUP8 => Some(8),
+            Self::DUP9 => Some(9),
+            Self::DUP10 => Some(10),
+            Self::DUP11 => Some(11),
+            Self::DUP12 => Some(12),
+            Self::DUP13 => Some(13),
+            Self::DUP14 => Some(14),
+            Self::DUP15 => Some(15),
+            Self::DUP16 => Some(16),
+            _ => None,
+        }
+    }
+
+    /// Returns the stack depth exchanged with the top if this is a SWAP opcode
+    pub fn is_swap(&self) -> Option<u8> {
+        match self {
+            Self::SWAP1 => Some(1),
+            Self::SWAP2 => Some(2),
+            Self::SWAP3 => Some(3),
+            Self::SWAP4 => Some(4),
+            Self::SWAP5 => Some(5),
+            Self::SWAP6 => Some(6),
+            Self::SWAP7 => Some(7),
+            Self::SWAP8 => Some(8),
+            Self::SWAP9 => Some(9),
+            Self::SWAP10 => Some(10),
+            Self::SWAP11 => Some(11),
+            Self::SWAP12 => Some(12),
+            Self::SWAP13 => Some(13),
+            Self::SWAP14 => Some(14),
+            Self::SWAP15 => Some(15),
+            Self::SWAP16 => Some(16),
+            _ => None,
+        }
+    }
+}
+
+/// Render the uppercase mnemonic, literals as written and labels as `name:`
+impl Display for Op {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::VALUE(value) => write!(f, "{}", value),
+            Self::LABEL(name) => write!(f, "{}:", name),
+            Self::DATA(bytes) => write!(f, ".data 0x{}", to_hex(bytes)),
+            Self::ORG(offset) => write!(f, ".org 0x{:x}", offset),
+            Self::ALIGN(boundary, pad) => write!(f, ".align {} 0x{:02x}", boundary, pad),
+            Self::CUSTOM(name, _) => write!(f, "{}", name),
+
+            // The other variants are named after their mnemonic
+            op => write!(f, "{:?}", op),
+        }
+    }
+}
+
+/// Parse a mnemonic like `add` or `PUSH1`, literals and labels are an error
+impl FromStr for Op {
+    type Err = LexError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match Op::parse_token(str)? {
+            Op::VALUE(_) | Op::LABEL(_) => {
+                Err(LexError::new(format!("{} is not an opcode mnemonic", str)))
+            }
+
+            op => Ok(op),
+        }
+    }
+}
+
+/// Lowercase hex of a byte, like `"5b"`
+pub(crate) fn hex_byte(byte: u8) -> &'static str {
+    static HEX: [u8; 512] = {
+        let digits = b"0123456789abcdef";
+        let mut hex = [0; 512];
+
+        let mut byte = 0;
+        while byte < 256 {
+            hex[2 * byte] = digits[byte >> 4];
+            hex[2 * byte + 1] = digits[byte & 0xf];
+            byte += 1;
+        }
+
+        hex
+    };
+
+    let start = 2 * byte as usize;
+    core::str::from_utf8(&HEX[start..start + 2]).expect("hex digits are ASCII")
+}
+
+/// Is the value a hex literal (with an optional 0x prefix)
+pub(crate) fn is_hex(value: &str) -> bool {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+
+    !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Can the value be a literal or a label name
+pub(crate) fn is_identifier(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_opcodes() {
+        let logs = [
+            ("log0", "a0"),
+            ("log1", "a1"),
+            ("log2", "a2"),
+            ("LOG3", "a3"),
+            ("LOG4", "a4"),
+        ];
+
+        for (mnemonic, byte) in logs {
+            assert_eq!(Op::from_str(mnemonic).unwrap().1, byte);
+        }
+    }
+
+    #[test]
+    fn lt_opcode() {
+        assert_eq!(Op::from_str("lt").unwrap().1, "10");
+    }
+    #[test]
+    fn call_opcodes() {
+        let calls = [
+            ("call", "f1"),
+            ("callcode", "f2"),
+            ("DELEGATECALL", "f4"),
+            ("staticcall", "fa"),
+        ];
+
+        for (mnemonic, byte) in calls {
+            assert_eq!(Op::from_str(mnemonic).unwrap().1, byte);
+        }
+
+        assert_eq!(Op::STATICCALL.stack_io(), (6, 1));
+    }
+    #[test]
+    fn environment_opcodes() {
+        assert_eq!(Op::from_str("caller").unwrap(), (Op::CALLER, "33"));
+        assert_eq!(Op::from_str("ORIGIN").unwrap(), (Op::ORIGIN, "32"));
+        assert_eq!(Op::from_str("gasprice").unwrap(), (Op::GASPRICE, "3a"));
+        assert_eq!(Op::from_str("codesize").unwrap(), (Op::CODESIZE, "38"));
+        assert_eq!(Op::from_str("codecopy").unwrap(), (Op::CODECOPY, "39"));
+    }
+    #[test]
+    fn prevrandao_and_difficulty_alias() {
+        assert_eq!(Op::from_str("prevrandao").unwrap(), (Op::PREVRANDAO, "44"));
+        assert_eq!(Op::from_str("DIFFICULTY").unwrap(), (Op::PREVRANDAO, "44"));
+    }
+    #[test]
+    fn return_data_opcodes() {
+        assert_eq!(Op::from_str("returndatasize").unwrap().1, "3d");
+        assert_eq!(Op::from_str("returndatacopy").unwrap().1, "3e");
+    }
+    #[test]
+    fn termination_opcodes() {
+        assert_eq!(Op::from_str("revert").unwrap(), (Op::REVERT, "fd"));
+        assert_eq!(Op::from_str("invalid").unwrap(), (Op::INVALID, "fe"));
+        assert_eq!(
+            Op::from_str("selfdestruct").unwrap(),
+            (Op::SELFDESTRUCT, "ff")
+        );
+        assert_eq!(Op::from_str("suicide").unwrap(), (Op::SELFDESTRUCT, "ff"));
+    }
+    #[test]
+    fn create_opcodes() {
+        assert_eq!(Op::from_str("create").unwrap(), (Op::CREATE, "f0"));
+        assert_eq!(Op::from_str("create2").unwrap(), (Op::CREATE2, "f5"));
+        assert_eq!(Op::from_str("CREATE2").unwrap(), (Op::CREATE2, "f5"));
+    }
+    #[test]
+    fn transient_storage_opcodes() {
+        assert_eq!(Op::from_str("tload").unwrap(), (Op::TLOAD, "5c"));
+        assert_eq!(Op::from_str("tstore").unwrap(), (Op::TSTORE, "5d"));
+    }
+    #[test]
+    fn mcopy_opcode() {
+        assert_eq!(Op::from_str("mcopy").unwrap(), (Op::MCOPY, "5e"));
+    }
+    #[test]
+    fn chain_opcodes() {
+        assert_eq!(Op::from_str("chainid").unwrap(), (Op::CHAINID, "46"));
+        assert_eq!(
+            Op::from_str("selfbalance").unwrap(),
+            (Op::SELFBALANCE, "47")
+        );
+        assert_eq!(Op::from_str("basefee").unwrap(), (Op::BASEFEE, "48"));
+    }
+    #[test]
+    fn external_code_opcodes() {
+        assert_eq!(
+            Op::from_str("extcodesize").unwrap(),
+            (Op::EXTCODESIZE, "3b")
+        );
+        assert_eq!(
+            Op::from_str("extcodehash").unwrap(),
+            (Op::EXTCODEHASH, "3f")
+        );
+        assert_eq!(
+            Op::from_str("extcodecopy").unwrap(),
+            (Op::EXTCODECOPY, "3c")
+        );
+    }
+    #[test]
+    fn blockhash_opcode() {
+        assert_eq!(Op::from_str("blockhash").unwrap(), (Op::BLOCKHASH, "40"));
+        assert_eq!(Op::BLOCKHASH.stack_io(), (1, 1));
+    }
+    #[test]
+    fn keccak256_alias() {
+        assert_eq!(Op::from_str("sha3").unwrap(), (Op::SHA3, "20"));
+        assert_eq!(Op::from_str("keccak256").unwrap(), (Op::SHA3, "20"));
+        assert_eq!(Op::from_str("KECCAK256").unwrap(), (Op::SHA3, "20"));
+    }
+    #[test]
+    fn parse_mnemonics() {
+        assert_eq!("add".parse::<Op>().unwrap(), Op::ADD);
+        assert_eq!("PUSH1".parse::<Op>().unwrap(), Op::PUSH1);
+        assert!("foo".parse::<Op>().is_err());
+        assert!("start:".parse::<Op>().is_err());
+    }
+    #[test]
+    fn display_mnemonics() {
+        assert_eq!(format!("{}", Op::ADD), "ADD");
+        assert_eq!(Op::PUSH1.to_string(), "PUSH1");
+        assert_eq!(Op::VALUE("0x80".to_string()).to_string(), "0x80");
+        assert_eq!(Op::LABEL("start".to_string()).to_string(), "start:");
+    }
+    #[test]
+    fn from_byte_inverts_from_str() {
+        assert_eq!(Op::from_byte(0x01), Some(Op::ADD));
+        assert_eq!(Op::from_byte(0x60), Some(Op::PUSH1));
+        assert_eq!(Op::from_byte(0x0c), None);
+
+        for mnemonic in MNEMONICS {
+            let (op, byte) = Op::from_str(mnemonic).unwrap();
+            assert_eq!(
+                Op::from_byte(u8::from_str_radix(byte, 16).unwrap()),
+                Some(op)
+            );
+        }
+    }
+    #[test]
+    fn opcode_bytes() {
+        assert_eq!(Op::MSTORE.opcode(), Some(0x52));
+        assert_eq!(Op::LABEL("start".to_string()).opcode(), Some(0x5b));
+        assert_eq!(Op::VALUE("0x01".to_string()).opcode(), None);
+        assert_eq!(Op::PUSH.opcode(), None);
+        assert_eq!(Op::from_str("mstore").unwrap().1, "52");
+    }
+    #[test]
+    fn stack_io_sample() {
+        let sample = [
+            (Op::ADD, (2, 1)),
+            (Op::MSTORE, (2, 0)),
+            (Op::PUSH1, (0, 1)),
+            (Op::PUSH0, (0, 1)),
+            (Op::DUP1, (1, 2)),
+            (Op::DUP16, (16, 17)),
+            (Op::SWAP1, (2, 2)),
+            (Op::SWAP16, (17, 17)),
+            (Op::ADDMOD, (3, 1)),
+            (Op::CALL, (7, 1)),
+            (Op::STATICCALL, (6, 1)),
+            (Op::CREATE2, (4, 1)),
+            (Op::LOG4, (6, 0)),
+            (Op::EXTCODECOPY, (4, 0)),
+            (Op::JUMPI, (2, 0)),
+            (Op::STOP, (0, 0)),
+        ];
+
+        for (op, io) in sample {
+            assert_eq!(op.stack_io(), io, "{}", op);
+        }
+    }
+    #[test]
+    fn stop_is_not_a_literal() {
+        assert_eq!(Op::from_str("stop").unwrap(), (Op::STOP, "00"));
+        assert_eq!(
+            Op::from_str("0x00").unwrap(),
+            (Op::VALUE("0x00".to_string()), "")
+        );
+        assert_eq!(Op::STOP.opcode(), Some(0x00));
+    }
+    #[test]
+    fn mnemonics_ignore_case() {
+        assert_eq!(Op::from_str("PuSh1").unwrap(), (Op::PUSH1, "60"));
+        assert_eq!(Op::from_str("Mstore").unwrap(), (Op::MSTORE, "52"));
+        assert_eq!(Op::from_str("Keccak256").unwrap(), (Op::SHA3, "20"));
+        assert_eq!(
+            Op::from_str("MyLabel").unwrap(),
+            (Op::VALUE("MyLabel".to_string()), "")
+        );
+        assert!(Op::from_str("Swap17").is_err());
+    }
+    #[test]
+    fn bitwise_and_shift_opcodes() {
+        let ops = [
+            ("iszero", Op::ISZERO, "15"),
+            ("and", Op::AND, "16"),
+            ("OR", Op::OR, "17"),
+            ("xor", Op::XOR, "18"),
+            ("not", Op::NOT, "19"),
+            ("byte", Op::BYTE, "1a"),
+            ("shl", Op::SHL, "1b"),
+            ("SHR", Op::SHR, "1c"),
+            ("sar", Op::SAR, "1d"),
+        ];
+
+        for (mnemonic, op, byte) in ops {
+            assert_eq!(Op::from_str(mnemonic).unwrap(), (op.clone(), byte));
+            assert_eq!(
+                Op::from_byte(u8::from_str_radix(byte, 16).unwrap()),
+                Some(op)
+            );
+        }
+    }
+    #[test]
+    fn every_mnemonic_round_trips() {
+        let ops = [
+            Op::MSTORE,
+            Op::MLOAD,
+            Op::SLOAD,
+            Op::SSTORE,
+            Op::TLOAD,
+            Op::TSTORE,
+            Op::MCOPY,
+            Op::JUMP,
+            Op::JUMPI,
+            Op::PC,
+            Op::MSIZE,
+            Op::GAS,
+            Op::JUMPDEST,
+            Op::CREATE,
+            Op::CREATE2,
+            Op::EXTCODECOPY,
+            Op::EXTCODESIZE,
+            Op::EXTCODEHASH,
+            Op::PUSH,
+            Op::PUSH1,
+            Op::PUSH2,
+            Op::PUSH3,
+            Op::PUSH4,
+            Op::PUSH5,
+            Op::PUSH6,
+            Op::PUSH7,
+            Op::PUSH8,
+            Op::PUSH9,
+            Op::PUSH10,
+            Op::PUSH11,
+            Op::PUSH12,
+            Op::PUSH13,
+            Op::PUSH14,
+            Op::PUSH15,
+            Op::PUSH16,
+            Op::PUSH17,
+            Op::PUSH18,
+            Op::PUSH19,
+            Op::PUSH20,
+            Op::PUSH21,
+            Op::PUSH22,
+            Op::PUSH23,
+            Op::PUSH24,
+            Op::PUSH25,
+            Op::PUSH26,
+            Op::PUSH27,
+            Op::PUSH28,
+            Op::PUSH29,
+            Op::PUSH30,
+            Op::PUSH31,
+            Op::PUSH32,
+            Op::PUSH0,
+            Op::POP,
+            Op::DUP1,
+            Op::DUP2,
+            Op::DUP3,
+            Op::DUP4,
+            Op::DUP5,
+            Op::DUP6,
+            Op::DUP7,
+            Op::DUP8,
+            Op::DUP9,
+            Op::DUP10,
+            Op::DUP11,
+            Op::DUP12,
+            Op::DUP13,
+            Op::DUP14,
+            Op::DUP15,
+            Op::DUP16,
+            Op::SWAP1,
+            Op::SWAP2,
+            Op::SWAP3,
+            Op::SWAP4,
+            Op::SWAP5,
+            Op::SWAP6,
+            Op::SWAP7,
+            Op::SWAP8,
+            Op::SWAP9,
+            Op::SWAP10,
+            Op::SWAP11,
+            Op::SWAP12,
+            Op::SWAP13,
+            Op::SWAP14,
+            Op::SWAP15,
+            Op::SWAP16,
+            Op::LOG0,
+            Op::LOG1,
+            Op::LOG2,
+            Op::LOG3,
+            Op::LOG4,
+            Op::STOP,
+            Op::ADD,
+            Op::MUL,
+            Op::SUB,
+            Op::DIV,
+            Op::SDIV,
+            Op::MOD,
+            Op::SMOD,
+            Op::ADDMOD,
+            Op::MULMOD,
+            Op::EXP,
+            Op::SIGNEXTEND,
+            Op::LT,
+            Op::GT,
+            Op::SLT,
+            Op::SGT,
+            Op::EQ,
+            Op::ISZERO,
+            Op::AND,
+            Op::OR,
+            Op::XOR,
+            Op::NOT,
+            Op::BYTE,
+            Op::SHL,
+            Op::SHR,
+            Op::SAR,
+            Op::SHA3,
+            Op::ADDRESS,
+            Op::BALANCE,
+            Op::CALLVALUE,
+            Op::CALLDATALOAD,
+            Op::CALLDATASIZE,
+            Op::CALLDATACOPY,
+            Op::RETURNDATASIZE,
+            Op::RETURNDATACOPY,
+            Op::CALLER,
+            Op::ORIGIN,
+            Op::GASPRICE,
+            Op::CODESIZE,
+            Op::CODECOPY,
+            Op::BLOCKHASH,
+            Op::COINBASE,
+            Op::TIMESTAMP,
+            Op::NUMBER,
+            Op::PREVRANDAO,
+            Op::GASLIMIT,
+            Op::CHAINID,
+            Op::SELFBALANCE,
+            Op::BASEFEE,
+            Op::RETURN,
+            Op::REVERT,
+            Op::INVALID,
+            Op::SELFDESTRUCT,
+            Op::CALL,
+            Op::CALLCODE,
+            Op::DELEGATECALL,
+            Op::STATICCALL,
+        ];
+
+        // A new variant fails to compile here until it is added to `ops`
+        let listed = |op: &Op| match op {
+            Op::VALUE(_)
+            | Op::LABEL(_)
+            | Op::DATA(_)
+            | Op::ORG(_)
+            | Op::ALIGN(..)
+            | Op::CUSTOM(..) => false,
+            Op::MSTORE
+            | Op::MLOAD
+            | Op::SLOAD
+            | Op::SSTORE
+            | Op::TLOAD
+            | Op::TSTORE
+            | Op::MCOPY
+            | Op::JUMP
+            | Op::JUMPI
+            | Op::PC
+            | Op::MSIZE
+            | Op::GAS
+            | Op::JUMPDEST
+            | Op::CREATE
+            | Op::CREATE2
+            | Op::EXTCODECOPY
+            | Op::EXTCODESIZE
+            | Op::EXTCODEHASH
+            | Op::PUSH
+            | Op::PUSH1
+            | Op::PUSH2
+            | Op::PUSH3
+            | Op::PUSH4
+            | Op::PUSH5
+            | Op::PUSH6
+            | Op::PUSH7
+            | Op::PUSH8
+            | Op::PUSH9
+            | Op::PUSH10
+            | Op::PUSH11
+            | Op::PUSH12
+            | Op::PUSH13
+            | Op::PUSH14
+            | Op::PUSH15
+            | Op::PUSH16
+            | Op::PUSH17
+            | Op::PUSH18
+            | Op::PUSH19
+            | Op::PUSH20
+            | Op::PUSH21
+            | Op::PUSH22
+            | Op::PUSH23
+            | Op::PUSH24
+            | Op::PUSH25
+            | Op::PUSH26
+            | Op::PUSH27
+            | Op::PUSH28
+            | Op::PUSH29
+            | Op::PUSH30
+            | Op::PUSH31
+            | Op::PUSH32
+            | Op::PUSH0
+            | Op::POP
+            | Op::DUP1
+            | Op::DUP2
+            | Op::DUP3
+            | Op::DUP4
+            | Op::DUP5
+            | Op::DUP6
+            | Op::DUP7
+            | Op::DUP8
+            | Op::DUP9
+            | Op::DUP10
+            | Op::DUP11
+            | Op::DUP12
+            | Op::DUP13
+            | Op::DUP14
+            | Op::DUP15
+            | Op::DUP16
+            | Op::SWAP1
+            | Op::SWAP2
+            | Op::SWAP3
+            | Op::SWAP4
+            | Op::SWAP5
+            | Op::SWAP6
+            | Op::SWAP7
+            | Op::SWAP8
+            | Op::SWAP9
+            | Op::SWAP10
+            | Op::SWAP11
+            | Op::SWAP12
+            | Op::SWAP13
+            | Op::SWAP14
+            | Op::SWAP15
+            | Op::SWAP16
+            | Op::LOG0
+            | Op::LOG1
+            | Op::LOG2
+            | Op::LOG3
+            | Op::LOG4
+            | Op::STOP
+            | Op::ADD
+            | Op::MUL
+            | Op::SUB
+            | Op::DIV
+            | Op::SDIV
+            | Op::MOD
+            | Op::SMOD
+            | Op::ADDMOD
+            | Op::MULMOD
+            | Op::EXP
+            | Op::SIGNEXTEND
+            | Op::LT
+            | Op::GT
+            | Op::SLT
+            | Op::SGT
+            | Op::EQ
+            | Op::ISZERO
+            | Op::AND
+            | Op::OR
+            | Op::XOR
+            | Op::NOT
+            | Op::BYTE
+            | Op::SHL
+            | Op::SHR
+            | Op::SAR
+            | Op::SHA3
+            | Op::ADDRESS
+            | Op::BALANCE
+            | Op::CALLVALUE
+            | Op::CALLDATALOAD
+            | Op::CALLDATASIZE
+            | Op::CALLDATACOPY
+            | Op::RETURNDATASIZE
+            | Op::RETURNDATACOPY
+            | Op::CALLER
+            | Op::ORIGIN
+            | Op::GASPRICE
+            | Op::CODESIZE
+            | Op::CODECOPY
+            | Op::BLOCKHASH
+            | Op::COINBASE
+            | Op::TIMESTAMP
+            | Op::NUMBER
+            | Op::PREVRANDAO
+            | Op::GASLIMIT
+            | Op::CHAINID
+            | Op::SELFBALANCE
+            | Op::BASEFEE
+            | Op::RETURN
+            | Op::REVERT
+            | Op::INVALID
+            | Op::SELFDESTRUCT
+            | Op::CALL
+            | Op::CALLCODE
+            | Op::DELEGATECALL
+            | Op::STATICCALL => true,
+        };
+
+        for op in ops {
+            assert!(listed(&op));
+            assert_eq!(Op::from_str(&op.to_string()).unwrap().0, op);
+        }
+    }
+    #[test]
+    fn balance() {
+        assert_eq!(Op::from_str("balance").unwrap(), (Op::BALANCE, "31"));
+        assert_eq!(Op::from_str("BALANCE").unwrap(), (Op::BALANCE, "31"));
+        assert_eq!(Op::BALANCE.stack_io(), (1, 1));
+    }
+    #[test]
+    fn swap_depths() {
+        assert_eq!(Op::from_str("swap1").unwrap(), (Op::SWAP1, "90"));
+        assert_eq!(Op::from_str("swap16").unwrap(), (Op::SWAP16, "9f"));
+        assert_eq!(Op::SWAP16.is_swap(), Some(16));
+
+        for name in ["swap0", "swap17", "SWAP17", "swap100"] {
+            let error = Op::from_str(name).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("SWAP only supports depths 1 to 16"),
+                "{}",
+                name
+            );
+        }
+    }
+    #[test]
+    fn push_widths() {
+        assert_eq!(Op::PUSH0.is_push(), Some(0));
+        assert_eq!(Op::PUSH1.is_push(), Some(1));
+        assert_eq!(Op::PUSH32.is_push(), Some(32));
+        assert_eq!(Op::PUSH.is_push(), None);
+        assert_eq!(Op::ADD.is_push(), None);
+        assert_eq!(Op::push_of_size(0), Some((Op::PUSH0, "5f")));
+    }
+    #[test]
+    fn terminating_opcodes() {
+        for op in [
+            Op::STOP,
+            Op::RETURN,
+            Op::REVERT,
+            Op::INVALID,
+            Op::SELFDESTRUCT,
+        ] {
+            assert!(op.is_terminating(), "{}", op);
+        }
+
+        for op in [Op::JUMP, Op::JUMPI, Op::JUMPDEST, Op::ADD, Op::PUSH0] {
+            assert!(!op.is_terminating(), "{}", op);
+        }
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(Op::ADD.describe(), "pops a, b; pushes (a + b) mod 2^256");
+        assert_eq!(Op::CALLER.describe(), "pushes the address of the caller");
+        assert_eq!(Op::PUSH2.describe(), Op::PUSH32.describe());
+        assert_eq!(Op::VALUE("0x01".to_string()).describe(), "");
+
+        // Every opcode has one
+        assert!((0..=255)
+            .filter_map(Op::from_byte)
+            .all(|op| !op.describe().is_empty()));
+    }
+}
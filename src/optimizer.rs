@@ -0,0 +1,360 @@
+use alloc::{collections::BTreeSet, format, string::String, vec, vec::Vec};
+
+use crate::{
+    instruction::{decode_hex, to_hex},
+    op::is_hex,
+    resolver::{bare_push_hex, local_reference},
+    Fork, Lexer, Op, Span,
+};
+
+/// Rewrite wasteful instruction sequences of the lexed program before its labels are
+/// resolved, so label offsets follow the smaller code. Returns the bytes saved
+pub fn optimize(lexer: &mut Lexer, fork: Fork) -> usize {
+    let labels: BTreeSet<String> = lexer
+        .result
+        .iter()
+        .filter_map(|(op, _, _)| match op {
+            Op::LABEL(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // `1b` and `1f` are label references when there is a `1:`
+    let references: Vec<String> = lexer
+        .result
+        .iter()
+        .filter_map(|(op, _, _)| match op {
+            Op::VALUE(value) => local_reference(value)
+                .filter(|(name, _)| labels.contains(*name))
+                .map(|_| value.clone()),
+            _ => None,
+        })
+        .collect();
+    let labels: BTreeSet<String> = labels.into_iter().chain(references).collect();
+
+    let mut saved = 0;
+
+    // A rewrite can make a new pattern out of its neighbours, so run until nothing changes
+    loop {
+        let (tokens, spans, bytes) = peephole(&lexer.result, &lexer.spans, &labels, fork);
+        if bytes == 0 {
+            return saved;
+        }
+
+        lexer.result = tokens;
+        lexer.spans = spans;
+        saved += bytes;
+    }
+}
+
+type Tokens = Vec<(Op, &'static str, usize)>;
+
+/// One pass over the tokens, returning the rewritten tokens with their spans and the
+/// bytes saved. Only adjacent tokens are matched so nothing is moved across a JUMPDEST
+fn peephole(
+    tokens: &[(Op, &'static str, usize)],
+    spans: &[Span],
+    labels: &BTreeSet<String>,
+    fork: Fork,
+) -> (Tokens, Vec<Span>, usize) {
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut result_spans = Vec::with_capacity(spans.len());
+    let mut saved = 0;
+
+    let mut index = 0;
+    while let Some((op, byte, line)) = tokens.get(index) {
+        let span = spans.get(index).copied().unwrap_or_default();
+        let next = tokens.get(index + 1).map(|(op, _, _)| op);
+
+        if let Some((folded, used, bytes)) = fold(&tokens[index..], labels, fork) {
+            result_spans.resize(result.len() + folded.len(), span);
+            result.extend(folded);
+            saved += bytes;
+            index += used;
+
+            continue;
+        }
+
+        match (op, next) {
+            // DUP1 POP, SWAP1 SWAP1 and NOT NOT leave the stack as it was
+            (Op::DUP1, Some(Op::POP)) | (Op::SWAP1, Some(Op::SWAP1)) | (Op::NOT, Some(Op::NOT)) => {
+                saved += 2;
+                index += 2;
+
+                continue;
+            }
+
+            // A PUSH of a literal zero becomes PUSH0, a bare PUSH takes the width of its hex
+            // digits. The resolver already infers PUSH0 for a bare PUSH of the number zero
+            (op, Some(Op::VALUE(value)))
+                if fork >= Fork::Shanghai
+                    && (matches!(op, Op::PUSH) || op.is_push().is_some_and(|size| size > 0))
+                    && is_zero(value, labels)
+                    && (!matches!(op, Op::PUSH) || value.starts_with("0x")) =>
+            {
+                saved += match op.is_push() {
+                    Some(size) => size as usize,
+                    None => bare_push_hex(value).map_or(1, |hex| hex.len() / 2),
+                };
+
+                result.push((Op::PUSH0, push0_byte(), *line));
+                result_spans.push(span);
+                index += 2;
+
+                continue;
+            }
+
+            _ => {}
+        }
+
+        result.push((op.clone(), *byte, *line));
+        result_spans.push(span);
+        index += 1;
+    }
+
+    (result, result_spans, saved)
+}
+
+/// Is the value a literal zero, rather than a label reference
+fn is_zero(value: &str, labels: &BTreeSet<String>) -> bool {
+    !labels.contains(value)
+        && is_hex(value)
+        && value
+            .strip_prefix("0x")
+            .unwrap_or(value)
+            .bytes()
+            .all(|c| c == b'0')
+}
+
+/// Fold two constant PUSHes and the arithmetic opcode after them into a single PUSH
+/// of the result, returning its tokens, the tokens it replaces and the bytes saved.
+/// Nothing is folded when the result would take more bytes than the original
+fn fold(
+    tokens: &[(Op, &'static str, usize)],
+    labels: &BTreeSet<String>,
+    fork: Fork,
+) -> Option<(Tokens, usize, usize)> {
+    let (second, second_tokens, second_size) = literal(tokens, labels)?;
+    let (top, top_tokens, top_size) = literal(&tokens[second_tokens..], labels)?;
+    let (op, _, line) = tokens.get(second_tokens + top_tokens)?;
+
+    // The top of the stack is the left operand, `PUSH1 3 PUSH1 5 SUB` is 5 - 3
+    let value = match op {
+        Op::ADD => word::add(top, second),
+        Op::SUB => word::sub(top, second),
+        Op::MUL => word::mul(top, second),
+        Op::DIV => word::div_rem(top, second).0,
+        Op::MOD => word::div_rem(top, second).1,
+        Op::AND => word::zip(top, second, |a, b| a & b),
+        Op::OR => word::zip(top, second, |a, b| a | b),
+        Op::XOR => word::zip(top, second, |a, b| a ^ b),
+        _ => return None,
+    };
+
+    let bytes = word::to_bytes(value);
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+
+    let folded: Tokens = match bytes {
+        [] if fork >= Fork::Shanghai => vec![(Op::PUSH0, push0_byte(), *line)],
+
+        bytes => {
+            let bytes = if bytes.is_empty() { &[0][..] } else { bytes };
+            let (push, byte) = Op::push_of_size(bytes.len())?;
+
+            vec![
+                (push, byte, *line),
+                (Op::VALUE(format!("0x{}", to_hex(bytes))), "", *line),
+            ]
+        }
+    };
+
+    let size = match folded.len() {
+        1 => 1,
+        _ => 1 + bytes.len().max(1),
+    };
+    let original = second_size + top_size + 1;
+    if size > original {
+        return None;
+    }
+
+    Some((folded, second_tokens + top_tokens + 1, original - size))
+}
+
+/// A literal constant PUSH at the start of the tokens, with the number of tokens it
+/// takes and the bytes it emits
+fn literal(
+    tokens: &[(Op, &str, usize)],
+    labels: &BTreeSet<String>,
+) -> Option<(word::Word, usize, usize)> {
+    match tokens {
+        [(Op::PUSH0, _, _), ..] => Some((word::ZERO, 1, 1)),
+
+        [(op, _, _), (Op::VALUE(value), _, _), ..] if !labels.contains(value) && is_hex(value) => {
+            let hex = value.strip_prefix("0x").unwrap_or(value);
+
+            // A PUSHn whose operand has the wrong width is left for the parser to reject
+            let size = match op.is_push() {
+                Some(size) if hex.len() == size as usize * 2 => size as usize,
+                None if matches!(op, Op::PUSH) => bare_push_hex(value)?.len() / 2,
+                _ => return None,
+            };
+
+            let bytes = decode_hex(&format!("{:0>1$}", hex, hex.len().div_ceil(2) * 2))?;
+            if bytes.len() > 32 {
+                return None;
+            }
+
+            Some((word::from_bytes(&bytes), 2, 1 + size))
+        }
+
+        _ => None,
+    }
+}
+
+fn push0_byte() -> &'static str {
+    Op::from_str("PUSH0").expect("PUSH0 is an opcode").1
+}
+
+/// 256-bit EVM words as little-endian 64-bit limbs, arithmetic wraps like the EVM
+mod word {
+    pub type Word = [u64; 4];
+
+    pub const ZERO: Word = [0; 4];
+
+    /// Big-endian bytes of at most 32 bytes
+    pub fn from_bytes(bytes: &[u8]) -> Word {
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(bytes);
+
+        let mut word = ZERO;
+        for (i, limb) in word.iter_mut().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            *limb = u64::from_be_bytes(padded[start..start + 8].try_into().unwrap());
+        }
+
+        word
+    }
+
+    /// The 32 big-endian bytes of the word
+    pub fn to_bytes(word: Word) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in word.iter().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn add(a: Word, b: Word) -> Word {
+        let mut result = ZERO;
+        let mut carry = false;
+
+        for i in 0..4 {
+            let (sum, first) = a[i].overflowing_add(b[i]);
+            let (sum, second) = sum.overflowing_add(carry as u64);
+            result[i] = sum;
+            carry = first || second;
+        }
+
+        result
+    }
+
+    pub fn sub(a: Word, b: Word) -> Word {
+        let mut result = ZERO;
+        let mut borrow = false;
+
+        for i in 0..4 {
+            let (difference, first) = a[i].overflowing_sub(b[i]);
+            let (difference, second) = difference.overflowing_sub(borrow as u64);
+            result[i] = difference;
+            borrow = first || second;
+        }
+
+        result
+    }
+
+    pub fn mul(a: Word, b: Word) -> Word {
+        let mut result = ZERO;
+
+        for i in 0..4 {
+            let mut carry = 0u128;
+
+            for j in 0..4 - i {
+                let product = a[i] as u128 * b[j] as u128 + result[i + j] as u128 + carry;
+                result[i + j] = product as u64;
+                carry = product >> 64;
+            }
+        }
+
+        result
+    }
+
+    /// Quotient and remainder, both are zero when dividing by zero like in the EVM
+    pub fn div_rem(a: Word, b: Word) -> (Word, Word) {
+        if b == ZERO {
+            return (ZERO, ZERO);
+        }
+
+        let mut quotient = ZERO;
+        let mut remainder = ZERO;
+
+        for bit in (0..256).rev() {
+            // Shift the remainder left by one and bring down the next bit of `a`
+            let overflow = remainder[3] >> 63;
+            for i in (1..4).rev() {
+                remainder[i] = (remainder[i] << 1) | (remainder[i - 1] >> 63);
+            }
+            remainder[0] = (remainder[0] << 1) | ((a[bit / 64] >> (bit % 64)) & 1);
+
+            if overflow == 1 || !less(remainder, b) {
+                remainder = sub(remainder, b);
+                quotient[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+
+        (quotient, remainder)
+    }
+
+    pub fn zip(a: Word, b: Word, f: impl Fn(u64, u64) -> u64) -> Word {
+        [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])]
+    }
+
+    fn less(a: Word, b: Word) -> bool {
+        a.iter().rev().lt(b.iter().rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::word::*;
+
+    #[test]
+    fn words_wrap_like_the_evm() {
+        let max = [u64::MAX; 4];
+        let one = from_bytes(&[1]);
+
+        assert_eq!(add(max, one), ZERO);
+        assert_eq!(sub(ZERO, one), max);
+        assert_eq!(mul(max, max), one);
+        assert_eq!(
+            to_bytes(mul(
+                from_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0]),
+                from_bytes(&[3])
+            ))[23..],
+            [3, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let mut quotient = [0xff; 32];
+        quotient[0] = 0x0f;
+        assert_eq!(
+            div_rem(max, from_bytes(&[0x10])),
+            (from_bytes(&quotient), from_bytes(&[0x0f]))
+        );
+        assert_eq!(div_rem(one, ZERO), (ZERO, ZERO));
+    }
+}
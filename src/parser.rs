@@ -0,0 +1,186 @@
+use crate::{
+    error::Kind,
+    instruction::{decode_hex, Instruction},
+    resolver::too_wide,
+    AssembleError, Op, ParseError, Span,
+};
+use alloc::{format, string::ToString, vec, vec::Vec};
+
+/// Groups resolved tokens into instructions. It only borrows the tokens, whose opcode
+/// bytes may live as long as them rather than being `'static`
+#[derive(Debug)]
+pub struct Parser<'a> {
+    cursor: usize,
+    tokens: &'a [(Op, &'a str, usize)],
+    spans: &'a [Span],
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = (Op, &'a str, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.tokens.get(self.cursor).cloned();
+
+        self.cursor += 1;
+
+        result
+    }
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [(Op, &'a str, usize)]) -> Self {
+        Self {
+            cursor: 0,
+            tokens,
+            spans: &[],
+        }
+    }
+
+    /// Record where each instruction is in the source, `spans` is parallel to the tokens
+    pub fn spans(mut self, spans: &'a [Span]) -> Self {
+        self.spans = spans;
+
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Instruction>, AssembleError> {
+        self.instructions().map_err(AssembleError::many)
+    }
+
+    /// Group every opcode with its operand, parsing goes on after a bad token so every
+    /// error is reported
+    fn instructions(&mut self) -> Result<Vec<Instruction>, Vec<ParseError>> {
+        // Every instruction takes at least one token, most PUSHes take two
+        let mut result: Vec<Instruction> = Vec::with_capacity(self.tokens.len());
+        let mut errors = vec![];
+
+        while let Some(token) = self.next() {
+            let span = self.spans.get(self.cursor - 1).copied().unwrap_or_default();
+
+            // An .org that is already reached, or the zero of an inferred PUSH0, has no bytes
+            if matches!(&token.0, Op::DATA(bytes) if bytes.is_empty()) {
+                continue;
+            }
+
+            match self.instruction(token, span) {
+                Ok(instruction) => result.push(instruction),
+                Err(error) => errors.push(error.column(span.column)),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(result),
+            false => Err(errors),
+        }
+    }
+
+    /// Parse the instruction starting at the token
+    fn instruction(
+        &mut self,
+        token: (Op, &str, usize),
+        span: Span,
+    ) -> Result<Instruction, ParseError> {
+        match token {
+            (Op::VALUE(value), _, line) => Err(ParseError::new(format!(
+                "literal {} on line {} is not the operand of a PUSH",
+                value, line
+            ))
+            .line(line)),
+
+            (Op::DATA(bytes), _, line) => Ok(Instruction {
+                operand: Some(bytes.clone()),
+                op: Op::DATA(bytes),
+                opcode: None,
+                line,
+                column: span.column,
+                len: span.len,
+            }),
+
+            (Op::ORG(_) | Op::ALIGN(..), _, line) => Err(ParseError::new(
+                "the padding of .org and .align must be resolved before parsing".to_string(),
+            )
+            .line(line)),
+
+            (Op::PUSH, _, line) => Err(ParseError::new(
+                "the size of PUSH must be resolved before parsing".to_string(),
+            )
+            .line(line)),
+
+            (op, _, line) => {
+                let opcode = op.opcode().ok_or_else(|| {
+                    ParseError::new(format!("{} has no opcode byte", op)).line(line)
+                })?;
+
+                // PUSH0 has no operand to read
+                let operand = match op.is_push() {
+                    Some(size) if size > 0 => Some(self.parse_push_operand(&op, size, line)?),
+                    _ => None,
+                };
+
+                Ok(Instruction {
+                    op,
+                    opcode: Some(opcode),
+                    operand,
+                    line,
+                    column: span.column,
+                    len: span.len,
+                })
+            }
+        }
+    }
+
+    /// Read the operand that follows a PUSH and check that it is exactly `size` bytes
+    fn parse_push_operand(
+        &mut self,
+        op: &Op,
+        size: u8,
+        line: usize,
+    ) -> Result<Vec<u8>, ParseError> {
+        // A missing operand leaves the next token to be parsed as an instruction
+        let value = match self.tokens.get(self.cursor) {
+            Some((Op::VALUE(value), _, _)) => {
+                self.cursor += 1;
+
+                value.clone()
+            }
+
+            _ => {
+                return Err(
+                    ParseError::new(format!("{} on line {} expects an operand", op, line))
+                        .line(line),
+                )
+            }
+        };
+
+        let hex = value.strip_prefix("0x").unwrap_or(&value);
+
+        if !hex.len().is_multiple_of(2) {
+            return Err(ParseError::new(format!(
+                "operand {} of {} has an odd number of hex digits",
+                value, op
+            ))
+            .line(line)
+            .kind(Kind::OddLengthHex(value)));
+        }
+
+        if hex.len() > Op::MAX_PUSH_SIZE * 2 {
+            return Err(too_wide(&value, &op.to_string(), hex.len() / 2, line));
+        }
+
+        if hex.len() != size as usize * 2 {
+            return Err(ParseError::new(format!(
+                "operand {} of {} must be {} bytes",
+                value, op, size
+            ))
+            .line(line)
+            .kind(Kind::BadOperandWidth {
+                operand: value,
+                expected: size as usize,
+            }));
+        }
+
+        decode_hex(hex).ok_or_else(|| {
+            ParseError::new(format!("operand {} of {} is not a hex literal", value, op)).line(line)
+        })
+    }
+}
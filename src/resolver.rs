@@ -0,0 +1,564 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{error::Kind, object::Relocation, op::is_hex, AssembleError, Fork, Op, ParseError};
+
+/// Error for an operand wider than any PUSH
+pub(crate) fn too_wide(operand: &str, op: &str, size: usize, line: usize) -> ParseError {
+    ParseError::new(format!(
+        "operand {} of {} on line {} is {} bytes but a PUSH takes at most {}",
+        operand,
+        op,
+        line,
+        size,
+        Op::MAX_PUSH_SIZE
+    ))
+    .line(line)
+    .kind(Kind::BadOperandWidth {
+        operand: operand.to_string(),
+        expected: Op::MAX_PUSH_SIZE,
+    })
+}
+
+/// Passes the widths of label PUSHes get to settle, they only grow so it takes at most
+/// one pass per byte a PUSH grows by
+const MAX_PASSES: usize = 64;
+
+/// Bytes the big-endian offset takes, at least one
+fn offset_size(offset: usize) -> usize {
+    (usize::BITS - offset.leading_zeros()).div_ceil(8).max(1) as usize
+}
+
+/// Is the label a numeric local label like `1:`
+pub(crate) fn is_local(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Split a local label reference like `1b` or `1f` into the label and whether it looks forward
+pub(crate) fn local_reference(value: &str) -> Option<(&str, bool)> {
+    if let Some(name) = value.strip_suffix('b').filter(|name| is_local(name)) {
+        return Some((name, false));
+    }
+
+    value
+        .strip_suffix('f')
+        .filter(|name| is_local(name))
+        .map(|name| (name, true))
+}
+
+/// Hex digits of the operand of a bare PUSH, which give its width. A `0x` literal keeps
+/// the digits written, so `0x0001` is a PUSH2, any other value gets the fewest bytes that
+/// fit it. `None` for a `0x` literal of an odd number of digits
+pub(crate) fn bare_push_hex(value: &str) -> Option<String> {
+    match value.strip_prefix("0x") {
+        Some(hex) if !hex.len().is_multiple_of(2) => None,
+        Some(hex) => Some(hex.to_string()),
+
+        None => {
+            let hex = value.trim_start_matches('0');
+            let width = hex.len().max(1).div_ceil(2) * 2;
+
+            Some(format!("{:0>width$}", hex))
+        }
+    }
+}
+
+/// Split an operand `sizeof(start,end)`, as the lexer writes it, into its two labels
+fn size_of(value: &str) -> Option<(&str, &str)> {
+    value
+        .strip_prefix("sizeof(")?
+        .strip_suffix(')')?
+        .split_once(',')
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    /// Byte offset of every label
+    pub labels: BTreeMap<String, usize>,
+
+    /// Operands to patch when the code is linked, only recorded when relocatable
+    pub relocations: Vec<Relocation>,
+
+    relocatable: bool,
+
+    /// Fork the code targets, a bare PUSH of the number zero is a PUSH0 from Shanghai on
+    fork: Fork,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            labels: BTreeMap::new(),
+            relocations: vec![],
+            relocatable: false,
+            fork: Fork::default(),
+        }
+    }
+
+    /// Resolve the code of an object, where labels that are not defined belong to
+    /// other objects and every label reference is recorded as a relocation
+    pub fn relocatable(mut self) -> Self {
+        self.relocatable = true;
+
+        self
+    }
+
+    /// Infer PUSHes for the fork, the newest one by default
+    pub fn fork(mut self, fork: Fork) -> Self {
+        self.fork = fork;
+
+        self
+    }
+
+    /// Record the offset of every label and replace label references with it
+    pub fn resolve(&mut self, tokens: &mut [(Op, &str, usize)]) -> Result<(), AssembleError> {
+        Self::resolve_local_labels(tokens)?;
+        let relaxed = Self::infer_push_sizes(tokens, self.fork)?;
+
+        self.relax(tokens, relaxed)?;
+
+        // A label reference takes the width of the PUSH before it, or 2 bytes on its own
+        let mut width = 2;
+        let mut offset = 0;
+        for (op, _, line) in tokens.iter_mut() {
+            if let Op::VALUE(value) = op {
+                if let Some((start, end)) = size_of(value) {
+                    let size = self.size_of(start, end, *line)?;
+                    *value = Self::encode_offset(value, size, width)?;
+                }
+
+                if value.contains(['+', '-']) {
+                    let (result, labels) = self.evaluate(value, *line)?;
+
+                    // Only an expression of one label more than it subtracts moves with the code
+                    match labels {
+                        0 => {}
+                        1 => self.relocate(offset, width, None),
+                        _ if !self.relocatable => {}
+                        _ => {
+                            return Err(ParseError::new(format!(
+                                "expression {} on line {} can not be relocated",
+                                value, line
+                            ))
+                            .line(*line)
+                            .into())
+                        }
+                    }
+
+                    *value = Self::encode_offset(value, result, width)?;
+                }
+
+                match self.labels.get(value) {
+                    Some(&target) => {
+                        self.relocate(offset, width, None);
+                        *value = Self::encode_offset(value, target, width)?;
+                    }
+
+                    None if is_hex(value) => {}
+
+                    // Left zero for the linker to fill in
+                    None if self.relocatable => {
+                        self.relocate(offset, width, Some(value.clone()));
+                        *value = "00".repeat(width);
+                    }
+
+                    None => {
+                        return Err(ParseError::new(format!(
+                            "label {} used on line {} is not defined",
+                            value, line
+                        ))
+                        .line(*line)
+                        .kind(Kind::UndefinedLabel(value.clone()))
+                        .into())
+                    }
+                }
+            }
+
+            width = op.is_push().map_or(2, usize::from);
+            offset += match op {
+                Op::VALUE(value) => Self::value_size(value),
+                Op::DATA(bytes) => bytes.len(),
+                _ => 1,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Give every bare PUSH of a label the smallest PUSHn that holds the offset of the label.
+    /// A wider PUSH moves the labels after it, so the widths start at one byte and grow
+    /// until they all fit. The code of an object keeps 2 bytes, its offsets move when linked
+    fn relax(
+        &mut self,
+        tokens: &mut [(Op, &str, usize)],
+        relaxed: Vec<usize>,
+    ) -> Result<(), AssembleError> {
+        if self.relocatable || relaxed.is_empty() {
+            return self.layout(tokens);
+        }
+
+        let mut original = tokens.to_vec();
+        for &index in &relaxed {
+            (original[index].0, original[index].1) = Op::push_of_size(1).expect("PUSH1 exists");
+        }
+
+        for _ in 0..MAX_PASSES {
+            tokens.clone_from_slice(&original);
+            self.layout(tokens)?;
+
+            let mut settled = true;
+            for &index in &relaxed {
+                let (Some(size), Op::VALUE(label)) =
+                    (tokens[index].0.is_push(), &tokens[index + 1].0)
+                else {
+                    continue;
+                };
+
+                let needed = offset_size(self.labels[label]);
+                if needed > usize::from(size) {
+                    (original[index].0, original[index].1) =
+                        Op::push_of_size(needed).expect("an offset fits in a PUSH");
+                    settled = false;
+                }
+            }
+
+            if settled {
+                return Ok(());
+            }
+        }
+
+        Err(ParseError::new(format!(
+            "the widths of label PUSHes did not settle after {} passes",
+            MAX_PASSES
+        ))
+        .into())
+    }
+
+    /// Compute the offset of every label, turning `.org` and `.align` into their padding
+    fn layout(&mut self, tokens: &mut [(Op, &str, usize)]) -> Result<(), AssembleError> {
+        let mut offset = 0;
+        let mut index = 0;
+        let mut lines = BTreeMap::new();
+
+        while let Some((op, _, line)) = tokens.get(index) {
+            match op {
+                Op::LABEL(name) => {
+                    if let Some(first) = lines.insert(name.clone(), *line) {
+                        return Err(ParseError::new(format!(
+                            "label {} is defined on line {} and again on line {}",
+                            name, first, line
+                        ))
+                        .line(*line)
+                        .kind(Kind::DuplicateLabel {
+                            label: name.clone(),
+                            first,
+                        })
+                        .into());
+                    }
+
+                    self.labels.insert(name.clone(), offset);
+                    offset += 1;
+                }
+
+                Op::VALUE(value) => offset += Self::value_size(value),
+
+                Op::DATA(bytes) => offset += bytes.len(),
+
+                &Op::ORG(target) => {
+                    if offset > target {
+                        return Err(ParseError::new(format!(
+                            ".org 0x{:x} on line {} is behind the current offset 0x{:x}",
+                            target, line, offset
+                        ))
+                        .line(*line)
+                        .into());
+                    }
+
+                    tokens[index].0 = Op::DATA(vec![0; target - offset]);
+                    offset = target;
+                }
+
+                &Op::ALIGN(boundary, pad) => {
+                    let padding = (boundary - offset % boundary) % boundary;
+
+                    tokens[index].0 = Op::DATA(vec![pad; padding]);
+                    offset += padding;
+                }
+
+                op => match op.is_push() {
+                    Some(size) if size > 0 => {
+                        // The operand is validated to be exactly `size` bytes by the parser
+                        offset += 1 + size as usize;
+                        index += 1;
+                    }
+
+                    _ => offset += 1,
+                },
+            }
+
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Record the operand at `offset` as a relocation, when the code is relocatable
+    fn relocate(&mut self, offset: usize, width: usize, symbol: Option<String>) {
+        if self.relocatable {
+            self.relocations.push(Relocation {
+                offset,
+                width,
+                symbol,
+            });
+        }
+    }
+
+    /// Bytes from the label `start` to the label `end`, which can not be before it
+    fn size_of(&self, start: &str, end: &str, line: usize) -> Result<usize, ParseError> {
+        let offset = |label: &str| {
+            self.labels.get(label).copied().ok_or_else(|| {
+                ParseError::new(format!(
+                    "label {} used on line {} is not defined",
+                    label, line
+                ))
+                .line(line)
+                .kind(Kind::UndefinedLabel(label.to_string()))
+            })
+        };
+
+        offset(end)?.checked_sub(offset(start)?).ok_or_else(|| {
+            ParseError::new(format!(
+                "sizeof({}, {}) on line {} is negative, {} is before {}",
+                start, end, line, end, start
+            ))
+            .line(line)
+        })
+    }
+
+    /// Evaluate an operand expression like `end-start` of labels and hex literals, with
+    /// the number of labels it adds less the number it subtracts
+    fn evaluate(&self, expression: &str, line: usize) -> Result<(usize, isize), ParseError> {
+        let mut result: Option<usize> = Some(0);
+        let mut labels = 0;
+        let mut rest = expression;
+
+        while !rest.is_empty() {
+            let negative = rest.starts_with('-');
+            let term = rest.trim_start_matches(['+', '-']);
+            let end = term.find(['+', '-']).unwrap_or(term.len());
+            let (term, tail) = term.split_at(end);
+            rest = tail;
+
+            let value = match self.labels.get(term) {
+                Some(&offset) => {
+                    labels += if negative { -1 } else { 1 };
+                    Some(offset)
+                }
+                None if is_hex(term) => {
+                    usize::from_str_radix(term.strip_prefix("0x").unwrap_or(term), 16).ok()
+                }
+                None => {
+                    return Err(ParseError::new(format!(
+                        "label {} used on line {} is not defined",
+                        term, line
+                    ))
+                    .line(line)
+                    .kind(Kind::UndefinedLabel(term.to_string())))
+                }
+            };
+
+            result = match negative {
+                true => value.and_then(|value| result?.checked_sub(value)),
+                false => value.and_then(|value| result?.checked_add(value)),
+            };
+        }
+
+        let result = result.ok_or_else(|| {
+            ParseError::new(format!(
+                "expression {} on line {} is negative or too large",
+                expression, line
+            ))
+            .line(line)
+        })?;
+
+        Ok((result, labels))
+    }
+
+    /// Give every numeric label like `1:` a unique name and point each `1b` at the nearest
+    /// `1:` before it and each `1f` at the nearest one after it. Without a numeric label of
+    /// that number `1b` and `1f` stay hex literals
+    fn resolve_local_labels(tokens: &mut [(Op, &str, usize)]) -> Result<(), ParseError> {
+        let mut definitions: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, (op, _, _)) in tokens.iter().enumerate() {
+            if let Op::LABEL(name) = op {
+                if is_local(name) {
+                    definitions.entry(name.clone()).or_default().push(index);
+                }
+            }
+        }
+
+        if definitions.is_empty() {
+            return Ok(());
+        }
+
+        let unique = |name: &str, index: usize| {
+            let occurrence = definitions[name].iter().position(|&i| i == index).unwrap();
+            format!("{}@{}", name, occurrence)
+        };
+
+        let mut renamed = vec![];
+        for (index, (op, _, line)) in tokens.iter().enumerate() {
+            match op {
+                Op::LABEL(name) if definitions.contains_key(name) => {
+                    renamed.push((index, Op::LABEL(unique(name, index))))
+                }
+
+                Op::VALUE(value) => {
+                    let Some((name, forward)) = local_reference(value) else {
+                        continue;
+                    };
+                    let Some(indices) = definitions.get(name) else {
+                        continue;
+                    };
+
+                    let target = match forward {
+                        true => indices.iter().find(|&&i| i > index),
+                        false => indices.iter().rev().find(|&&i| i < index),
+                    };
+
+                    let target = target.ok_or_else(|| {
+                        ParseError::new(format!(
+                            "local label {} used on line {} has no {}: {} it",
+                            value,
+                            line,
+                            name,
+                            if forward { "after" } else { "before" }
+                        ))
+                        .line(*line)
+                    })?;
+
+                    renamed.push((index, Op::VALUE(unique(name, *target))));
+                }
+
+                _ => {}
+            }
+        }
+
+        for (index, op) in renamed {
+            tokens[index].0 = op;
+        }
+
+        Ok(())
+    }
+
+    /// Replace every bare PUSH with the smallest PUSHn that fits its operand, label
+    /// references are 2 bytes until they are relaxed and the number zero is a PUSH0 when
+    /// the fork has it. Returns the index of every bare PUSH of a label
+    fn infer_push_sizes(
+        tokens: &mut [(Op, &str, usize)],
+        fork: Fork,
+    ) -> Result<Vec<usize>, ParseError> {
+        let labels: BTreeSet<String> = tokens
+            .iter()
+            .filter_map(|(op, _, _)| match op {
+                Op::LABEL(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut relaxed = vec![];
+
+        for index in 0..tokens.len() {
+            if !matches!(tokens[index].0, Op::PUSH) {
+                continue;
+            }
+
+            let (size, value, zero) = match tokens.get_mut(index + 1) {
+                Some((Op::VALUE(value), _, _)) if labels.contains(value) => {
+                    relaxed.push(index);
+                    (2, value.clone(), false)
+                }
+
+                // An expression or a label that is not defined
+                Some((Op::VALUE(value), _, _)) if !is_hex(value) => (2, value.clone(), false),
+
+                Some((Op::VALUE(value), _, line)) => {
+                    let hex = bare_push_hex(value).ok_or_else(|| {
+                        ParseError::new(format!(
+                            "PUSH operand {} on line {} has an odd number of hex digits, \
+                             its digits give the width of the PUSH",
+                            value, line
+                        ))
+                        .line(*line)
+                    })?;
+
+                    // Only a number is a PUSH0, a hex literal keeps the width of its digits
+                    let zero = !value.starts_with("0x") && hex.bytes().all(|c| c == b'0');
+                    (hex.len() / 2, core::mem::replace(value, hex), zero)
+                }
+
+                _ => {
+                    return Err(ParseError::new(format!(
+                        "PUSH on line {} expects an operand",
+                        tokens[index].2
+                    ))
+                    .line(tokens[index].2))
+                }
+            };
+
+            if size > Op::MAX_PUSH_SIZE {
+                return Err(too_wide(&value, "PUSH", size, tokens[index].2));
+            }
+
+            // The operand is left as data of no bytes, like an .org that is already reached
+            if zero && fork >= Fork::Shanghai {
+                tokens[index].0 = Op::PUSH0;
+                tokens[index].1 = Op::push_of_size(0).expect("PUSH0 exists").1;
+                tokens[index + 1].0 = Op::DATA(vec![]);
+
+                continue;
+            }
+
+            let (op, byte) = Op::push_of_size(size).expect("sizes up to 32 have a PUSH");
+
+            tokens[index].0 = op;
+            tokens[index].1 = byte;
+        }
+
+        Ok(relaxed)
+    }
+
+    /// Encode the offset of a label, or the value of an expression, as hex padded to `width` bytes
+    fn encode_offset(label: &str, offset: usize, width: usize) -> Result<String, ParseError> {
+        let hex = format!("{:0>1$x}", offset, width * 2);
+
+        if hex.len() > width * 2 {
+            return Err(ParseError::new(format!(
+                "label {} at offset 0x{} needs {} bytes but only {} are available",
+                label,
+                hex,
+                hex.len().div_ceil(2),
+                width
+            ))
+            .kind(Kind::BadOperandWidth {
+                operand: label.to_string(),
+                expected: width,
+            }));
+        }
+
+        Ok(hex)
+    }
+
+    /// Bytes a bare value takes in the output, label references are 2 bytes
+    fn value_size(value: &str) -> usize {
+        if is_hex(value) {
+            value.strip_prefix("0x").unwrap_or(value).len().div_ceil(2)
+        } else {
+            2
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::assemble;
+use alloc::string::{String, ToString};
+
+/// Assemble the source for the browser, the error is its message as the
+/// playground only shows it
+pub fn assemble_js(source: &str) -> Result<String, String> {
+    assemble(source).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_their_messages() {
+        assert_eq!(
+            assemble_js("push1 0x01 push1 0x02 add").unwrap(),
+            "6001600201"
+        );
+
+        let error = assemble_js("push1").unwrap_err();
+        assert!(
+            error.contains("PUSH1 on line 1 expects an operand"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,1641 @@
+#[test]
+fn assemble_readme_example() {
+    assert_eq!(
+        easm::assemble("PUSH1 0x80\nPUSH1 0x40\nMSTORE").unwrap(),
+        "6080604052"
+    );
+}
+
+#[test]
+fn push1_operand_wider_than_a_byte() {
+    assert!(easm::assemble("push1 0x123456").is_err());
+    assert_eq!(easm::assemble("push1 0x12").unwrap(), "6012");
+}
+
+#[test]
+fn unknown_token_is_rejected() {
+    let error = easm::assemble("pish1 0x01").unwrap_err();
+
+    assert!(error.to_string().contains("pish1"));
+}
+
+#[test]
+fn odd_length_literals() {
+    assert!(easm::assemble("push1 0xf").is_err());
+    assert!(easm::assemble(".data 0xf").is_err());
+}
+
+#[test]
+fn floating_literal_is_rejected() {
+    let error = easm::assemble("push1 0x01\n0x42\nadd").unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("line 2 with description 0x42 is not the operand of a PUSH"));
+}
+
+#[test]
+fn literal_after_a_nullary_opcode_is_rejected() {
+    let error = easm::assemble("address 0x10").unwrap_err();
+
+    assert!(error.to_string().contains(
+        "0x10 is not the operand of a PUSH, ADDRESS takes no operand, use .data for raw bytes"
+    ));
+    assert!(easm::assemble("caller 16").is_err());
+    assert_eq!(easm::assemble("address .data 0x10").unwrap(), "3010");
+}
+
+#[test]
+fn define_constants() {
+    assert_eq!(
+        easm::assemble("#define SLOT 0x00\npush1 SLOT mstore").unwrap(),
+        "600052"
+    );
+    assert!(easm::assemble("#define SLOT 0x00\n#define SLOT 0x01").is_err());
+}
+
+#[test]
+fn macros_expand_inline() {
+    let source = "macro clear_mem { push1 0x00 push1 0x00 mstore }\nclear_mem\nclear_mem";
+
+    assert_eq!(easm::assemble(source).unwrap(), "60006000526000600052");
+    assert!(easm::assemble("macro a { b }\nmacro b { a }\na").is_err());
+}
+
+#[test]
+fn estimate_static_gas() {
+    let estimate =
+        easm::estimate_gas(&easm::tokenize("push1 0x02 push1 0x03 mul push1 0x00 exp").unwrap());
+
+    assert_eq!(estimate.total, 3 + 3 + 5 + 3);
+    assert_eq!(estimate.dynamic, 1);
+}
+
+#[test]
+fn stack_underflow_is_reported() {
+    let report = easm::check_stack(&easm::tokenize("push1 0x01\nadd\npush1 0x02").unwrap());
+    let underflow = report.underflow.unwrap();
+
+    assert_eq!(
+        (underflow.line, underflow.needed, underflow.available),
+        (2, 2, 1)
+    );
+    assert_eq!(report.height, 2);
+}
+
+#[test]
+fn leftover_stack_items_are_reported() {
+    use easm::Leftover;
+
+    let leftover = |source| easm::check_stack(&easm::tokenize(source).unwrap()).leftover;
+
+    assert_eq!(
+        leftover("caller\ncallvalue pop\nstop\ncaller"),
+        Some(Leftover {
+            line: 3,
+            items: 1,
+            stop: true
+        })
+    );
+    assert_eq!(
+        leftover("push1 0x01\npush1 0x02"),
+        Some(Leftover {
+            line: 2,
+            items: 2,
+            stop: false
+        })
+    );
+    assert_eq!(leftover("caller pop stop"), None);
+    assert_eq!(leftover("caller push1 0x04 jump\njumpdest stop"), None);
+    assert_eq!(leftover("caller caller push1 here jumpi\nhere: stop"), None);
+
+    // The items made up for an underflow are not left over
+    assert_eq!(leftover("dup3 pop stop"), None);
+    assert_eq!(leftover("add stop"), None);
+    assert_eq!(
+        leftover("caller caller push1 here jumpi\nhere:\ncaller"),
+        None
+    );
+    assert_eq!(leftover("push1 0x20 push1 0x00 return"), None);
+
+    let diagnostics = easm::check(easm::lex("caller\nstop").unwrap(), easm::Fork::default());
+    assert_eq!(diagnostics[0].code, "LeftoverStack");
+    assert_eq!(diagnostics[0].line, Some(2));
+
+    // Each section starts from an empty stack, the runtime can not pop what the init left
+    let source = ".init {\ncaller\n}\n.runtime {\npop stop\n}";
+    let diagnostics = easm::check(easm::lex(source).unwrap(), easm::Fork::default());
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.code, diagnostic.line))
+        .collect();
+    assert_eq!(
+        found,
+        [("LeftoverStack", Some(2)), ("StackUnderflow", Some(5))]
+    );
+
+    let diagnostics = easm::check(
+        easm::lex(".init { stop } .runtime { }").unwrap(),
+        easm::Fork::default(),
+    );
+    assert_eq!(diagnostics[0].code, "EmptyProgram");
+}
+
+#[test]
+fn too_deep_is_not_also_an_underflow() {
+    let codes = |source| {
+        easm::check(easm::lex(source).unwrap(), easm::Fork::default())
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .filter(|code| code.starts_with("Stack"))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(codes("caller dup2 stop"), ["StackTooDeep"]);
+    assert_eq!(codes("caller swap1 stop"), ["StackTooDeep"]);
+
+    // An earlier underflow of another instruction is still reported
+    assert_eq!(
+        codes("add pop caller dup3 stop"),
+        ["StackUnderflow", "StackTooDeep"]
+    );
+}
+
+#[test]
+fn too_deep_dup_and_swap() {
+    let too_deep = |source| {
+        easm::check_stack(&easm::tokenize(source).unwrap())
+            .too_deep
+            .map(|fault| (fault.op, fault.line, fault.available))
+    };
+
+    assert_eq!(
+        too_deep("caller caller caller\ndup5"),
+        Some((easm::Op::DUP5, 2, 3))
+    );
+    assert_eq!(
+        too_deep("caller caller caller\nswap3"),
+        Some((easm::Op::SWAP3, 2, 3))
+    );
+    assert_eq!(too_deep("caller caller caller caller swap3 dup4"), None);
+
+    // Other underflows are not about the depth of a DUP or SWAP
+    assert_eq!(too_deep("add"), None);
+}
+
+#[test]
+fn max_stack_height_is_tracked() {
+    let report = easm::check_stack(&easm::tokenize("caller caller caller pop pop caller").unwrap());
+    assert_eq!((report.height, report.max_height), (2, 3));
+
+    let deep = "caller ".repeat(easm::MAX_STACK_DEPTH + 1);
+    assert_eq!(
+        easm::check_stack(&easm::tokenize(&deep).unwrap()).max_height,
+        1025
+    );
+}
+
+#[test]
+fn include_files() {
+    assert_eq!(
+        easm::assemble_file("tests/fixtures/include/main.easm").unwrap(),
+        "6080604052"
+    );
+
+    let error = easm::assemble_file("tests/fixtures/include/cycle_a.easm").unwrap_err();
+    assert!(error.to_string().contains("cycle"));
+
+    let error = easm::assemble("push1 0x01\n#include \"missing.easm\"").unwrap_err();
+    assert!(error.to_string().contains("line 2"));
+    assert!(error.to_string().contains("missing.easm"));
+}
+
+#[test]
+fn include_paths_are_searched_in_order() {
+    let lex = |include_paths: &[&str]| {
+        let mut lexer = easm::Lexer::new();
+        lexer.include_paths = include_paths.iter().map(Into::into).collect();
+        lexer.lex("#include \"answer.easm\"\nstop").map(|()| lexer)
+    };
+
+    let lexer = lex(&["tests/fixtures/include", "tests/fixtures/prelude"]).unwrap();
+    assert_eq!(lexer.result.len(), 3);
+
+    let error = lex(&["tests/fixtures/include"]).unwrap_err().to_string();
+    assert!(error.contains("can not find answer.easm, tried answer.easm, "));
+    assert!(error.contains("tests/fixtures/include/answer.easm"));
+}
+
+#[test]
+fn listing_shows_offsets() {
+    let tokens = easm::tokenize("push1 0x01\nstart:\npush2 start").unwrap();
+    let instructions = easm::Parser::new(&tokens).parse().unwrap();
+
+    assert_eq!(
+        easm::listing(&instructions),
+        "0x0000  PUSH1 0x01    6001\n0x0002  start:        5b\n0x0003  PUSH2 0x0002  610002\n"
+    );
+}
+
+#[test]
+fn trace_shows_stack_heights() {
+    let tokens = easm::tokenize("push1 0x01\npop pop\nstart:\ncaller").unwrap();
+    let instructions = easm::Parser::new(&tokens).parse().unwrap();
+
+    assert_eq!(
+        easm::trace(&instructions),
+        "0x0000  PUSH1 0x01  [0 -> 1]\n\
+         0x0002  POP         [1 -> 0]\n\
+         0x0003  POP         [0 -> -1]  underflow\n\
+         0x0004  start:      [-1 -> -1]  jump target, jumps may arrive with another height\n\
+         0x0005  CALLER      [-1 -> 0]\n"
+    );
+}
+
+#[test]
+fn gas_listing_keeps_a_running_total() {
+    let tokens = easm::tokenize_file("tests/fixtures/arithmetic.easm").unwrap();
+    let instructions = easm::Parser::new(&tokens).parse().unwrap();
+
+    assert_eq!(
+        easm::gas_listing(&instructions),
+        "0x0000  PUSH1 0x02      3        3\n\
+         0x0002  PUSH1 0x03      3        6\n\
+         0x0004  ADD             3        9\n\
+         0x0005  PUSH1 0x04      3       12\n\
+         0x0007  MUL             5       17\n\
+         0x0008  PUSH1 0x02      3       20\n\
+         0x000a  EXP            10*      20\n\
+         0x000b  PUSH0           2       22\n\
+         0x000c  MSTORE          3       25\n\
+         0x000d  STOP            0       25\n"
+    );
+}
+
+#[test]
+fn coverage_splits_used_and_unused_opcodes() {
+    use easm::Op;
+
+    let tokens = easm::tokenize("start:\npush1 0x01 push1 0x02 add\ncaller selfdestruct").unwrap();
+    let coverage = easm::coverage(&easm::Parser::new(&tokens).parse().unwrap());
+
+    assert_eq!(
+        coverage.used,
+        vec![
+            Op::ADD,
+            Op::CALLER,
+            Op::JUMPDEST,
+            Op::PUSH1,
+            Op::SELFDESTRUCT
+        ]
+    );
+    assert_eq!(coverage.used.len() + coverage.unused.len(), 146);
+    assert!(coverage.unused.contains(&Op::STOP));
+    assert!(!coverage.unused.contains(&Op::ADD));
+
+    let deprecated: Vec<_> = coverage
+        .used
+        .iter()
+        .filter(|op| op.is_deprecated())
+        .collect();
+    assert_eq!(deprecated, [&Op::SELFDESTRUCT]);
+}
+
+#[test]
+fn sizeof_is_the_length_between_two_labels() {
+    // Init code that copies the runtime after it into memory and returns it
+    let source = "push sizeof(runtime, end) dup1 push runtime push0 codecopy push0 return
+        runtime:
+        push1 0x2a push0 mstore push1 0x20 push0 return
+        end:";
+
+    assert_eq!(
+        easm::assemble(source).unwrap(),
+        "61000980600a5f395ff35b602a5f5260205ff35b"
+    );
+    assert_eq!(
+        easm::assemble("push1 sizeof(a,b) a: b:").unwrap(),
+        "60015b5b"
+    );
+
+    let error = easm::assemble("push1 sizeof(b, a) a: b:").unwrap_err();
+    assert!(error.to_string().contains("is negative, a is before b"));
+
+    let error = easm::assemble("push1 sizeof(a, missing) a:").unwrap_err();
+    assert!(matches!(error, easm::AssembleError::UndefinedLabel { .. }));
+
+    assert!(easm::assemble("sizeof(a, a) a:").is_err());
+    assert!(easm::assemble("push1 sizeof(a) a:").is_err());
+    assert!(easm::assemble("push1 sizeof(a,\na) a:").is_err());
+}
+
+#[test]
+fn parser_borrows_tokens_for_a_scope() {
+    let instructions = {
+        // Tokens whose opcode bytes are not 'static, dropped with the parser
+        let byte = String::from("60");
+        let tokens = vec![
+            (easm::Op::PUSH1, byte.as_str(), 1),
+            (easm::Op::VALUE("0x2a".to_string()), "", 1),
+        ];
+
+        let mut parser = easm::Parser::new(&tokens);
+        parser.parse().unwrap()
+    };
+
+    assert_eq!(easm::bytecode(&instructions), [0x60, 0x2a]);
+}
+
+#[test]
+fn bare_push_takes_the_width_of_its_hex_digits() {
+    assert_eq!(easm::assemble("push 0x01").unwrap(), "6001");
+    assert_eq!(easm::assemble("push 0x0001").unwrap(), "610001");
+    assert_eq!(easm::assemble("push 0x0000ff").unwrap(), "620000ff");
+
+    // Decimals still get the fewest bytes that fit them
+    assert_eq!(easm::assemble("push 1 push 256").unwrap(), "6001610100");
+
+    let error = easm::assemble("push 0x001").unwrap_err();
+    assert!(error.to_string().contains("odd number of hex digits"));
+
+    for odd in ["push 0x1", "push 0x123", "push 0x12345"] {
+        let error = easm::assemble(odd).unwrap_err();
+        assert!(
+            error.to_string().contains("odd number of hex digits"),
+            "{}",
+            odd
+        );
+    }
+
+    // One, two and 32 bytes pick PUSH1, PUSH2 and PUSH32
+    assert_eq!(easm::assemble("push 0xff").unwrap(), "60ff");
+    assert_eq!(easm::assemble("push 0xffff").unwrap(), "61ffff");
+    let word = "ab".repeat(32);
+    assert_eq!(
+        easm::assemble(&format!("push 0x{}", word)).unwrap(),
+        format!("7f{}", word)
+    );
+
+    let error = easm::assemble(&format!("push 0x{}00", word)).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("is 33 bytes but a PUSH takes at most 32"));
+
+    // A label takes the fewest bytes that fit its offset
+    assert_eq!(
+        easm::assemble("push end jump end: stop").unwrap(),
+        "6003565b00"
+    );
+    let far = easm::assemble("push end jump .repeat 256 { stop } end: stop").unwrap();
+    assert!(far.starts_with("61010456"));
+}
+
+#[test]
+fn bare_push_of_the_number_zero_is_push0_from_shanghai() {
+    let assemble = |source: &str, fork| {
+        easm::Assembler::builder()
+            .fork(fork)
+            .build()
+            .unwrap()
+            .assemble(source)
+            .unwrap()
+    };
+
+    for fork in easm::Fork::ALL.iter().copied() {
+        let expected = match fork >= easm::Fork::Shanghai {
+            true => "5f",
+            false => "6000",
+        };
+
+        assert_eq!(assemble("push 0", fork), expected, "{:?}", fork);
+        assert_eq!(assemble("push 0b0", fork), expected, "{:?}", fork);
+    }
+
+    // A hex literal keeps the width of its digits, a placeholder stays as wide as written
+    assert_eq!(assemble("push 0x00", easm::Fork::Shanghai), "6000");
+    assert_eq!(assemble("push 0x0000", easm::Fork::Paris), "610000");
+    assert_eq!(assemble("push 0x0000", easm::Fork::Shanghai), "610000");
+    assert_eq!(assemble("push 1", easm::Fork::Shanghai), "6001");
+    assert_eq!(easm::assemble("push 0 push 0x00").unwrap(), "5f6000");
+
+    // Labels after the PUSH0 move back with it
+    let source = "push 0 push here jump here: stop";
+    assert_eq!(assemble(source, easm::Fork::Cancun), "5f6004565b00");
+    assert_eq!(assemble(source, easm::Fork::Paris), "60006005565b00");
+}
+
+#[test]
+fn lint_warns_about_wide_pushes() {
+    let lint = |source: &str| {
+        easm::lint(&easm::lex(source).unwrap())
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        lint("push4 0x00000001\npush3 0x0000ff"),
+        [
+            "PUSH4 on line 1 pushes 0x00000001 which fits in PUSH1",
+            "PUSH3 on line 2 pushes 0x0000ff which fits in PUSH1"
+        ]
+    );
+    assert_eq!(
+        lint("push4 1"),
+        ["PUSH4 on line 1 pushes 0x00000001 which fits in PUSH1"]
+    );
+
+    // A full word, a label, a value that needs the width and a bare PUSH are fine
+    let fine = format!(
+        "push32 0x{}01 push2 start start: push4 0x12345678 push1 0x00 push 0x0001",
+        "00".repeat(31)
+    );
+    assert!(lint(&fine).is_empty());
+}
+
+#[test]
+fn assemble_from_a_reader() {
+    let reader = std::io::Cursor::new("start: push1 start jump\nstop".as_bytes());
+    assert_eq!(easm::assemble_reader(reader).unwrap(), "5b60005600");
+
+    // Source that is not UTF-8 is an error of its own rather than a panic
+    let invalid = std::io::Cursor::new([0x70, 0x75, 0xff]);
+    assert!(easm::assemble_reader(invalid)
+        .unwrap_err()
+        .to_string()
+        .contains("can not read the source"));
+}
+
+#[test]
+fn max_ops_caps_the_tokens() {
+    let lex = |max_ops| {
+        let mut lexer = easm::Lexer::new();
+        lexer.max_ops = Some(max_ops);
+        lexer.lex("push1 0x01\npush1 0x02\nadd").map(|()| lexer)
+    };
+
+    assert_eq!(lex(5).unwrap().result.len(), 5);
+
+    let error = lex(4).unwrap_err();
+    assert_eq!(error.line(), Some(3));
+    assert!(error
+        .to_string()
+        .contains("the program has more than the limit of 4 tokens"));
+
+    let lex = |max_ops, source| {
+        let mut lexer = easm::Lexer::new();
+        lexer.max_ops = Some(max_ops);
+        lexer.lex(source).map(|()| lexer)
+    };
+
+    // A nested .repeat fails before it is expanded, not after billions of tokens
+    let error = lex(
+        1000,
+        ".repeat 1000000 { .repeat 1000000 { .repeat 1000000 { stop } } }",
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("the program has more than the limit of 1000 tokens"));
+
+    assert_eq!(
+        lex(20, ".repeat 4 { .repeat 5 { stop } }")
+            .unwrap()
+            .result
+            .len(),
+        20
+    );
+    assert!(lex(20, "stop .repeat 4 { .repeat 5 { stop } }").is_err());
+}
+
+#[test]
+fn spans_reach_the_instructions() {
+    use easm::{Parser, Span};
+
+    let mut lexer = easm::lex("push1 0x01\n    calldataload stop").unwrap();
+    assert_eq!(
+        lexer.spans[2],
+        Span {
+            line: 2,
+            column: 5,
+            len: 12
+        }
+    );
+
+    easm::Resolver::new().resolve(&mut lexer.result).unwrap();
+    let instructions = Parser::new(&lexer.result)
+        .spans(&lexer.spans)
+        .parse()
+        .unwrap();
+    assert_eq!(instructions[1].span(), lexer.spans[2]);
+    assert_eq!(instructions[2].span().column, 18);
+}
+
+#[test]
+fn ret_and_rev_push_the_length_first() {
+    assert_eq!(easm::assemble("ret(0x00, 0x20)").unwrap(), "60206000f3");
+    assert_eq!(easm::assemble("ret(0x00,0x20)").unwrap(), "60206000f3");
+    assert_eq!(
+        easm::assemble("#define SIZE 4\nrev(0, SIZE)").unwrap(),
+        "60045ffd"
+    );
+
+    assert!(easm::assemble("ret(0x00)").is_err());
+    assert!(easm::assemble("ret(0x00, 0x20, 0x40)").is_err());
+    assert!(easm::assemble("ret(0x00,\n0x20)").is_err());
+}
+
+#[test]
+fn ir_assembles_to_the_same_bytecode() {
+    let source = "#define SIZE 0x20\nmacro ZERO { push1 0x00 }\nstart:\nZERO\npush1 SIZE\n.data 0xbeef\npush start jump";
+    let instructions = easm::Parser::new(&easm::tokenize(source).unwrap())
+        .parse()
+        .unwrap();
+
+    let ir = easm::ir(&instructions);
+    assert_eq!(
+        ir,
+        "JUMPDEST ; start\nPUSH1 0x00\nPUSH1 0x20\n.data 0xbeef\nPUSH1 0x00\nJUMP\n"
+    );
+    assert_eq!(
+        easm::assemble(&ir).unwrap(),
+        easm::to_hex(&easm::bytecode(&instructions))
+    );
+}
+
+#[test]
+fn hex_prefix_needs_hex_digits() {
+    use easm::Op;
+
+    assert_eq!(
+        Op::from_str("0xadd").unwrap().0,
+        Op::VALUE("0xadd".to_string())
+    );
+    assert_eq!(Op::from_str("add").unwrap().0, Op::ADD);
+    assert!(Op::from_str("0xgt")
+        .unwrap_err()
+        .to_string()
+        .contains("0xgt is not a hex literal"));
+
+    // The prefix is only stripped once
+    assert!(easm::assemble("push1 0x0x12").is_err());
+    assert!(easm::assemble("push 0x0x12").is_err());
+    assert!(easm::assemble(".data 0x0x12").is_err());
+
+    assert_eq!(easm::assemble("push2 0x0add").unwrap(), "610add");
+    assert!(easm::assemble("push1 0xgt").is_err());
+    assert!(easm::assemble("push1 0x").is_err());
+    assert!(easm::assemble("0xgt: stop").is_err());
+
+    // An opcode after a bad word is lexed on its own rather than skipped as its operand
+    let mut tokens = easm::Tokens::new("bad_word add");
+    assert!(tokens.next().unwrap().is_err());
+    assert_eq!(tokens.next().unwrap().unwrap().0, Op::ADD);
+}
+
+#[test]
+fn custom_opcodes_are_assembled() {
+    let assemble = |lexer: &mut easm::Lexer, source| {
+        lexer.lex(source).unwrap();
+        let mut tokens = std::mem::take(&mut lexer.result);
+        easm::Resolver::new().resolve(&mut tokens).unwrap();
+
+        easm::assemble_tokens(&tokens).unwrap()
+    };
+
+    let mut lexer = easm::Lexer::new();
+    lexer.add_opcode("FOO", 0xf9, false).unwrap();
+    assert_eq!(assemble(&mut lexer, "push1 0x01 foo stop"), "6001f900");
+
+    // A built-in opcode is only replaced when asked
+    assert!(lexer.add_opcode("add", 0xf9, false).is_err());
+    lexer.add_opcode("add", 0xf9, true).unwrap();
+    assert_eq!(assemble(&mut lexer, "ADD"), "f9");
+
+    assert!(lexer.add_opcode("0xff", 0xf9, false).is_err());
+    assert!(lexer.add_opcodes("FOO 0xf9", false).is_err());
+}
+
+#[test]
+fn assembler_keeps_its_settings() {
+    use easm::{Assembler, Fork};
+
+    let assembler = Assembler::builder()
+        .fork(Fork::London)
+        .define("OWNER", "0x20")
+        .opcode("FOO", 0xf9)
+        .build()
+        .unwrap();
+
+    assert_eq!(assembler.assemble("push1 OWNER foo").unwrap(), "6020f9");
+    assert_eq!(assembler.assemble("foo push1 OWNER").unwrap(), "f96020");
+
+    // The fork and the constants of one program are not seen by the next
+    assert!(assembler.assemble("push0").is_err());
+    assert!(assembler.assemble("#define SIZE 0x01\nstop").is_ok());
+    assert!(assembler.assemble("push1 SIZE").is_err());
+
+    let strict = Assembler::builder().strict(true).build().unwrap();
+    assert!(strict.assemble("push1 0x00 pop").is_ok());
+    assert!(strict.assemble("push2 0x0001 pop").is_err());
+    assert!(strict.assemble("stop caller").is_err());
+
+    // The stack check is strict too, a DUP deeper than the stack fails
+    assert!(strict.assemble("dup3 pop stop").is_err());
+    assert!(strict.assemble("push1 0x00").is_err());
+    assert_eq!(
+        Assembler::builder()
+            .build()
+            .unwrap()
+            .assemble("dup3 pop stop")
+            .unwrap(),
+        "825000"
+    );
+
+    assert!(Assembler::builder().opcode("add", 0xf9).build().is_err());
+}
+
+#[test]
+fn sections_assemble_into_creation_bytecode() {
+    let source = "
+        .init {
+            push1 0x01 push1 0x00 sstore
+        }
+
+        .runtime {
+            push1 done jump
+            done: stop
+        }
+    ";
+
+    let code = easm::assemble(source).unwrap();
+
+    // The init code is followed by PUSH1 length, DUP1, PUSH1 offset, PUSH1 0, CODECOPY,
+    // PUSH1 0 and RETURN. The labels of the runtime start at its own first byte
+    assert_eq!(&code[..10], "6001600055");
+    assert_eq!(&code[10..14], "6005");
+    assert_eq!(&code[16..20], "6010");
+    assert_eq!(&code[0x10 * 2..], "6003565b00");
+    assert_eq!(easm::to_hex(&easm::assemble_bytes(source).unwrap()), code);
+
+    // Every entry point that assembles splits the sections the same way
+    let source = std::fs::read_to_string("tests/fixtures/sections.easm").unwrap();
+    let code = "600150600380600e6000396000f3600200";
+    assert_eq!(easm::assemble(&source).unwrap(), code);
+    assert_eq!(
+        easm::assemble_file("tests/fixtures/sections.easm").unwrap(),
+        code
+    );
+    assert_eq!(
+        easm::assemble_reader(std::io::Cursor::new(&source)).unwrap(),
+        code
+    );
+
+    let assembler = easm::Assembler::builder().build().unwrap();
+    assert_eq!(assembler.assemble(&source).unwrap(), code);
+
+    let strict = easm::Assembler::builder().strict(true).build().unwrap();
+    assert!(strict.assemble(".init { } .runtime { stop }").is_ok());
+    assert!(strict
+        .assemble(".init { stop push1 0x01 } .runtime { stop }")
+        .is_err());
+
+    // Tokens have no way to tell the sections apart
+    let error = easm::tokenize(&source).unwrap_err();
+    assert!(error.to_string().contains("can only be assembled"));
+
+    assert!(easm::assemble(".init { push1 0x01 }").is_err());
+    assert!(easm::assemble_bytes(".init { push1 0x01 }").is_err());
+    assert!(easm::assemble("push1 0x01 .runtime { stop }").is_err());
+    assert!(easm::assemble(".runtime { stop } .init { stop }").is_err());
+    assert!(easm::assemble(".runtime { .init { stop } }").is_err());
+}
+
+#[test]
+fn constants_can_be_used_above_their_define() {
+    let source = "
+        push1 SLOT sload
+        #ifdef SLOT
+        push1 0x01
+        #endif
+        #define SLOT 0x02
+    ";
+
+    // An #ifdef only sees the constants defined above it
+    assert_eq!(easm::assemble(source).unwrap(), "600254");
+
+    assert!(
+        easm::assemble("push1 SLOT\n#define SLOT 0x02\n#define SLOT 0x03")
+            .unwrap_err()
+            .to_string()
+            .contains("constant SLOT is already defined")
+    );
+    assert!(easm::assemble("push1 SLOT")
+        .unwrap_err()
+        .to_string()
+        .contains("label SLOT used on line 1 is not defined"));
+}
+
+#[test]
+fn push_operands_must_fill_their_width() {
+    let error = |source: &str| easm::assemble(source).unwrap_err().to_string();
+
+    assert!(error("push1 0x0102").contains("operand 0x0102 of PUSH1 must be 1 bytes"));
+    assert!(error("push2 0x01").contains("operand 0x01 of PUSH2 must be 2 bytes"));
+    assert!(error("push4 0x000001").contains("must be 4 bytes"));
+
+    // PUSH32 takes a full word and nothing more
+    let word = "ff".repeat(32);
+    assert_eq!(
+        easm::assemble(&format!("push32 0x{}", word)).unwrap(),
+        format!("7f{}", word)
+    );
+    assert!(
+        error(&format!("push32 0x{}ff", word)).contains("is 33 bytes but a PUSH takes at most 32")
+    );
+    assert!(error(&format!("push31 0x{}", word)).contains("must be 31 bytes"));
+}
+
+#[test]
+fn decimal_push_operands() {
+    assert_eq!(easm::assemble("push1 255").unwrap(), "60ff");
+    assert_eq!(easm::assemble("push2 258").unwrap(), "610102");
+    assert_eq!(easm::assemble("push 256").unwrap(), "610100");
+    assert!(easm::assemble("push1 256").is_err());
+}
+
+#[test]
+fn storage_opcodes() {
+    assert_eq!(
+        easm::assemble("push1 0x2a push1 0x00 sstore").unwrap(),
+        "602a600055"
+    );
+    assert_eq!(easm::assemble("push1 0x00 sload").unwrap(), "600054");
+}
+
+#[test]
+fn calldata_opcodes() {
+    assert_eq!(easm::assemble("calldatasize").unwrap(), "36");
+    assert_eq!(easm::assemble("push1 0x00 calldataload").unwrap(), "600035");
+    assert_eq!(easm::assemble("CALLDATACOPY").unwrap(), "37");
+}
+
+#[test]
+fn push0_takes_no_operand() {
+    assert_eq!(easm::assemble("push0 push1 0x01 add").unwrap(), "5f600101");
+    assert_eq!(easm::disassemble(&[0x5f, 0x00]), "PUSH0 STOP");
+}
+
+#[test]
+fn mcopy_program() {
+    assert_eq!(
+        easm::assemble("push1 0x20 push1 0x00 push1 0x40 mcopy").unwrap(),
+        "6020600060405e"
+    );
+}
+
+#[test]
+fn push_label_operands_take_the_push_width() {
+    assert_eq!(easm::assemble("push2 end jump end:").unwrap(), "610004565b");
+    assert_eq!(easm::assemble("push1 end jump end:").unwrap(), "6003565b");
+    assert_eq!(
+        easm::assemble("push3 end jump end:").unwrap(),
+        "62000005565b"
+    );
+
+    let padding = format!(".data 0x{}", "00".repeat(300));
+    let error = easm::assemble(&format!("push1 end {} end:", padding)).unwrap_err();
+    assert!(error.to_string().contains("label end"));
+}
+
+#[test]
+fn push_string_literals() {
+    assert_eq!(easm::assemble("push \"hi\"").unwrap(), "616869");
+    assert_eq!(
+        easm::assemble("push \"a b\" ; comment").unwrap(),
+        "62612062"
+    );
+    assert_eq!(easm::assemble("push2 \"a\"").unwrap(), "610061");
+    assert_eq!(
+        easm::assemble(r#"push "\n\"" push ";//""#).unwrap(),
+        "610a22623b2f2f"
+    );
+
+    assert!(easm::assemble(&format!("push \"{}\"", "a".repeat(33))).is_err());
+    assert!(easm::assemble("push1 \"ab\"").is_err());
+    assert!(easm::assemble("\"hi\"").is_err());
+    assert!(easm::assemble("push \"hi").is_err());
+}
+
+#[test]
+fn push_character_literals() {
+    assert_eq!(easm::assemble("push1 'A'").unwrap(), "6041");
+    assert_eq!(easm::assemble("push2 'A' push 'z'").unwrap(), "610041607a");
+    assert_eq!(
+        easm::assemble(r"push1 '\n' push1 '\t' push1 '\0' push1 '\''").unwrap(),
+        "600a600960006027"
+    );
+    assert_eq!(
+        easm::assemble("push1 ' ' push1 ';' ; it's a comment").unwrap(),
+        "6020603b"
+    );
+
+    assert!(easm::assemble("push1 'AB'").is_err());
+    assert!(easm::assemble("push1 'é'").is_err());
+    assert!(easm::assemble("push1 ''").is_err());
+    assert!(easm::assemble("push1 'A").is_err());
+}
+
+#[test]
+fn underscores_group_digits() {
+    assert_eq!(easm::assemble("push4 1_000_000").unwrap(), "63000f4240");
+    assert_eq!(easm::assemble("push4 0xdead_beef").unwrap(), "63deadbeef");
+    assert_eq!(easm::assemble("push1 0b1010_0101").unwrap(), "60a5");
+    assert_eq!(easm::assemble("push 1_000").unwrap(), "6103e8");
+    assert_eq!(easm::assemble("push1 -1_0").unwrap(), "60f6");
+    assert_eq!(easm::assemble("push2 1_000 + 2").unwrap(), "6103ea");
+
+    for source in ["push2 1__000", "push2 1000_", "push2 0x_ff", "push1 -1_"] {
+        let error = easm::assemble(source).unwrap_err();
+        assert!(error.to_string().contains("misplaced _"), "{}", source);
+    }
+}
+
+#[test]
+fn errors_point_at_the_token() {
+    let error = easm::assemble("push1 0x01\n  pish1 0x02").unwrap_err();
+
+    assert!(error
+        .to_string()
+        .ends_with("pish1 is neither a known opcode nor a valid literal\n  pish1 0x02\n  ^^^^^"));
+}
+
+#[test]
+fn duplicate_labels_are_rejected() {
+    let error = easm::assemble("start:\npush1 0x01\nstart:").unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("label start is defined on line 1 and again on line 3"));
+}
+
+#[test]
+fn undefined_labels_are_rejected() {
+    let error = easm::assemble("start:\npush2 typo_label\njump").unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("label typo_label used on line 2 is not defined"));
+}
+
+#[test]
+fn fork_targeting() {
+    let tokens = easm::tokenize("push1 0x01\npush0").unwrap();
+
+    let error = easm::check_fork(&tokens, easm::Fork::London).unwrap_err();
+    assert!(error.to_string().contains(
+        "line 2 with description PUSH0 needs the shanghai fork but the target is london"
+    ));
+
+    assert!(easm::check_fork(&tokens, easm::Fork::Shanghai).is_ok());
+    assert!(easm::check_fork(&tokens, easm::Fork::default()).is_ok());
+    assert_eq!(easm::Fork::from_name("merge"), Some(easm::Fork::Paris));
+}
+
+#[test]
+fn data_directive() {
+    assert_eq!(
+        easm::assemble("push2 end jump end: .data 0xdeadbeef").unwrap(),
+        "610004565bdeadbeef"
+    );
+    assert_eq!(easm::assemble(".data cafe stop").unwrap(), "cafe00");
+
+    assert!(easm::assemble(".data 0xabc").is_err());
+    assert!(easm::assemble(".data 0xzz").is_err());
+    assert!(easm::assemble(".data").is_err());
+}
+
+#[test]
+fn raw_bytes_keep_label_offsets() {
+    assert_eq!(
+        easm::assemble("push1 0x01 .raw 6002 [6003] push2 end jump end:").unwrap(),
+        "60016002600361000a565b"
+    );
+
+    assert!(easm::assemble("[abc]").is_err());
+    assert!(easm::assemble(".raw 0xgg").is_err());
+}
+
+#[test]
+fn lexer_does_not_keep_the_source() {
+    let mut lexer = easm::Lexer::new();
+
+    {
+        let source = String::from("push1 0x01");
+        lexer.lex(&source).unwrap();
+    }
+
+    assert_eq!(lexer.result.len(), 2);
+}
+
+#[test]
+fn dangling_push_is_rejected() {
+    let error = easm::assemble("push1 0x01\npush1").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("PUSH1 on line 2 expects an operand"));
+
+    let error = easm::assemble("push2\nadd").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("PUSH2 on line 1 expects an operand"));
+
+    let error = easm::assemble("push").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("PUSH on line 1 expects an operand"));
+
+    assert_eq!(easm::assemble("push0").unwrap(), "5f");
+}
+
+#[test]
+fn parser_groups_operands_with_their_push() {
+    let tokens = easm::tokenize("push1 0x80 mstore").unwrap();
+    let instructions = easm::Parser::new(&tokens).parse().unwrap();
+
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[0].op, easm::Op::PUSH1);
+    assert_eq!(instructions[0].operand, Some(vec![0x80]));
+    assert_eq!(easm::bytecode(&instructions), vec![0x60, 0x80, 0x52]);
+}
+
+#[test]
+fn stop_after_push_is_an_opcode() {
+    assert_eq!(easm::assemble("push1 0x01 stop").unwrap(), "600100");
+}
+
+#[test]
+fn assemble_prebuilt_instructions() {
+    use easm::{Instruction, Op};
+
+    let instruction = |op: Op, operand: Option<Vec<u8>>| Instruction {
+        opcode: op.opcode(),
+        op,
+        operand,
+        line: 0,
+        column: 0,
+        len: 0,
+    };
+
+    let program = [
+        instruction(Op::PUSH1, Some(vec![0x2a])),
+        instruction(Op::LABEL("end".to_string()), None),
+        instruction(Op::STOP, None),
+    ];
+    assert_eq!(
+        easm::assemble_ops(&program).unwrap(),
+        [0x60, 0x2a, 0x5b, 0x00]
+    );
+
+    assert!(easm::assemble_ops(&[instruction(Op::PUSH2, Some(vec![0x2a]))]).is_err());
+    assert!(easm::assemble_ops(&[instruction(Op::ADD, Some(vec![0x2a]))]).is_err());
+}
+
+#[test]
+fn negative_decimal_operands() {
+    assert_eq!(
+        easm::assemble("push32 -1").unwrap(),
+        format!("7f{}", "ff".repeat(32))
+    );
+    assert_eq!(easm::assemble("push1 -1 push2 -2").unwrap(), "60ff61fffe");
+    assert_eq!(easm::assemble("push1 -128").unwrap(), "6080");
+    assert_eq!(
+        easm::assemble("push -1").unwrap(),
+        format!("7f{}", "ff".repeat(32))
+    );
+    assert_eq!(easm::assemble("push1 -0").unwrap(), "6000");
+
+    assert!(easm::assemble("push1 -129").is_err());
+    assert!(easm::assemble("push1 -256").is_err());
+}
+
+#[test]
+fn code_after_a_terminator_is_unreachable() {
+    let instructions = |source| {
+        easm::Parser::new(&easm::tokenize(source).unwrap())
+            .parse()
+            .unwrap()
+    };
+
+    let unreachable = easm::find_unreachable(&instructions("push1 0x00\nstop\nadd")).unwrap();
+    assert_eq!(unreachable.line, 3);
+    assert_eq!(unreachable.op, easm::Op::ADD);
+    assert_eq!(unreachable.after, easm::Op::STOP);
+
+    assert_eq!(
+        easm::find_unreachable(&instructions("stop\nback: push1 0x00 .data 0x01")),
+        None
+    );
+    assert_eq!(
+        easm::find_unreachable(&instructions("revert .data 0x0102")),
+        None
+    );
+}
+
+#[test]
+fn peephole_optimizer() {
+    let optimize = |source, fork| {
+        let mut lexer = easm::lex(source).unwrap();
+        let saved = easm::optimize(&mut lexer, fork);
+
+        let mut resolver = easm::Resolver::new();
+        resolver.resolve(&mut lexer.result).unwrap();
+
+        (easm::assemble_tokens(&lexer.result).unwrap(), saved)
+    };
+
+    assert_eq!(
+        optimize("push1 0x00 dup1 pop swap1 swap1 stop", easm::Fork::Cancun),
+        ("5f00".to_string(), 5)
+    );
+    assert_eq!(
+        optimize("push1 0x00 stop", easm::Fork::London),
+        ("600000".to_string(), 0)
+    );
+
+    // A bare PUSH of 0x0000 is only a PUSH0 when optimized, the number zero already is
+    assert_eq!(
+        optimize("push 0x0000 stop", easm::Fork::Cancun),
+        ("5f00".to_string(), 2)
+    );
+    assert_eq!(
+        optimize("push 0 stop", easm::Fork::Cancun),
+        ("5f00".to_string(), 0)
+    );
+
+    // Removing SWAP1 SWAP1 makes a DUP1 POP, the label moves back by both pairs
+    assert_eq!(
+        optimize(
+            "dup1 swap1 swap1 pop push1 end end: stop",
+            easm::Fork::Cancun
+        ),
+        ("60025b00".to_string(), 4)
+    );
+
+    // A label between the two halves of a pattern keeps both
+    assert_eq!(
+        optimize("dup1 here: pop", easm::Fork::Cancun),
+        ("805b50".to_string(), 0)
+    );
+}
+
+#[test]
+fn constant_folding() {
+    let optimize = |source, fork| {
+        let mut lexer = easm::lex(source).unwrap();
+        let saved = easm::optimize(&mut lexer, fork);
+
+        let mut resolver = easm::Resolver::new();
+        resolver.resolve(&mut lexer.result).unwrap();
+
+        (easm::assemble_tokens(&lexer.result).unwrap(), saved)
+    };
+
+    let cancun = easm::Fork::Cancun;
+    assert_eq!(
+        optimize("push1 0x02 push1 0x03 add", cancun),
+        ("6005".to_string(), 3)
+    );
+    assert_eq!(
+        optimize("push1 3 push1 5 sub", cancun),
+        ("6002".to_string(), 3)
+    );
+    assert_eq!(
+        optimize("push1 2 push1 0x10 div", cancun),
+        ("6008".to_string(), 3)
+    );
+    assert_eq!(optimize("push1 0 push1 5 div", cancun).0, "5f");
+    assert_eq!(
+        optimize("push2 0x0100 push1 0xff mul", cancun),
+        ("61ff00".to_string(), 3)
+    );
+
+    // Chains fold one pair at a time, a zero result is a PUSH0 only from Shanghai on
+    assert_eq!(
+        optimize("push1 1 push1 2 push1 3 add add", cancun).0,
+        "6006"
+    );
+    assert_eq!(
+        optimize("push1 4 push1 4 sub", easm::Fork::London),
+        ("6000".to_string(), 3)
+    );
+
+    // 0 - 1 is 32 bytes of 0xff which is larger than the original, only the zero shrinks
+    assert_eq!(
+        optimize("push1 1 push1 0 sub", cancun),
+        ("60015f03".to_string(), 1)
+    );
+
+    // Label references are not constants
+    assert_eq!(
+        optimize("push1 1 push1 end add end: stop", cancun).0,
+        "60016005015b00"
+    );
+}
+
+#[test]
+fn numeric_local_labels() {
+    // A loop back to the nearest 1: before, and a jump forward past the loop
+    assert_eq!(
+        easm::assemble("1: push2 1b jump 1: push2 1f jump 1: stop").unwrap(),
+        "5b610000565b61000a565b00"
+    );
+
+    // Mixed with named labels, and 2f stays a hex literal without a 2:
+    assert_eq!(
+        easm::assemble("start: push1 1f push1 2f 1: push2 start").unwrap(),
+        "5b6005602f5b610000"
+    );
+
+    assert!(easm::assemble("push2 1f jump 1: stop push2 1f").is_err());
+}
+
+#[test]
+fn org_pads_to_the_offset() {
+    assert_eq!(
+        easm::assemble("push2 target jump .org 0x10 target: stop").unwrap(),
+        format!("61001056{}5b00", "00".repeat(12))
+    );
+    assert_eq!(easm::assemble(".org 4").unwrap(), "00000000");
+
+    assert!(easm::assemble("push1 0x01 .org 0x02").is_ok());
+    assert!(easm::assemble("push2 0x0102 .org 0x02").is_err());
+}
+
+#[test]
+fn align_pads_to_a_multiple() {
+    assert_eq!(
+        easm::assemble("push1 0x01 caller .align 4 end: push1 end").unwrap(),
+        "600133005b6004"
+    );
+    assert_eq!(
+        easm::assemble("push1 0x01 .align 8 0xfe").unwrap(),
+        "6001fefefefefefe"
+    );
+    assert_eq!(easm::assemble("caller .align 1").unwrap(), "33");
+
+    assert!(easm::assemble(".align 0").is_err());
+    assert!(easm::assemble(".align 4 0xfefe").is_err());
+}
+
+#[test]
+fn error_kinds_can_be_matched() {
+    use easm::AssembleError;
+
+    assert!(matches!(
+        easm::assemble("push1 0x01\npish1 0x02"),
+        Err(AssembleError::UnknownToken { line: Some(2), token, .. }) if token == "pish1"
+    ));
+    assert!(matches!(
+        easm::assemble("push2 0x01"),
+        Err(AssembleError::BadOperandWidth {
+            line: Some(1),
+            expected: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        easm::assemble("push1 300"),
+        Err(AssembleError::BadOperandWidth { expected: 1, .. })
+    ));
+    assert!(matches!(
+        easm::assemble("push2 nowhere"),
+        Err(AssembleError::UndefinedLabel { label, .. }) if label == "nowhere"
+    ));
+    assert!(matches!(
+        easm::assemble("start:\nstart:"),
+        Err(AssembleError::DuplicateLabel {
+            line: Some(2),
+            first: 1,
+            ..
+        })
+    ));
+    assert!(matches!(
+        easm::assemble(".data 0xabc"),
+        Err(AssembleError::OddLengthHex { line: Some(1), .. })
+    ));
+
+    // Other errors keep the error of the stage they come from
+    assert!(matches!(
+        easm::assemble("macro m {"),
+        Err(AssembleError::Lex(_))
+    ));
+}
+
+#[test]
+fn binary_and_octal_operands() {
+    assert_eq!(easm::assemble("push1 0b1010").unwrap(), "600a");
+    assert_eq!(easm::assemble("push2 0o777").unwrap(), "6101ff");
+    assert_eq!(easm::assemble("push 0b100000000").unwrap(), "610100");
+    assert_eq!(easm::assemble("push1 010").unwrap(), "600a");
+
+    let error = easm::assemble("push1 0b102").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("0b102 is not a valid binary literal"));
+    assert!(easm::assemble("push1 0o8").is_err());
+    assert!(easm::assemble("push1 0b").is_err());
+}
+
+#[test]
+fn operands_wider_than_32_bytes() {
+    let literal = format!("0x{}", "ab".repeat(33));
+
+    for source in [
+        format!("push {}", literal),
+        format!("push32 {}", literal),
+        format!("push {}", "9".repeat(80)),
+    ] {
+        let error = easm::assemble(&source).unwrap_err();
+
+        assert!(matches!(
+            error,
+            easm::AssembleError::BadOperandWidth { expected: 32, .. }
+        ));
+        assert!(error
+            .to_string()
+            .contains("bytes but a PUSH takes at most 32"));
+    }
+
+    let error = easm::assemble(&format!("push {}", literal)).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains(&format!("{} of PUSH on line 1 is 33 bytes", literal)));
+}
+
+#[test]
+fn selector_directive() {
+    assert_eq!(
+        easm::assemble("#selector \"transfer(address,uint256)\" eq").unwrap(),
+        "63a9059cbb14"
+    );
+    assert_eq!(
+        easm::assemble("#selector \"balanceOf(address)\"").unwrap(),
+        "6370a08231"
+    );
+
+    assert!(easm::assemble("#selector transfer").is_err());
+}
+
+#[test]
+fn constant_jumps_must_land_on_a_jumpdest() {
+    let check = |source| {
+        let instructions = easm::Parser::new(&easm::tokenize(source).unwrap())
+            .parse()
+            .unwrap();
+
+        easm::check_jumps(&instructions)
+            .into_iter()
+            .map(|jump| (jump.line, jump.target))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(check("push1 0x03 jump jumpdest"), vec![]);
+    assert_eq!(check("push1 0x02 jump\njumpdest"), vec![(1, 2)]);
+    assert_eq!(
+        check("caller push1 0x00 jumpi\nend: push2 end jump"),
+        vec![(1, 0)]
+    );
+
+    // A JUMPDEST byte inside data or an operand is not a JUMPDEST
+    assert_eq!(check("push1 0x03 jump .data 0x5b"), vec![(1, 3)]);
+}
+
+#[test]
+fn control_flow_of_constant_jumps() {
+    let instructions = easm::Parser::new(
+        &easm::tokenize("start: push1 start jump\ncaller jump\nend: push2 end jumpi").unwrap(),
+    )
+    .parse()
+    .unwrap();
+
+    let flow = easm::control_flow(&instructions);
+    assert_eq!(
+        flow.targets.into_iter().collect::<Vec<_>>(),
+        vec![(0x00, vec![0x03]), (0x06, vec![0x0a])]
+    );
+    assert_eq!(flow.dynamic, vec![0x05]);
+}
+
+#[test]
+fn every_error_is_reported() {
+    use easm::{AssembleError, Op};
+
+    let error = easm::assemble("caller\npish1\npush1 300\n.data 0xabc").unwrap_err();
+    let lines: Vec<_> = error
+        .errors()
+        .iter()
+        .map(|error| match error {
+            AssembleError::UnknownToken { line, .. }
+            | AssembleError::BadOperandWidth { line, .. }
+            | AssembleError::OddLengthHex { line, .. } => *line,
+            error => panic!("unexpected error {}", error),
+        })
+        .collect();
+    assert_eq!(lines, vec![Some(2), Some(3), Some(4)]);
+    assert_eq!(error.to_string().matches("Error when lexing").count(), 3);
+
+    let error = easm::assemble_tokens(&[
+        (Op::VALUE("0x01".to_string()), "", 1),
+        (Op::PUSH1, "60", 2),
+        (Op::CALLER, "33", 3),
+        (Op::PUSH2, "61", 4),
+        (Op::VALUE("0x01".to_string()), "", 4),
+    ])
+    .unwrap_err();
+    assert_eq!(error.errors().len(), 3);
+
+    // A single error is not wrapped
+    assert!(matches!(
+        easm::assemble("pish1"),
+        Err(AssembleError::UnknownToken { .. })
+    ));
+}
+
+#[test]
+fn repeat_expands_its_block() {
+    assert_eq!(
+        easm::assemble(".repeat 3 { push1 0x00 }").unwrap(),
+        "600060006000"
+    );
+    assert_eq!(
+        easm::assemble("caller .repeat 0 { push1 0x00 } stop").unwrap(),
+        "3300"
+    );
+    assert_eq!(
+        easm::assemble(".repeat 2 {\ncaller .repeat 0x2 { pop }\n}").unwrap(),
+        "335050335050"
+    );
+
+    // Labels in a repeated block are defined again on every expansion
+    assert!(easm::assemble(".repeat 2 { start: }").is_err());
+    assert!(easm::assemble(".repeat 2 { pop").is_err());
+    assert!(easm::assemble(".repeat two { pop }").is_err());
+
+    // A bad count is one error, the block it would repeat is skipped
+    for source in [
+        ".repeat zz { stop }",
+        ".repeat zz {\nstop\n}",
+        ".repeat { stop }",
+    ] {
+        let error = easm::assemble(source).unwrap_err();
+        assert_eq!(error.errors().len(), 1, "{}", source);
+        assert!(error.to_string().contains("count"), "{}", source);
+    }
+
+    let error = easm::assemble(".repeat zz { stop } pish").unwrap_err();
+    assert_eq!(error.errors().len(), 2);
+    assert!(error
+        .to_string()
+        .contains(".repeat expects a hex or decimal count but got zz"));
+}
+
+#[test]
+fn operand_expressions_of_labels() {
+    assert_eq!(
+        easm::assemble("push1 end - start\nstart: caller caller\nend:").unwrap(),
+        "60035b33335b"
+    );
+    assert_eq!(easm::assemble("push2 end + 2 end:").unwrap(), "6100055b");
+    assert_eq!(
+        easm::assemble("push end - start start: end:").unwrap(),
+        "6100015b5b"
+    );
+    assert_eq!(easm::assemble("push1 0x10 - 2 + 0b1").unwrap(), "600f");
+
+    assert!(matches!(
+        easm::assemble("push1 end - nowhere end:"),
+        Err(easm::AssembleError::UndefinedLabel { label, .. }) if label == "nowhere"
+    ));
+    assert!(easm::assemble("push1 start - end start: end:").is_err());
+    assert!(matches!(
+        easm::assemble("push1 end + 0x100 end:"),
+        Err(easm::AssembleError::BadOperandWidth { expected: 1, .. })
+    ));
+    assert!(easm::assemble("push1 end - end:").is_err());
+}
+
+#[test]
+fn size_limits() {
+    assert!(easm::size_warnings(easm::MAX_CODE_SIZE, Some(easm::MAX_INITCODE_SIZE)).is_empty());
+    assert_eq!(easm::size_warnings(easm::MAX_CODE_SIZE + 1, None).len(), 1);
+    assert_eq!(
+        easm::size_warnings(1, Some(easm::MAX_INITCODE_SIZE + 1))[0].message,
+        "the init code is 49153 bytes, more than the 49152 byte limit of EIP-3860"
+    );
+
+    // A program of no bytes is warned about too
+    assert_eq!(easm::size_warnings(0, None)[0].code, "EmptyProgram");
+}
+
+#[test]
+fn conditional_assembly() {
+    let source = "#ifdef DEBUG\ncaller\n#else\norigin\n#endif\nstop";
+    assert_eq!(easm::assemble(source).unwrap(), "3200");
+    assert_eq!(
+        easm::assemble(&format!("#define DEBUG 1\n{}", source)).unwrap(),
+        "3300"
+    );
+
+    // A false block is skipped as a whole, nested blocks included
+    let nested = "#define A 1\n#ifdef A\n#ifdef B\ncaller\n#else\norigin\n#endif\n#else\n#ifdef A\nstop\n#endif\n\"unclosed\n#endif";
+    assert_eq!(easm::assemble(nested).unwrap(), "32");
+
+    assert!(easm::assemble("#ifdef A\ncaller").is_err());
+    assert!(easm::assemble("#endif").is_err());
+    assert!(easm::assemble("#ifdef A\n#else\n#else\n#endif").is_err());
+    assert!(easm::assemble("#ifdef\n#endif").is_err());
+}
+
+#[test]
+fn tokens_stream_lazily() {
+    use easm::{Op, Span, Tokens};
+
+    let mut tokens = Tokens::new("push1 0x01\n  caller\nbad_word\nstop");
+    assert_eq!(
+        tokens.next().unwrap().unwrap(),
+        (
+            Op::PUSH1,
+            Span {
+                line: 1,
+                column: 1,
+                len: 5
+            }
+        )
+    );
+    assert_eq!(
+        tokens.next().unwrap().unwrap(),
+        (
+            Op::VALUE("0x01".to_string()),
+            Span {
+                line: 1,
+                column: 7,
+                len: 4
+            }
+        )
+    );
+    assert_eq!(
+        tokens.next().unwrap().unwrap(),
+        (
+            Op::CALLER,
+            Span {
+                line: 2,
+                column: 3,
+                len: 6
+            }
+        )
+    );
+    assert!(tokens.next().unwrap().is_err());
+    assert_eq!(tokens.next().unwrap().unwrap().0, Op::STOP);
+    assert!(tokens.next().is_none());
+
+    // Only the lines the first token needs are read, a later error is not seen yet
+    let mut tokens = Tokens::new("caller\norigin\npop\npop\npop\n\"unclosed");
+    assert_eq!(tokens.next().unwrap().unwrap().0, Op::CALLER);
+
+    let ops: Vec<Op> = Tokens::new("push \"ab\" macro m { pop }\nm .repeat 2 { m }")
+        .map(|token| token.unwrap().0)
+        .collect();
+    assert_eq!(
+        ops,
+        vec![
+            Op::PUSH2,
+            Op::VALUE("0x6162".to_string()),
+            Op::POP,
+            Op::POP,
+            Op::POP
+        ]
+    );
+}
+
+#[test]
+fn bitwise_and_shift_operations() {
+    assert_eq!(
+        easm::assemble("push1 0x0f push1 0xf0 or push1 0x04 shr not iszero").unwrap(),
+        "600f60f01760041c1915"
+    );
+    assert_eq!(easm::disassemble(&[0x1b, 0x1d]), "SHL SAR");
+}
+
+#[test]
+fn balance_of_the_contract() {
+    assert_eq!(easm::assemble("address balance").unwrap(), "3031");
+}
+
+#[test]
+fn code_ends_with_a_terminator() {
+    let ends = |source| {
+        easm::ends_with_terminator(
+            &easm::Parser::new(&easm::tokenize(source).unwrap())
+                .parse()
+                .unwrap(),
+        )
+    };
+
+    assert!(ends("caller return"));
+    assert!(ends("stop .data 0x00"));
+    assert!(!ends("caller"));
+    assert!(!ends(""));
+}
+
+#[test]
+fn huge_directive_offsets_are_rejected() {
+    assert!(easm::assemble(".org 0xffffffffffffffff").is_err());
+    assert!(easm::assemble("caller .align 0xffffffffffffffff").is_err());
+    assert!(easm::assemble(".repeat 0xffffffffffff { pop }").is_err());
+
+    // Each count is in range, their product is not
+    assert!(
+        easm::assemble(".repeat 0x10000 { .repeat 0x10000 { .repeat 0x10000 { pop } } }")
+            .unwrap_err()
+            .to_string()
+            .contains("the program expands to more than 1048576 tokens")
+    );
+}
+
+fn object(source: &str) -> easm::Object {
+    let mut lexer = easm::lex(source).unwrap();
+    let mut resolver = easm::Resolver::new().relocatable();
+    resolver.resolve(&mut lexer.result).unwrap();
+
+    let instructions = easm::Parser::new(&lexer.result).parse().unwrap();
+    easm::Object::new(&instructions, &resolver)
+}
+
+#[test]
+fn objects_link_to_their_final_offsets() {
+    let first = object(
+        "main: push2 transfer jump\n1: push1 1b push1 end - main push1 main + 0x01 stop end:",
+    );
+    let second = object("transfer: push2 main jump");
+
+    assert_eq!(first.symbols.keys().collect::<Vec<_>>(), ["end", "main"]);
+
+    // The second object goes first, so every offset of the first moves by 5 bytes
+    // except the difference of two of its labels
+    assert_eq!(
+        easm::to_hex(&easm::link(&[second.clone(), first.clone()]).unwrap()),
+        "5b61000556".to_string() + "5b610000565b600a600d6006005b"
+    );
+
+    match easm::link(std::slice::from_ref(&first)).unwrap_err() {
+        easm::AssembleError::UndefinedLabel { label, .. } => assert_eq!(label, "transfer"),
+        error => panic!("expected an undefined label, got {}", error),
+    }
+
+    let error = easm::link(&[first, second.clone(), second]).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("label transfer is defined in object 2 and again in object 3"));
+}
+
+#[test]
+fn errors_know_where_they_are() {
+    let error = easm::assemble("stop\n  push1 0x01 pish1").unwrap_err();
+
+    assert_eq!(
+        (error.line(), error.column(), error.code()),
+        (Some(2), Some(14), "UnknownToken")
+    );
+
+    let error = easm::assemble("push2 nowhere").unwrap_err();
+    assert_eq!((error.line(), error.code()), (Some(1), "UndefinedLabel"));
+}
+
+#[test]
+fn label_pushes_take_the_smallest_width() {
+    assert_eq!(easm::assemble("push end stop end:").unwrap(), "6003005b");
+    assert_eq!(
+        easm::assemble("start: push start jump").unwrap(),
+        "5b600056"
+    );
+
+    let code = easm::assemble("push end .org 0xff end:").unwrap();
+    assert_eq!(code[..4], *"60ff");
+
+    // The first PUSH grows past 0xff, which moves `near` past 0xff too
+    let code = easm::assemble("push far push near .repeat 251 { stop } near: far:").unwrap();
+    assert_eq!(code[..12], *"610102610101");
+    assert_eq!(code[12..], "00".repeat(251) + "5b5b");
+}
+
+#[test]
+fn byte_order_mark_and_windows_line_endings() {
+    assert_eq!(
+        easm::assemble("\u{feff}push1 0x01\r\npush1 0x02\radd\r\n").unwrap(),
+        "6001600201"
+    );
+
+    let error = easm::assemble("\u{feff}stop\r\n\r\n\rpish1\r\n").unwrap_err();
+    assert_eq!(error.line(), Some(4));
+}
@@ -0,0 +1,876 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Run the easm binary with `args`, piping `source` to stdin, and return stdout
+fn easm(args: &[&str], source: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn hex_prefix_only_with_flag() {
+    assert_eq!(easm(&[], "push1 0x80"), "6080\n");
+    assert_eq!(easm(&["-x"], "push1 0x80"), "0x6080\n");
+    assert_eq!(easm(&["--hex-prefix"], "push1 0x80"), "0x6080\n");
+}
+
+#[test]
+fn unknown_flag_prints_usage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .arg("--bogus")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Usage"));
+}
+
+#[test]
+fn binary_format_writes_raw_bytes() {
+    assert_eq!(easm(&["--format", "bin"], "push1 0x41"), "`A");
+}
+
+#[test]
+fn stats_counts_opcodes() {
+    assert_eq!(
+        easm(&["--stats"], "push1 0x80 push1 0x40 mstore"),
+        "size: 5 bytes\ninstructions: 3\n  PUSH1   2\n  MSTORE  1\n"
+    );
+
+    let large = format!("push32 0x{} ", "01".repeat(32)).repeat(800);
+    assert!(easm(&["--stats"], &large).contains("EIP-170"));
+}
+
+#[test]
+fn multiple_files_are_assembled_in_order() {
+    assert_eq!(
+        easm(
+            &[
+                "tests/fixtures/concat/prelude.easm",
+                "tests/fixtures/concat/body.easm"
+            ],
+            ""
+        ),
+        "610004565b\n"
+    );
+}
+
+#[test]
+fn tokens_as_json() {
+    assert_eq!(
+        easm(&["--tokens-json"], "push1 0x80"),
+        "[\n  {\"op\": \"PUSH1\", \"byte\": \"60\", \"line\": 1},\n  {\"op\": \"VALUE\", \"value\": \"0x80\", \"byte\": \"\", \"line\": 1}\n]\n"
+    );
+}
+
+#[test]
+fn verbose_traces_to_stderr() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .arg("tests/fixtures/concat/body.easm")
+            .output()
+            .unwrap()
+    };
+
+    let quiet = run(&[]);
+    assert!(quiet.stderr.is_empty());
+
+    let verbose = run(&["-v"]);
+    let trace = String::from_utf8(verbose.stderr).unwrap();
+    assert!(trace.contains("symbols:\n  label end 0x0001\n"));
+    assert!(trace.contains("bytecode: 2 bytes"));
+    assert_eq!(verbose.stdout, quiet.stdout);
+}
+
+#[test]
+fn verify_round_trips_the_bytecode() {
+    assert_eq!(
+        easm(&["--verify"], "start: push2 start jump"),
+        "5b61000056\n"
+    );
+
+    // Bytes that are not opcodes, and a PUSH cut off by the end, go back as data
+    assert_eq!(easm(&["--verify"], "push1 0x80 .data 0x0c"), "60800c\n");
+    assert_eq!(
+        easm(&["asm", "--verify"], "push1 0x01 stop .data 0c0d"),
+        "6001000c0d\n"
+    );
+    assert_eq!(easm(&["asm", "--verify"], "stop .data 0x6101"), "006101\n");
+    assert_eq!(
+        easm(
+            &[
+                "asm",
+                "--verify",
+                "--opcodes",
+                "tests/fixtures/opcodes/extra.toml"
+            ],
+            "foo bar stop"
+        ),
+        "f90c00\n"
+    );
+}
+
+#[test]
+fn symbols_are_sorted_by_offset() {
+    assert_eq!(
+        easm(&["--symbols"], "#define SIZE 0x20\nstart: push1 SIZE end: jump middle:\nback:"),
+        "label start 0x0000\nlabel end 0x0003\nlabel middle 0x0005\nlabel back 0x0006\nconstant SIZE 0x20\n"
+    );
+}
+
+#[test]
+fn sourcemap_records_every_instruction() {
+    let path = std::env::temp_dir().join(format!("easm-sourcemap-{}.map", std::process::id()));
+
+    assert_eq!(
+        easm(
+            &["--sourcemap", path.to_str().unwrap()],
+            "push1 0x80\n  mstore .data 0x0102"
+        ),
+        "6080520102\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[\n  {\"start\": 0, \"length\": 2, \"line\": 1, \"col\": 1},\n  \
+         {\"start\": 2, \"length\": 1, \"line\": 2, \"col\": 3},\n  \
+         {\"start\": 3, \"length\": 2, \"line\": 2, \"col\": 10}\n]"
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn deploy_wraps_the_runtime() {
+    assert_eq!(
+        easm(&["--deploy"], "push1 0x2a stop"),
+        "600380600b6000396000f3602a00\n"
+    );
+}
+
+#[test]
+fn subcommands() {
+    assert_eq!(easm(&["asm", "-x"], "push1 0x80"), "0x6080\n");
+    assert_eq!(
+        easm(&["analyze"], "push1 0x80 pop"),
+        "size: 3 bytes\ninstructions: 2\n  POP    1\n  PUSH1  1\n"
+    );
+    assert_eq!(easm(&["analyze", "--gas"], "push1 0x80 pop"), "5\n");
+    assert_eq!(easm(&["disasm", "0x6080"], ""), "PUSH1 0x80\n");
+    assert_eq!(
+        easm(&["disasm", "--annotate", "0x6001600200"], ""),
+        "60(PUSH1) 01 60(PUSH1) 02 00(STOP)\n"
+    );
+    assert_eq!(easm(&["--decode-annotate", "0x600c"], ""), "60(PUSH1) 0c\n");
+    assert_eq!(
+        easm(&["disasm", "--base", "0x100", "0x608000"], ""),
+        "0x0100  PUSH1 0x80\n0x0102  STOP\n"
+    );
+    assert_eq!(
+        easm(&["disasm", "--base", "16", "0x00"], ""),
+        "0x0010  STOP\n"
+    );
+
+    // The flags of one subcommand are rejected by the others
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--gas"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("--gas is not an option of asm"));
+}
+
+#[test]
+fn stack_limit_is_an_error() {
+    assert!(easm(&["analyze", "--stack"], "caller caller pop").ends_with("max stack height: 2\n"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["analyze", "--stack"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all("caller ".repeat(1025).as_bytes())?;
+            child.wait_with_output()
+        })
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("1024"));
+}
+
+#[test]
+fn cfg_lists_the_jumps_to_every_jumpdest() {
+    assert_eq!(
+        easm(
+            &["analyze", "--cfg"],
+            "start: push1 start jump\ncaller jump\njumpdest push1 0x00 jumpi"
+        ),
+        "0x0000 <- 0x0003, 0x0009\n0x0006 <-\n<dynamic> <- 0x0005\n"
+    );
+}
+
+#[test]
+fn check_reports_every_problem() {
+    let check = |source: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(["analyze", "--check"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child.stdin.take().unwrap().write_all(source.as_bytes())?;
+                child.wait_with_output()
+            })
+            .unwrap();
+
+        assert!(output.stdout.is_empty());
+
+        (
+            output.status.success(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    assert_eq!(
+        check("push1 0x01 push1 0x02 add pop"),
+        (true, String::new())
+    );
+
+    // Warnings alone pass
+    let (success, stderr) = check("push1 0x02 jump\nstop caller");
+    assert!(success);
+    assert_eq!(stderr.lines().count(), 2);
+    assert!(stderr.lines().all(|line| line.starts_with("warning: ")));
+
+    let (success, stderr) = check("dup2\nstop caller");
+    assert!(!success);
+    assert!(stderr.contains("error: DUP2 on line 1"));
+    assert!(stderr.contains("warning: CALLER on line 2 is unreachable"));
+
+    let (success, stderr) = check("push1 nowhere jump");
+    assert!(!success);
+    assert!(stderr.starts_with("error: "));
+}
+
+#[test]
+fn max_size_fails_over_the_limit() {
+    let assemble = |size: usize, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                let source = format!(".data 0x{}", "00".repeat(size));
+                child.stdin.take().unwrap().write_all(source.as_bytes())?;
+                child.wait_with_output()
+            })
+            .unwrap();
+
+        (
+            output.status.success(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    assert_eq!(
+        assemble(24576, &["asm", "--max-size"]),
+        (true, String::new())
+    );
+
+    let (success, stderr) = assemble(24577, &["asm"]);
+    assert!(success);
+    assert!(stderr.starts_with("warning: the runtime code is 24577 bytes"));
+
+    let (success, stderr) = assemble(24577, &["asm", "--max-size"]);
+    assert!(!success);
+    assert!(stderr.contains("EIP-170"));
+}
+
+#[test]
+fn defines_from_the_command_line() {
+    let source = "#ifdef DEBUG\ncaller\n#endif\nstop";
+
+    assert_eq!(easm(&["asm"], source), "00\n");
+    assert_eq!(easm(&["asm", "--define", "DEBUG"], source), "3300\n");
+
+    // The command line wins over a #define of the same name, in the source or included
+    let source = "#define SIZE 0x20\npush1 SIZE";
+    assert_eq!(easm(&["asm"], source), "6020\n");
+    assert_eq!(easm(&["asm", "--define", "SIZE"], source), "6001\n");
+
+    let source = "#ifdef DEBUG\n#define DEBUG 0x02\n#endif\npush1 DEBUG";
+    assert_eq!(easm(&["asm", "--define", "DEBUG"], source), "6001\n");
+}
+
+#[test]
+fn auto_stop_ends_the_code() {
+    assert_eq!(easm(&["asm"], "caller"), "33\n");
+    assert_eq!(easm(&["asm", "--auto-stop"], "caller"), "3300\n");
+    assert_eq!(easm(&["asm", "--auto-stop"], "caller revert"), "33fd\n");
+    assert_eq!(easm(&["asm", "--auto-stop"], "stop .data 0xab"), "00ab\n");
+    assert_eq!(
+        easm(&["asm", "--auto-stop"], "caller stop jumpdest"),
+        "33005b00\n"
+    );
+}
+
+#[test]
+fn code_from_the_command_line() {
+    assert_eq!(
+        easm(&["asm", "--code", "push1 0x01 push1 0x02 add"], ""),
+        "6001600201\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--code", "stop", "program.easm"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("--code and FILE can not be used together"));
+}
+
+#[test]
+fn metadata_trailer() {
+    let hash = format!("1220{}", "ab".repeat(32));
+    let output = easm(&["asm", "--metadata", "--metadata-hash", &hash], "stop");
+
+    // STOP, the CBOR map of the hash and its length of 0x2a bytes
+    assert_eq!(output, format!("00a164697066735822{}002a\n", hash));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--metadata", "--code", "stop"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("--metadata and --metadata-hash must be used together"));
+}
+
+#[test]
+fn objects_are_emitted_and_linked() {
+    let dir = std::env::temp_dir();
+    let first = dir.join(format!("easm-link-{}-a.o", std::process::id()));
+    let second = dir.join(format!("easm-link-{}-b.o", std::process::id()));
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    easm(
+        &["asm", "--emit-obj", "-o", first],
+        "main: push2 helper jump",
+    );
+    easm(
+        &["asm", "--emit-obj", "-o", second],
+        "helper: push2 main jump",
+    );
+    assert_eq!(
+        std::fs::read_to_string(first).unwrap(),
+        "easm object\ncode 5b61000056\nsymbol main 0x0000\nrelocation 0x0002 2 helper\n"
+    );
+
+    assert_eq!(easm(&["link", first, second], ""), "5b610005565b61000056\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["link", first])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("label helper used in object 1 is not defined"));
+
+    std::fs::remove_file(first).unwrap();
+    std::fs::remove_file(second).unwrap();
+}
+
+#[test]
+fn little_endian_operands() {
+    assert_eq!(easm(&["asm"], "push2 258"), "610102\n");
+    assert_eq!(
+        easm(&["asm", "--operand-endian", "big"], "push2 258"),
+        "610102\n"
+    );
+    assert_eq!(
+        easm(&["asm", "--operand-endian", "little"], "push2 258"),
+        "610201\n"
+    );
+
+    // Hex is written as typed and a bare PUSH keeps the zero byte that moves to the front
+    assert_eq!(
+        easm(
+            &["asm", "--operand-endian", "little"],
+            "push2 0x0102 push 256"
+        ),
+        "610102610001\n"
+    );
+}
+
+#[test]
+fn diagnostics_as_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["analyze", "--diagnostics-json", "--fork", "london"])
+        .arg("tests/fixtures/diagnostics.easm")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[\n  \
+         {\"severity\": \"error\", \"message\": \"Error when lexing on line 2 with description PUSH0 needs the shanghai fork but the target is london\", \"line\": 2, \"col\": null, \"code\": \"Lex\"},\n  \
+         {\"severity\": \"warning\", \"message\": \"CALLER on line 4 is unreachable, it follows STOP with no JUMPDEST in between\", \"line\": 4, \"col\": 3, \"code\": \"Unreachable\"},\n  \
+         {\"severity\": \"warning\", \"message\": \"JUMP on line 2 goes to 0x0003 which is not a JUMPDEST\", \"line\": 2, \"col\": 18, \"code\": \"BadJump\"}\n]\n"
+    );
+
+    assert_eq!(
+        easm(&["analyze", "--diagnostics-json"], "stop\n  pish1"),
+        "[\n  {\"severity\": \"error\", \"message\": \"Error when lexing on line 2 with description \
+         pish1 is neither a known opcode nor a valid literal\\n  pish1\\n  ^^^^^\", \"line\": 2, \
+         \"col\": 3, \"code\": \"UnknownToken\"}\n]\n"
+    );
+
+    // Errors of the parser and the resolver have their line too
+    let parse = easm(&["analyze", "--diagnostics-json"], "stop\n  push1");
+    assert!(parse.contains(
+        "\"message\": \"Error when parsing with description PUSH1 on line 2 expects an operand\", \
+         \"line\": 2, \"col\": 3, \"code\": \"Parse\""
+    ));
+
+    let resolve = easm(&["analyze", "--diagnostics-json"], "push1 0x01\n.org 0x00");
+    assert!(resolve.contains("\"line\": 2, \"col\": null, \"code\": \"Parse\""));
+}
+
+#[test]
+fn hex_output_in_rows() {
+    let source = "push1 0x01 push1 0x02 add push1 0x00 mstore";
+
+    assert_eq!(
+        easm(&["asm", "--columns", "4"], source),
+        "0000  60016002\n0004  01600052\n"
+    );
+    assert_eq!(
+        easm(&["asm", "--columns", "3", "-x"], source),
+        "0000  0x600160\n0003  0x020160\n0006  0x0052\n"
+    );
+
+    let path = std::env::temp_dir().join(format!("easm-columns-{}.hex", std::process::id()));
+    easm(
+        &["asm", "--columns", "5", "-o", path.to_str().unwrap()],
+        source,
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "0000  6001600201\n0005  600052"
+    );
+    std::fs::remove_file(path).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--columns", "4", "--format", "bin", "--code", "stop"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn truncated_push_is_reported() {
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["disasm", "0x7f010203"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "PUSH32 <incomplete: 3 of 32 bytes>\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: the bytecode ends at 0x0004 in the middle of the PUSH32 at 0x0000, it has 3 of its 32 bytes\n"
+    );
+}
+
+#[test]
+fn strict_fails_on_warnings() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .args(["--code", "push1 0x02 jump\nstop caller"])
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["asm"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6002560033\n");
+
+    let output = run(&["asm", "--strict"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: CALLER on line 2 is unreachable"));
+    assert!(stderr.ends_with("the warnings are errors with --strict\n"));
+
+    assert!(run(&["analyze", "--check"]).status.success());
+    assert!(!run(&["analyze", "--check", "--strict"]).status.success());
+}
+
+#[test]
+fn entry_checks_the_start_of_the_code() {
+    let run = |entry: &str| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args([
+                "asm",
+                "--entry",
+                entry,
+                "--code",
+                "push1 0x80 push1 0x40 mstore",
+            ])
+            .output()
+            .unwrap()
+    };
+
+    let output = run("0x6080");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6080604052\n");
+
+    let output = run("6080604052");
+    assert!(output.status.success());
+
+    let output = run("0x6080604060");
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "the code starts with 0x6080604052 instead of the --entry 0x6080604060\n"
+    );
+
+    assert!(!run("0x60806040520000").status.success());
+    assert!(!run("0xzz").status.success());
+}
+
+#[test]
+fn lint_and_strict_flag_wide_pushes() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .args(["--code", "push4 0x00000001"])
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["asm"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = run(&["asm", "--lint"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: PUSH4 on line 1 pushes 0x00000001 which fits in PUSH1\n\
+         warning: the code ends on line 1 with 1 items on the stack, as far as it can be told \
+         without following jumps\n"
+    );
+
+    assert!(!run(&["asm", "--strict"]).status.success());
+    assert!(!run(&["analyze", "--check", "--strict"]).status.success());
+}
+
+#[test]
+fn empty_programs_are_flagged() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .args(["--code", "; nothing but a comment\n"])
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["asm"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\n");
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("warning: the program assembles to no bytecode"));
+
+    let output = run(&["asm", "--require-nonempty"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    assert!(!run(&["asm", "--strict"]).status.success());
+}
+
+#[test]
+fn max_ops_limits_the_program() {
+    assert_eq!(
+        easm(&["asm", "--max-ops", "3"], "push1 0x01 stop"),
+        "600100\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--max-ops", "2", "--code", "push1 0x01 stop"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("more than the limit of 2 tokens"));
+}
+
+#[test]
+fn output_case_sets_the_case_of_the_hex() {
+    assert_eq!(
+        easm(&["asm", "--output-case", "upper"], "push1 0xAB"),
+        "60AB\n"
+    );
+    assert_eq!(
+        easm(&["asm", "--output-case", "upper", "-x"], "push1 0xab"),
+        "0x60AB\n"
+    );
+    assert_eq!(
+        easm(&["asm", "--output-case", "lower"], "push1 0xAB"),
+        "60ab\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--output-case", "title", "--code", "stop"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn include_path_finds_shared_files() {
+    assert_eq!(
+        easm(
+            &["asm", "--include-path", "tests/fixtures/prelude"],
+            "#include \"answer.easm\"\nstop"
+        ),
+        "602a00\n"
+    );
+}
+
+#[test]
+fn count_only_prints_the_size() {
+    let source = "push2 0x0102 .data 0xbeef\nstart: push start jump";
+    let hex = easm(&["asm"], source);
+
+    assert_eq!(
+        easm(&["asm", "--count-only"], source),
+        format!("{}\n", hex.trim_end().len() / 2)
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--count-only", "--deploy", "--code", "stop"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn opcodes_table_adds_mnemonics() {
+    let table = "tests/fixtures/opcodes/extra.toml";
+    assert_eq!(
+        easm(&["asm", "--opcodes", table], "foo bar stop"),
+        "f90c00\n"
+    );
+
+    let shadowing = "tests/fixtures/opcodes/shadowing.toml";
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .args(["--code", "add"])
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["asm", "--opcodes", shadowing]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("custom opcode ADD is already the built-in ADD"));
+
+    let output = run(&["asm", "--opcodes", shadowing, "--override"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "f9\n");
+}
+
+#[test]
+fn explain_describes_every_instruction() {
+    assert_eq!(
+        easm(&["analyze", "--explain"], "push1 0x01 push1 0x02 add"),
+        "0x0000  PUSH1 0x01  (0x60) pushes the operand that follows it\n\
+         0x0002  PUSH1 0x02  (0x60) pushes the operand that follows it\n\
+         0x0004  ADD         (0x01) pops a, b; pushes (a + b) mod 2^256\n"
+    );
+}
+
+#[test]
+fn diff_compares_two_programs() {
+    let diff = |new: &str| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(["analyze", "--diff", "tests/fixtures/diff/old.easm", new])
+            .output()
+            .unwrap()
+    };
+
+    let output = diff("tests/fixtures/diff/new.easm");
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "0x0002  01 -> 02  PUSH1 0x01 -> PUSH1 0x02\n"
+    );
+
+    let output = diff("tests/fixtures/diff/old.easm");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+#[cfg(not(feature = "watch"))]
+fn watch_needs_its_feature() {
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "--watch", "tests/fixtures/arithmetic.easm"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("--watch needs easm built with the watch feature"));
+}
+
+#[test]
+fn manifest_records_the_build() {
+    let path = std::env::temp_dir().join(format!("easm-manifest-{}.json", std::process::id()));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args(["asm", "tests/fixtures/manifest/store.easm", "--manifest"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "5b602a600055600056\n"
+    );
+
+    let manifest = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        manifest.contains("{\"file\": \"tests/fixtures/manifest/store.easm\", \"keccak256\": \"0x")
+    );
+    assert!(manifest.contains("\"fork\": \"cancun\""));
+    assert!(manifest.contains("\"length\": 9"));
+    assert!(manifest.contains("\"labels\": {\"start\": 0}"));
+    assert!(manifest.contains("\"constants\": {\"SLOT\": \"0x00\"}"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn sections_are_linted_and_stopped_on_their_own() {
+    let asm = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args([
+                "asm",
+                "--code",
+                ".init { stop push1 0x01 } .runtime { push1 0x02 }",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = asm(&[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("PUSH1 on line 1 is unreachable"));
+
+    assert!(!asm(&["--strict"]).status.success());
+
+    // Only the runtime gets the STOP, the init code runs on into the code that returns it
+    let output = asm(&["--auto-stop"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "006001600380600e6000396000f3600200\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+        .args([
+            "analyze",
+            "--diff",
+            "tests/fixtures/sections.easm",
+            "tests/fixtures/sections.easm",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn strict_fails_on_the_stack_check() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .args(["--code", "dup3 pop stop"])
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["asm"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "825000\n");
+
+    let output = run(&["asm", "--strict"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("error: DUP3 on line 1 reaches 3 items deep but the stack has 0"));
+}
+
+#[test]
+fn explain_a_single_opcode() {
+    let explain = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_easm"))
+            .args(args)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let add = "ADD (0x01): pops a, b; pushes (a + b) mod 2^256\n";
+    assert_eq!(explain(&["--explain", "add"]), add);
+    assert_eq!(explain(&["analyze", "--explain", "ADD"]), add);
+
+    // A file is still explained as a program
+    let listing = explain(&["analyze", "--explain", "tests/fixtures/arithmetic.easm"]);
+    assert!(listing.starts_with("0x0000  PUSH1 0x02  (0x60)"));
+}
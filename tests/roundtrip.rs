@@ -0,0 +1,72 @@
+// Randomized round trips of instructions through the assembler and the disassembler,
+// with a fixed seed so a failure can be reproduced
+
+use easm::{Instruction, Op};
+
+/// xorshift64, enough to spread the cases over the opcode table
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// A random valid instruction, PUSHes get an operand of their width
+fn instruction(random: &mut Random) -> Instruction {
+    let op = loop {
+        if let Some(op) = Op::from_byte(random.below(256) as u8) {
+            break op;
+        }
+    };
+
+    let operand = op
+        .is_push()
+        .filter(|&size| size > 0)
+        .map(|size| (0..size).map(|_| random.below(256) as u8).collect());
+
+    Instruction {
+        opcode: op.opcode(),
+        op,
+        operand,
+        line: 1,
+        column: 0,
+        len: 0,
+    }
+}
+
+#[test]
+fn instructions_round_trip_through_the_disassembler() {
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..2000 {
+        let program: Vec<Instruction> = (0..random.below(40))
+            .map(|_| instruction(&mut random))
+            .collect();
+
+        let bytecode = easm::assemble_ops(&program).unwrap();
+        let disassembly = easm::disassemble(&bytecode);
+
+        let tokens = easm::tokenize(&disassembly).unwrap();
+        let parsed = easm::Parser::new(&tokens).parse().unwrap();
+
+        // Lines and columns are those of the disassembly
+        let parts = |instructions: &[Instruction]| {
+            instructions
+                .iter()
+                .map(|instruction| (instruction.op.clone(), instruction.operand.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(parts(&parsed), parts(&program), "{}", disassembly);
+        assert_eq!(easm::bytecode(&parsed), bytecode);
+    }
+}